@@ -0,0 +1,182 @@
+//! Rotation of the execution log into `logs-archive/`. Once
+//! `execution-logs.json` outgrows `log_archive_bytes` it is moved there as
+//! `logs-<timestamp>.json` and a fresh log starts, so history is kept rather
+//! than trimmed. Only the current log is held in memory.
+
+use crate::{
+  read_json, usage, write_json, writable_data_dir, ActionLogSummary, AppState, ExecutionLogEntry,
+  JsonFormat, Preferences,
+};
+use chrono::DateTime;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tracing::{info, instrument, warn};
+
+pub fn archive_dir(handle: &AppHandle) -> Result<PathBuf, String> {
+  Ok(writable_data_dir(handle)?.join("logs-archive"))
+}
+
+/// Archives, oldest first; the timestamp in the name sorts chronologically.
+fn archives(dir: &Path) -> Result<Vec<PathBuf>, String> {
+  if !dir.exists() {
+    return Ok(Vec::new());
+  }
+  let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+    .map_err(|error| format!("Failed to read the log archive: {error}"))?
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| {
+      path.file_name().and_then(|name| name.to_str()).is_some_and(|name| {
+        name.starts_with("logs-") && name.ends_with(".json")
+      })
+    })
+    .collect();
+  paths.sort();
+  Ok(paths)
+}
+
+/// Move the just-written log at `path` into the archive if it outgrew the
+/// threshold, emptying `logs`, then drop archives beyond `log_archive_count`.
+pub fn rotate_if_needed(
+  handle: &AppHandle,
+  preferences: &Preferences,
+  path: &Path,
+  logs: &mut Vec<ExecutionLogEntry>,
+  format: JsonFormat,
+) -> Result<(), String> {
+  let size = fs::metadata(path)
+    .map_err(|error| format!("Failed to read the size of {}: {error}", path.display()))?
+    .len();
+  if size <= preferences.log_archive_bytes {
+    return Ok(());
+  }
+
+  let dir = archive_dir(handle)?;
+  fs::create_dir_all(&dir)
+    .map_err(|error| format!("Failed to create the log archive: {error}"))?;
+  let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%3fZ");
+  let archived = dir.join(format!("logs-{stamp}.json"));
+  fs::rename(path, &archived)
+    .map_err(|error| format!("Failed to archive the execution log: {error}"))?;
+  info!(entries = logs.len(), size, archive = %archived.display(), "archived execution log");
+  logs.clear();
+  write_json(path, &*logs, format)?;
+
+  if preferences.log_archive_count > 0 {
+    let archives = archives(&dir)?;
+    let excess = archives.len().saturating_sub(preferences.log_archive_count);
+    for old in &archives[..excess] {
+      if let Err(error) = fs::remove_file(old) {
+        warn!(%error, archive = %old.display(), "failed to delete old log archive");
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Result of `query_log_archives`.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LogHistoryStats {
+  total_runs: usize,
+  successful_runs: usize,
+  failed_runs: usize,
+  /// Share of successful runs, from 0 to 1.
+  success_rate: f64,
+  average_duration_ms: f64,
+  first_run_at: Option<String>,
+  last_run_at: Option<String>,
+  by_action: HashMap<String, ActionLogSummary>,
+  /// Archive files read, not counting the current log.
+  archives: usize,
+  /// Archive files that couldn't be read and were skipped.
+  unreadable_archives: usize,
+}
+
+/// Totals over every archive and the current log, optionally limited to one
+/// action and to runs between `since` and `until` (RFC 3339, inclusive).
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub async fn query_log_archives(
+  handle: AppHandle,
+  action_id: Option<String>,
+  since: Option<String>,
+  until: Option<String>,
+) -> Result<LogHistoryStats, String> {
+  let parse = |bound: Option<String>| {
+    bound
+      .map(|bound| {
+        DateTime::parse_from_rfc3339(&bound)
+          .map_err(|error| format!("Invalid date {bound}: {error}"))
+      })
+      .transpose()
+  };
+  let since = parse(since)?;
+  let until = parse(until)?;
+  let in_range = |entry: &ExecutionLogEntry| {
+    let Ok(timestamp) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+      return since.is_none() && until.is_none();
+    };
+    since.map_or(true, |since| timestamp >= since)
+      && until.map_or(true, |until| timestamp <= until)
+  };
+  let wanted = |entry: &ExecutionLogEntry| {
+    action_id.as_ref().map_or(true, |id| &entry.action_id == id) && in_range(entry)
+  };
+
+  let mut stats = LogHistoryStats::default();
+  let mut succeeded_by_action: HashMap<String, usize> = HashMap::new();
+  let mut total_duration_ms = 0.0;
+  let mut count = |entry: &ExecutionLogEntry, stats: &mut LogHistoryStats| {
+    stats.total_runs += 1;
+    if entry.success {
+      stats.successful_runs += 1;
+      *succeeded_by_action.entry(entry.action_id.clone()).or_default() += 1;
+    }
+    total_duration_ms += entry.duration_ms;
+    if stats.first_run_at.is_none() {
+      stats.first_run_at = Some(entry.timestamp.clone());
+    }
+    stats.last_run_at = Some(entry.timestamp.clone());
+    let summary = stats.by_action.entry(entry.action_id.clone()).or_default();
+    summary.count += 1;
+    summary.last_run_at = Some(entry.timestamp.clone());
+  };
+
+  for path in archives(&archive_dir(&handle)?)? {
+    match read_json::<Vec<ExecutionLogEntry>>(&path) {
+      Ok(entries) => {
+        stats.archives += 1;
+        for entry in entries.unwrap_or_default().iter().filter(|entry| wanted(entry)) {
+          count(entry, &mut stats);
+        }
+      }
+      Err(error) => {
+        warn!(%error, "skipping unreadable log archive");
+        stats.unreadable_archives += 1;
+      }
+    }
+  }
+  {
+    let state = handle.state::<AppState>();
+    let logs = state.logs.lock().map_err(|_| "Failed to lock log state".to_string())?;
+    for entry in logs.iter().filter(|entry| wanted(entry)) {
+      count(entry, &mut stats);
+    }
+  }
+
+  stats.failed_runs = stats.total_runs - stats.successful_runs;
+  if stats.total_runs > 0 {
+    stats.success_rate = stats.successful_runs as f64 / stats.total_runs as f64;
+    stats.average_duration_ms = total_duration_ms / stats.total_runs as f64;
+  }
+  let run_counts = usage::run_counts(&handle)?;
+  for (action_id, summary) in &mut stats.by_action {
+    let succeeded = succeeded_by_action.get(action_id).copied().unwrap_or(0);
+    summary.success_rate = succeeded as f64 / summary.count as f64;
+    summary.run_count = run_counts.get(action_id).copied();
+  }
+  Ok(stats)
+}
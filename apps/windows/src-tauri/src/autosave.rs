@@ -0,0 +1,83 @@
+//! Debounced setup saves for edits made as the user types. Each edit
+//! replaces the pending setup, and only the last one of a burst is written,
+//! a moment after the edits stop. Quitting, switching profiles and loading
+//! setup write a pending save first.
+
+use crate::{write_setup, AppState, SetupFile, SetupPayload};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tracing::{debug, instrument, warn};
+
+const SAVE_DELAY: Duration = Duration::from_millis(750);
+
+#[derive(Default)]
+pub struct Saver {
+  /// The latest edit, not written yet.
+  pending: Mutex<Option<SetupPayload>>,
+  /// Bumped by every edit; a scheduled save only goes ahead if it is still
+  /// the latest.
+  generation: AtomicU64,
+}
+
+impl Saver {
+  pub fn has_pending(&self) -> bool {
+    self.pending.lock().is_ok_and(|pending| pending.is_some())
+  }
+}
+
+/// Like `save_setup`, but written a moment later and coalesced with the
+/// edits that follow. Setup is validated right away so mistakes still come
+/// back from the call; a failed write is reported with `setup-save-failed`.
+#[tauri::command]
+#[instrument(skip_all, err)]
+pub fn save_setup_debounced(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  setup: SetupPayload,
+) -> Result<(), String> {
+  SetupFile::from(setup.clone()).validate()?;
+  *state
+    .autosave
+    .pending
+    .lock()
+    .map_err(|_| "Failed to lock autosave state".to_string())? = Some(setup);
+
+  let generation = state.autosave.generation.fetch_add(1, Ordering::SeqCst) + 1;
+  thread::spawn(move || {
+    thread::sleep(SAVE_DELAY);
+    if handle.state::<AppState>().autosave.generation.load(Ordering::SeqCst) == generation {
+      if let Err(error) = flush(&handle) {
+        warn!(%error, "failed to auto-save setup");
+        let _ = handle.emit_all("setup-save-failed", error);
+      }
+    }
+  });
+  Ok(())
+}
+
+/// Write the pending edit now, if there is one.
+pub fn flush(handle: &AppHandle) -> Result<(), String> {
+  let state = handle.state::<AppState>();
+  let Some(setup) = state
+    .autosave
+    .pending
+    .lock()
+    .map_err(|_| "Failed to lock autosave state".to_string())?
+    .take()
+  else {
+    return Ok(());
+  };
+  debug!("writing auto-saved setup");
+  write_setup(handle, &state, setup)
+}
+
+/// Drop the pending edit, e.g. when a direct save supersedes it.
+pub fn discard(state: &AppState) {
+  if let Ok(mut pending) = state.autosave.pending.lock() {
+    pending.take();
+  }
+  state.autosave.generation.fetch_add(1, Ordering::SeqCst);
+}
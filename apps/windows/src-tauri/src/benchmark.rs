@@ -0,0 +1,150 @@
+//! Timing of the simulated copy/paste round trip, to tune capture delays.
+
+use crate::input::send_modified_key;
+use crate::{clipboard_watch, current_preferences, Preferences, CAPTURE_SENTINEL};
+use arboard::Clipboard;
+use serde::Serialize;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tracing::{info, instrument};
+
+/// Give up on a single step after this long; the field probably lost focus.
+const STEP_TIMEOUT: Duration = Duration::from_secs(2);
+const DEFAULT_ITERATIONS: u32 = 5;
+const MAX_ITERATIONS: u32 = 20;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingStats {
+  pub min_ms: f64,
+  pub avg_ms: f64,
+  pub max_ms: f64,
+}
+
+impl TimingStats {
+  fn from_samples(samples: &[f64]) -> Option<Self> {
+    if samples.is_empty() {
+      return None;
+    }
+    Some(Self {
+      min_ms: samples.iter().copied().fold(f64::INFINITY, f64::min),
+      avg_ms: samples.iter().sum::<f64>() / samples.len() as f64,
+      max_ms: samples.iter().copied().fold(0.0, f64::max),
+    })
+  }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundtripBenchmark {
+  pub iterations: u32,
+  /// Iterations where the pasted text never came back.
+  pub failures: u32,
+  /// Ctrl+C until the clipboard changed.
+  pub capture: Option<TimingStats>,
+  /// Ctrl+V until a copy of the field returned the sample text.
+  pub roundtrip: Option<TimingStats>,
+}
+
+/// Copy the focused field until the clipboard holds `expected`, returning
+/// how long the last, successful copy took to land.
+fn copy_until(
+  board: &mut Clipboard,
+  preferences: &Preferences,
+  expected: &str,
+  deadline: Instant,
+) -> Result<Option<f64>, String> {
+  while Instant::now() < deadline {
+    let _ = board.set_text(CAPTURE_SENTINEL);
+    send_modified_key(preferences, 'a')?;
+    let copied_at = Instant::now();
+    send_modified_key(preferences, 'c')?;
+
+    loop {
+      match board.get_text() {
+        Ok(text) if text != CAPTURE_SENTINEL => {
+          if text == expected {
+            return Ok(Some(copied_at.elapsed().as_secs_f64() * 1000.0));
+          }
+          // Copied before the paste landed; try again.
+          break;
+        }
+        _ if Instant::now() >= deadline => return Ok(None),
+        _ => thread::sleep(Duration::from_millis(5)),
+      }
+    }
+  }
+  Ok(None)
+}
+
+/// Paste `sample_text` into the focused text field and copy it back, several
+/// times. The field's content is replaced; the clipboard is restored.
+fn run_benchmark(
+  preferences: &Preferences,
+  sample_text: &str,
+  iterations: u32,
+) -> Result<RoundtripBenchmark, String> {
+  let _own_write = clipboard_watch::own_write();
+  let mut board =
+    Clipboard::new().map_err(|error| format!("Clipboard access failed: {error}"))?;
+  let previous = board.get_text().unwrap_or_default();
+
+  let mut capture = Vec::new();
+  let mut roundtrip = Vec::new();
+  let mut measure = |board: &mut Clipboard| -> Result<(), String> {
+    for _ in 0..iterations {
+      board
+        .set_text(sample_text)
+        .map_err(|error| format!("Clipboard write failed: {error}"))?;
+      send_modified_key(preferences, 'a')?;
+      let pasted_at = Instant::now();
+      send_modified_key(preferences, 'v')?;
+
+      if let Some(capture_ms) =
+        copy_until(board, preferences, sample_text, pasted_at + STEP_TIMEOUT)?
+      {
+        capture.push(capture_ms);
+        roundtrip.push(pasted_at.elapsed().as_secs_f64() * 1000.0);
+      }
+    }
+    Ok(())
+  };
+  let measured = measure(&mut board);
+
+  let _ = board.set_text(&previous);
+  measured?;
+
+  Ok(RoundtripBenchmark {
+    iterations,
+    failures: iterations - capture.len() as u32,
+    capture: TimingStats::from_samples(&capture),
+    roundtrip: TimingStats::from_samples(&roundtrip),
+  })
+}
+
+/// Measure capture and paste latency against whichever text field has focus,
+/// so the capture wait can be set from data instead of guesswork.
+#[tauri::command]
+#[instrument(skip(handle, sample_text), err)]
+pub async fn benchmark_roundtrip(
+  handle: AppHandle,
+  sample_text: String,
+  iterations: Option<u32>,
+) -> Result<RoundtripBenchmark, String> {
+  if sample_text.is_empty() {
+    return Err("Sample text cannot be empty".to_string());
+  }
+  let iterations = iterations.unwrap_or(DEFAULT_ITERATIONS).clamp(1, MAX_ITERATIONS);
+  let preferences = current_preferences(&handle);
+
+  // Keystroke simulation blocks; keep it off the async runtime's workers.
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    run_benchmark(&preferences, &sample_text, iterations)
+  })
+  .await
+  .map_err(|error| format!("Benchmark failed: {error}"))??;
+
+  info!(failures = result.failures, capture = ?result.capture, "round trip benchmark finished");
+  Ok(result)
+}
@@ -0,0 +1,77 @@
+//! In-memory LRU of recent outputs, for actions that opt into caching.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Key {
+  action_id: String,
+  input_hash: u64,
+  model: String,
+}
+
+impl Key {
+  /// The prompt is hashed along with the input, so editing an action stops
+  /// its old outputs from being served.
+  pub fn new(action_id: &str, model: &str, system_prompt: &str, input: &str) -> Self {
+    let mut hasher = DefaultHasher::new();
+    (system_prompt, input).hash(&mut hasher);
+    Self {
+      action_id: action_id.to_string(),
+      input_hash: hasher.finish(),
+      model: model.to_string(),
+    }
+  }
+}
+
+struct Entry {
+  key: Key,
+  output: String,
+  stored_at: Instant,
+}
+
+/// Sizes are small, so a list scanned front to back is enough. The most
+/// recently used entry is at the back.
+#[derive(Default)]
+pub struct ResponseCache {
+  entries: VecDeque<Entry>,
+}
+
+impl ResponseCache {
+  /// The output stored for `key`, unless it is older than `ttl`.
+  pub fn get(&mut self, key: &Key, ttl: Duration) -> Option<String> {
+    self.entries.retain(|entry| entry.stored_at.elapsed() < ttl);
+    let index = self.entries.iter().position(|entry| &entry.key == key)?;
+    let entry = self.entries.remove(index)?;
+    let output = entry.output.clone();
+    self.entries.push_back(entry);
+    Some(output)
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Bytes of the stored outputs, which make up most of the cache's size.
+  pub fn output_bytes(&self) -> usize {
+    self.entries.iter().map(|entry| entry.output.len()).sum()
+  }
+
+  /// Store `output`, evicting the least recently used entries past `capacity`.
+  pub fn insert(&mut self, key: Key, output: String, capacity: usize) {
+    self.entries.retain(|entry| entry.key != key);
+    if capacity == 0 {
+      return;
+    }
+    while self.entries.len() >= capacity {
+      self.entries.pop_front();
+    }
+    self.entries.push_back(Entry {
+      key,
+      output,
+      stored_at: Instant::now(),
+    });
+  }
+}
@@ -0,0 +1,247 @@
+//! Cheap, best-effort guess at what a capture is: a URL, code, prose, and in
+//! which script. Used for analytics in the log and so actions could branch on
+//! it later; nothing depends on it being right.
+
+use serde::{Deserialize, Serialize};
+
+/// Only the start of a long capture is looked at.
+const SAMPLE_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentKind {
+  /// A single web address.
+  Url,
+  /// A single email address.
+  Email,
+  /// Source code, markup or structured data like JSON.
+  Code,
+  /// Sentences in a natural language.
+  Prose,
+  /// Numbers, lists of symbols and anything else.
+  Other,
+}
+
+/// Writing system of most of the letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Script {
+  Latin,
+  Cyrillic,
+  Greek,
+  Arabic,
+  Hebrew,
+  Devanagari,
+  Thai,
+  Hangul,
+  /// Japanese: kana, usually mixed with kanji.
+  Kana,
+  /// Chinese characters without kana.
+  Han,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentClass {
+  pub kind: ContentKind,
+  /// `None` when there are no letters or no script has most of them.
+  pub script: Option<Script>,
+}
+
+/// Classify `text`, or `None` for blank text.
+pub fn classify(text: &str) -> Option<ContentClass> {
+  let sample: String = text.trim().chars().take(SAMPLE_CHARS).collect();
+  if sample.is_empty() {
+    return None;
+  }
+  Some(ContentClass { kind: kind(&sample), script: script(&sample) })
+}
+
+/// `classify` for the UI, e.g. on text typed into the capture preview.
+#[tauri::command]
+pub fn classify_text(text: String) -> Option<ContentClass> {
+  classify(&text)
+}
+
+fn kind(sample: &str) -> ContentKind {
+  if !sample.contains(char::is_whitespace) {
+    if is_url(sample) {
+      return ContentKind::Url;
+    }
+    if is_email(sample) {
+      return ContentKind::Email;
+    }
+  }
+  if looks_like_code(sample) {
+    return ContentKind::Code;
+  }
+  let letters = sample.chars().filter(|c| c.is_alphabetic()).count();
+  let visible = sample.chars().filter(|c| !c.is_whitespace()).count();
+  // Scripts without spaces between words still count as prose.
+  if letters * 10 >= visible * 7 && (sample.contains(' ') || script(sample).is_some()) {
+    ContentKind::Prose
+  } else {
+    ContentKind::Other
+  }
+}
+
+fn is_url(token: &str) -> bool {
+  let lower = token.to_ascii_lowercase();
+  let schemes = ["https://", "http://", "ftp://"];
+  if let Some(rest) = schemes.iter().find_map(|scheme| lower.strip_prefix(scheme)) {
+    return !rest.is_empty();
+  }
+  lower.strip_prefix("www.").is_some_and(|rest| rest.contains('.'))
+}
+
+fn is_email(token: &str) -> bool {
+  let Some((local, domain)) = token.split_once('@') else {
+    return false;
+  };
+  !local.is_empty()
+    && !domain.contains('@')
+    && domain.split('.').count() >= 2
+    && domain.split('.').all(|part| !part.is_empty())
+}
+
+/// Most lines look like code, or the text is dense with code punctuation.
+fn looks_like_code(sample: &str) -> bool {
+  let trimmed = sample.trim_start();
+  if (trimmed.starts_with('{') || trimmed.starts_with('[') || trimmed.starts_with('<'))
+    && matches!(sample.trim_end().chars().last(), Some('}' | ']' | '>'))
+  {
+    return true;
+  }
+
+  let lines: Vec<&str> = sample.lines().filter(|line| !line.trim().is_empty()).collect();
+  let code_lines = lines.iter().filter(|line| is_code_line(line)).count();
+  if lines.len() >= 2 && code_lines * 2 >= lines.len() {
+    return true;
+  }
+
+  let visible = sample.chars().filter(|c| !c.is_whitespace()).count().max(1);
+  let symbols = sample.chars().filter(|c| "{}[]();=<>&|".contains(*c)).count();
+  code_lines >= 1 && symbols * 10 >= visible
+}
+
+const CODE_KEYWORDS: &[&str] = &[
+  "fn ", "def ", "class ", "import ", "from ", "#include", "function ", "const ", "let ",
+  "var ", "public ", "private ", "return ", "if (", "for (", "while (", "package ", "using ",
+  "SELECT ", "select ", "pub ", "struct ", "impl ", "#!/",
+];
+
+fn is_code_line(line: &str) -> bool {
+  let trimmed = line.trim();
+  CODE_KEYWORDS.iter().any(|keyword| trimmed.starts_with(keyword))
+    || trimmed.ends_with(';')
+    || trimmed.ends_with('{')
+    || trimmed == "}"
+    || trimmed.starts_with("//")
+    || ["=>", "->", "::", "==", "!=", "&&", "||"].iter().any(|token| trimmed.contains(token))
+}
+
+fn script_of(c: char) -> Option<Script> {
+  let script = match c as u32 {
+    0x0041..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+    0x0400..=0x04FF => Script::Cyrillic,
+    0x0370..=0x03FF => Script::Greek,
+    0x0600..=0x06FF | 0x0750..=0x077F => Script::Arabic,
+    0x0590..=0x05FF => Script::Hebrew,
+    0x0900..=0x097F => Script::Devanagari,
+    0x0E00..=0x0E7F => Script::Thai,
+    0xAC00..=0xD7AF | 0x1100..=0x11FF => Script::Hangul,
+    0x3040..=0x30FF => Script::Kana,
+    0x4E00..=0x9FFF | 0x3400..=0x4DBF => Script::Han,
+    _ => return None,
+  };
+  Some(script)
+}
+
+/// The script of at least half the letters.
+fn script(sample: &str) -> Option<Script> {
+  let mut counts: Vec<(Script, usize)> = Vec::new();
+  let mut letters = 0;
+  for c in sample.chars().filter(|c| c.is_alphabetic()) {
+    letters += 1;
+    let Some(script) = script_of(c) else {
+      continue;
+    };
+    match counts.iter_mut().find(|(known, _)| *known == script) {
+      Some((_, count)) => *count += 1,
+      None => counts.push((script, 1)),
+    }
+  }
+  // Japanese mixes kana and kanji, so kana among Chinese characters means
+  // Japanese rather than Chinese.
+  if counts.iter().any(|(script, _)| *script == Script::Kana) {
+    let han = counts.iter().find(|(script, _)| *script == Script::Han).map_or(0, |(_, n)| *n);
+    counts.retain(|(script, _)| *script != Script::Han);
+    for (script, count) in &mut counts {
+      if *script == Script::Kana {
+        *count += han;
+      }
+    }
+  }
+  let (script, count) = counts.into_iter().max_by_key(|(_, count)| *count)?;
+  (count * 2 >= letters).then_some(script)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn kind_of(text: &str) -> Option<ContentKind> {
+    classify(text).map(|class| class.kind)
+  }
+
+  fn script_of_text(text: &str) -> Option<Script> {
+    classify(text).and_then(|class| class.script)
+  }
+
+  #[test]
+  fn blank_text_is_not_classified() {
+    assert_eq!(classify(""), None);
+    assert_eq!(classify(" \r\n\t"), None);
+  }
+
+  #[test]
+  fn single_addresses_are_urls_or_emails() {
+    assert_eq!(kind_of("https://example.com/a?b=c"), Some(ContentKind::Url));
+    assert_eq!(kind_of("  www.example.org\n"), Some(ContentKind::Url));
+    assert_eq!(kind_of("https://"), Some(ContentKind::Other));
+    assert_eq!(kind_of("someone@example.co.jp"), Some(ContentKind::Email));
+    assert_ne!(kind_of("someone@localhost"), Some(ContentKind::Email));
+    assert_ne!(kind_of("see https://example.com for details"), Some(ContentKind::Url));
+  }
+
+  #[test]
+  fn code_is_told_apart_from_prose() {
+    let rust = "fn main() {\r\n    println!(\"hi\");\r\n}\r\n";
+    assert_eq!(kind_of(rust), Some(ContentKind::Code));
+    assert_eq!(kind_of("{\"key\": [1, 2]}"), Some(ContentKind::Code));
+    let prose = "Thanks for the update. I will review the draft tomorrow morning.";
+    assert_eq!(kind_of(prose), Some(ContentKind::Prose));
+    assert_eq!(kind_of("12,345.67 + 89"), Some(ContentKind::Other));
+  }
+
+  #[test]
+  fn scripts_without_spaces_still_count_as_prose() {
+    let japanese = "今日は天気がいいですね。散歩に行きましょう。";
+    assert_eq!(kind_of(japanese), Some(ContentKind::Prose));
+    assert_eq!(script_of_text(japanese), Some(Script::Kana));
+    assert_eq!(script_of_text("我们明天见面吧"), Some(Script::Han));
+  }
+
+  #[test]
+  fn the_script_is_that_of_most_letters() {
+    assert_eq!(script_of_text("Привет, как дела?"), Some(Script::Cyrillic));
+    assert_eq!(script_of_text("Καλημέρα σας"), Some(Script::Greek));
+    assert_eq!(script_of_text("مرحبا بالعالم"), Some(Script::Arabic));
+    assert_eq!(script_of_text("안녕하세요 여러분"), Some(Script::Hangul));
+    assert_eq!(script_of_text("Crème brûlée à la carte"), Some(Script::Latin));
+    // A script needs at least half the letters.
+    assert!(script_of_text("abc абв").is_some());
+    assert_eq!(script_of_text("ab аб αβ"), None);
+    assert_eq!(script_of_text("12345 !?"), None);
+  }
+}
@@ -0,0 +1,75 @@
+//! Clipboard text reads that survive malformed text from the source app,
+//! and the clipboard's change counter.
+
+use arboard::Clipboard;
+use tracing::warn;
+
+/// Like `Clipboard::get_text`, with two repairs for text some apps write:
+/// embedded NUL characters are dropped, and a buffer that doesn't convert to
+/// UTF-8 (e.g. unpaired UTF-16 surrogates) is re-read and decoded lossily.
+pub fn read_text(board: &mut Clipboard) -> Result<String, arboard::Error> {
+  let text = match board.get_text() {
+    Err(arboard::Error::ConversionFailure) => {
+      let recovered = read_unicode_text_lossy().ok_or(arboard::Error::ConversionFailure)?;
+      warn!(chars = recovered.chars().count(), "clipboard text was malformed, decoded it lossily");
+      recovered
+    }
+    result => result?,
+  };
+
+  if text.contains('\0') {
+    warn!("clipboard text contained NUL characters, dropped them");
+    return Ok(text.replace('\0', ""));
+  }
+  if text.contains(char::REPLACEMENT_CHARACTER) {
+    warn!("clipboard text contains replacement characters; the source app wrote invalid text");
+  }
+  Ok(text)
+}
+
+/// Read `CF_UNICODETEXT` directly, replacing invalid UTF-16 with U+FFFD.
+#[cfg(windows)]
+fn read_unicode_text_lossy() -> Option<String> {
+  use windows_sys::Win32::System::DataExchange::{CloseClipboard, GetClipboardData, OpenClipboard};
+  use windows_sys::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+  const CF_UNICODETEXT: u32 = 13;
+
+  // SAFETY: the clipboard is opened and closed around the read, and the
+  // global memory is only read while locked, within its reported size.
+  unsafe {
+    if OpenClipboard(std::ptr::null_mut()) == 0 {
+      return None;
+    }
+    let data = GetClipboardData(CF_UNICODETEXT);
+    let pointer = if data.is_null() { std::ptr::null() } else { GlobalLock(data) as *const u16 };
+    let text = (!pointer.is_null()).then(|| {
+      let wide = std::slice::from_raw_parts(pointer, GlobalSize(data) / 2);
+      let end = wide.iter().position(|&unit| unit == 0).unwrap_or(wide.len());
+      let text = String::from_utf16_lossy(&wide[..end]);
+      GlobalUnlock(data);
+      text
+    });
+    CloseClipboard();
+    text
+  }
+}
+
+#[cfg(not(windows))]
+fn read_unicode_text_lossy() -> Option<String> {
+  None
+}
+
+/// How many times the clipboard was written since login. It changes with
+/// every copy, even of the same text. `None` where there is no such counter.
+#[cfg(windows)]
+pub fn sequence_number() -> Option<u32> {
+  use windows_sys::Win32::System::DataExchange::GetClipboardSequenceNumber;
+
+  // SAFETY: takes no arguments and only reads a counter.
+  Some(unsafe { GetClipboardSequenceNumber() })
+}
+
+#[cfg(not(windows))]
+pub fn sequence_number() -> Option<u32> {
+  None
+}
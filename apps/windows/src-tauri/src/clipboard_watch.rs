@@ -0,0 +1,210 @@
+//! Experimental: watch the clipboard for newly copied text and offer to run
+//! an action on it. Strictly opt-in with `clipboard_watch_enabled`, since it
+//! reads everything the user copies, and pausable for the session.
+
+use crate::{classify, clipboard, current_preferences, notify, AppState, Preferences};
+use arboard::Clipboard;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tracing::{debug, info, instrument, warn};
+
+/// Accepted range for `Preferences::clipboard_watch_interval_ms`.
+const INTERVAL_MS: std::ops::RangeInclusive<u64> = 250..=60_000;
+/// How often a disabled or paused watcher checks whether it should resume.
+const IDLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Longer copies are not offered; they are rarely meant for an action.
+const MAX_CHARS: usize = 20_000;
+
+/// Clipboard writes ShortcutAI itself has in progress. Captures, pastes and
+/// copies hold this so their text isn't offered back to the user.
+static OWN_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Held while ShortcutAI writes to the clipboard; see `own_write`.
+pub struct OwnWrite(());
+
+impl Drop for OwnWrite {
+  fn drop(&mut self) {
+    OWN_WRITES.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+/// Mark the clipboard as ShortcutAI's until the guard is dropped. Whatever
+/// it holds afterwards becomes the watcher's baseline rather than a change.
+pub fn own_write() -> OwnWrite {
+  OWN_WRITES.fetch_add(1, Ordering::SeqCst);
+  OwnWrite(())
+}
+
+fn writing() -> bool {
+  OWN_WRITES.load(Ordering::SeqCst) > 0
+}
+
+/// Wait up to `timeout` for ShortcutAI's own clipboard use to end, so a
+/// capture can put the user's clipboard back before the app quits. Returns
+/// whether it ended.
+pub fn wait_for_own_writes(timeout: Duration) -> bool {
+  let deadline = Instant::now() + timeout;
+  while writing() {
+    if Instant::now() >= deadline {
+      return false;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  true
+}
+
+#[derive(Default)]
+pub struct Watcher {
+  /// Paused with `set_clipboard_watch_paused`, until resumed or restarted.
+  paused: AtomicBool,
+}
+
+/// Payload of `clipboard-changed`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ClipboardChanged {
+  text: String,
+  content: Option<classify::ContentClass>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardWatchStatus {
+  enabled: bool,
+  paused: bool,
+  interval_ms: u64,
+}
+
+/// Checks run by `save_setup`.
+pub fn validate(preferences: &Preferences) -> Result<(), String> {
+  let interval = preferences.clipboard_watch_interval_ms;
+  if !INTERVAL_MS.contains(&interval) {
+    return Err(format!(
+      "Clipboard watch interval must be between {} and {} ms, got {interval}",
+      INTERVAL_MS.start(),
+      INTERVAL_MS.end()
+    ));
+  }
+  Ok(())
+}
+
+/// Poll the clipboard in the background for the lifetime of the app. The
+/// thread idles while the watch is off or paused, and takes whatever is on
+/// the clipboard when it resumes as the baseline, so turning it on never
+/// offers text copied before.
+pub fn spawn(handle: AppHandle) {
+  let spawned = thread::Builder::new().name("clipboard-watch".to_string()).spawn(move || {
+    let mut clipboard = Poller::default();
+    loop {
+      let preferences = current_preferences(&handle);
+      if !preferences.clipboard_watch_enabled
+        || handle.state::<AppState>().clipboard_watch.paused.load(Ordering::SeqCst)
+      {
+        clipboard.reset();
+        thread::sleep(IDLE_INTERVAL);
+        continue;
+      }
+
+      if let Some(text) = clipboard.poll() {
+        changed(&handle, text);
+      }
+      thread::sleep(Duration::from_millis(preferences.clipboard_watch_interval_ms));
+    }
+  });
+  if let Err(error) = spawned {
+    warn!(%error, "failed to start clipboard watch");
+  }
+}
+
+fn changed(handle: &AppHandle, text: String) {
+  let chars = text.chars().count();
+  debug!(chars, "clipboard changed");
+  let content = classify::classify(&text);
+  let _ = handle.emit_all("clipboard-changed", ClipboardChanged { text, content });
+  notify::clipboard_suggestion(handle, chars);
+}
+
+/// Remembers the last text seen, to report only changes.
+#[derive(Default)]
+struct Poller {
+  /// `None` until a baseline was taken, after starting or resuming.
+  last: Option<String>,
+  #[cfg(windows)]
+  sequence: u32,
+}
+
+impl Poller {
+  fn reset(&mut self) {
+    self.last = None;
+  }
+
+  /// Newly copied text worth offering, if any.
+  fn poll(&mut self) -> Option<String> {
+    if writing() {
+      self.reset();
+      return None;
+    }
+    if self.last.is_some() && !self.sequence_changed() {
+      return None;
+    }
+
+    let text = Clipboard::new().and_then(|mut board| clipboard::read_text(&mut board));
+    // A write of ShortcutAI's own may have started during the read.
+    if writing() {
+      self.reset();
+      return None;
+    }
+    let text = text.unwrap_or_default();
+    let last = self.last.replace(text.clone())?;
+    (text != last && !text.trim().is_empty() && text.chars().count() <= MAX_CHARS)
+      .then_some(text)
+  }
+
+  /// Windows counts clipboard writes, which is far cheaper than reading the
+  /// clipboard on every tick.
+  #[cfg(windows)]
+  fn sequence_changed(&mut self) -> bool {
+    use windows_sys::Win32::System::DataExchange::GetClipboardSequenceNumber;
+
+    // SAFETY: takes no arguments and only reads a counter.
+    let sequence = unsafe { GetClipboardSequenceNumber() };
+    let changed = sequence != self.sequence;
+    self.sequence = sequence;
+    changed
+  }
+
+  #[cfg(not(windows))]
+  fn sequence_changed(&mut self) -> bool {
+    true
+  }
+}
+
+/// Pause or resume the clipboard watch for this session, without changing
+/// the `clipboard_watch_enabled` preference.
+#[tauri::command]
+#[instrument(skip(handle, state))]
+pub fn set_clipboard_watch_paused(handle: AppHandle, state: State<'_, AppState>, paused: bool) {
+  state.clipboard_watch.paused.store(paused, Ordering::SeqCst);
+  info!(paused, "clipboard watch paused state changed");
+  let _ = handle.emit_all("clipboard-watch-status", status(&handle, &state));
+}
+
+#[tauri::command]
+pub fn clipboard_watch_status(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+) -> ClipboardWatchStatus {
+  status(&handle, &state)
+}
+
+fn status(handle: &AppHandle, state: &AppState) -> ClipboardWatchStatus {
+  let preferences = current_preferences(handle);
+  ClipboardWatchStatus {
+    enabled: preferences.clipboard_watch_enabled,
+    paused: state.clipboard_watch.paused.load(Ordering::SeqCst),
+    interval_ms: preferences.clipboard_watch_interval_ms,
+  }
+}
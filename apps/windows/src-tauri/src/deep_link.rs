@@ -0,0 +1,84 @@
+//! `shortcutai://run/<action_id>` deep links, e.g. from a browser extension.
+
+use crate::{
+  capture_or_report, current_preferences, input_options, read_json, reveal_main_window, run,
+  setup_file_path, AppState, SetupFile,
+};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+pub const SCHEME: &str = "shortcutai";
+
+/// Extract the action id from `shortcutai://run/<action_id>`. Anything else,
+/// including ids with unexpected characters, is rejected.
+fn parse_run_link(url: &str) -> Option<&str> {
+  let rest = url.trim().strip_prefix(SCHEME)?.strip_prefix("://")?;
+  let action_id = rest.strip_prefix("run/")?;
+  let action_id = action_id
+    .split(['?', '#'])
+    .next()
+    .unwrap_or_default()
+    .trim_end_matches('/');
+
+  let valid = !action_id.is_empty()
+    && action_id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+  valid.then_some(action_id)
+}
+
+/// Register the URL scheme and route incoming links. A link that launched
+/// the app arrives as a command-line argument rather than through the
+/// listener, so that is handled here too.
+pub fn register(handle: &AppHandle) {
+  let app_handle = handle.clone();
+  if let Err(error) = tauri_plugin_deep_link::register(SCHEME, move |url| {
+    handle_link(&app_handle, &url)
+  }) {
+    warn!(%error, "failed to register deep link scheme");
+  }
+
+  let prefix = format!("{SCHEME}://");
+  if let Some(url) = std::env::args().skip(1).find(|arg| arg.starts_with(&prefix)) {
+    handle_link(handle, &url);
+  }
+}
+
+fn handle_link(handle: &AppHandle, url: &str) {
+  let Some(action_id) = parse_run_link(url) else {
+    warn!(url, "ignoring malformed deep link");
+    return;
+  };
+
+  let known = setup_file_path(handle)
+    .and_then(|path| read_json::<SetupFile>(&path))
+    .ok()
+    .flatten()
+    .is_some_and(|setup| setup.actions.iter().any(|action| action.id == action_id));
+  if !known {
+    warn!(action_id, "ignoring deep link for unknown action");
+    return;
+  }
+
+  info!(action_id, "running action from deep link");
+  let workers = &handle.state::<AppState>().workers;
+  let handle = handle.clone();
+  let action_id = action_id.to_string();
+  let submitted = workers.submit(move || {
+    let preferences = current_preferences(&handle);
+    let options = input_options(&handle, &action_id);
+    let Some(text) = capture_or_report(&handle, &preferences, &options) else {
+      reveal_main_window(&handle, preferences.completion_notice);
+      return;
+    };
+    if let Err(error) =
+      tauri::async_runtime::block_on(run::run_action(&handle, &action_id, text))
+    {
+      warn!(%error, "deep link run failed");
+    }
+    reveal_main_window(&handle, preferences.completion_notice);
+  });
+  if let Err(error) = submitted {
+    warn!(%error, "ignoring deep link");
+  }
+}
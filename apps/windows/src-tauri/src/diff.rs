@@ -0,0 +1,251 @@
+//! Word-level diff between an action's input and output, for showing what a
+//! "fix grammar" style action actually changed.
+
+use serde::{Deserialize, Serialize};
+
+/// Above this many token pairs in the changed middle, the middle is reported
+/// as one removal and one addition instead of being diffed.
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpanKind {
+  Unchanged,
+  Removed,
+  Added,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSpan {
+  kind: SpanKind,
+  text: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSummary {
+  pub chars_added: usize,
+  pub chars_removed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDiff {
+  /// In order; each run of changes between unchanged spans is at most one
+  /// removal followed by one addition (see `lcs_diff`).
+  /// Concatenating the unchanged and removed spans gives `before`, the
+  /// unchanged and added ones `after`.
+  spans: Vec<DiffSpan>,
+  summary: DiffSummary,
+}
+
+/// Diff `before` against `after` by words, whitespace runs and punctuation.
+#[tauri::command]
+pub fn diff_text(before: String, after: String) -> TextDiff {
+  let spans = diff(&before, &after);
+  let summary = summarize(&spans);
+  TextDiff { spans, summary }
+}
+
+/// Characters added and removed going from `before` to `after`.
+pub fn summary(before: &str, after: &str) -> DiffSummary {
+  summarize(&diff(before, after))
+}
+
+fn summarize(spans: &[DiffSpan]) -> DiffSummary {
+  let mut summary = DiffSummary::default();
+  for span in spans {
+    match span.kind {
+      SpanKind::Added => summary.chars_added += span.text.chars().count(),
+      SpanKind::Removed => summary.chars_removed += span.text.chars().count(),
+      SpanKind::Unchanged => {}
+    }
+  }
+  summary
+}
+
+/// Runs of letters and digits, runs of whitespace, and single other characters.
+fn tokens(text: &str) -> Vec<&str> {
+  let class = |c: char| {
+    if c.is_alphanumeric() {
+      0
+    } else if c.is_whitespace() {
+      1
+    } else {
+      2
+    }
+  };
+  let mut tokens = Vec::new();
+  let mut start = 0;
+  let mut previous = None;
+  for (index, c) in text.char_indices() {
+    let current = class(c);
+    if index > start && (previous != Some(current) || current == 2) {
+      tokens.push(&text[start..index]);
+      start = index;
+    }
+    previous = Some(current);
+  }
+  if start < text.len() {
+    tokens.push(&text[start..]);
+  }
+  tokens
+}
+
+fn diff(before: &str, after: &str) -> Vec<DiffSpan> {
+  let a = tokens(before);
+  let b = tokens(after);
+  let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+  let suffix = a[prefix..]
+    .iter()
+    .rev()
+    .zip(b[prefix..].iter().rev())
+    .take_while(|(x, y)| x == y)
+    .count();
+  let (a_middle, b_middle) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+  let mut spans = Spans::default();
+  for token in &a[..prefix] {
+    spans.push(SpanKind::Unchanged, token);
+  }
+  if a_middle.len().saturating_mul(b_middle.len()) > MAX_CELLS {
+    a_middle.iter().for_each(|token| spans.push(SpanKind::Removed, token));
+    b_middle.iter().for_each(|token| spans.push(SpanKind::Added, token));
+  } else {
+    lcs_diff(a_middle, b_middle, &mut spans);
+  }
+  for token in &a[a.len() - suffix..] {
+    spans.push(SpanKind::Unchanged, token);
+  }
+  spans.0
+}
+
+/// Longest common subsequence over the tokens, walked from the front. Ties
+/// go to removals, so once a run of changes starts adding it never removes
+/// again before the next unchanged token.
+fn lcs_diff(a: &[&str], b: &[&str], spans: &mut Spans) {
+  let width = b.len() + 1;
+  // lengths[i * width + j] is the LCS length of a[i..] and b[j..].
+  let mut lengths = vec![0u32; (a.len() + 1) * width];
+  for i in (0..a.len()).rev() {
+    for j in (0..b.len()).rev() {
+      lengths[i * width + j] = if a[i] == b[j] {
+        lengths[(i + 1) * width + j + 1] + 1
+      } else {
+        lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+      };
+    }
+  }
+
+  let (mut i, mut j) = (0, 0);
+  while i < a.len() && j < b.len() {
+    if a[i] == b[j] {
+      spans.push(SpanKind::Unchanged, a[i]);
+      i += 1;
+      j += 1;
+    } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+      spans.push(SpanKind::Removed, a[i]);
+      i += 1;
+    } else {
+      spans.push(SpanKind::Added, b[j]);
+      j += 1;
+    }
+  }
+  a[i..].iter().for_each(|token| spans.push(SpanKind::Removed, token));
+  b[j..].iter().for_each(|token| spans.push(SpanKind::Added, token));
+}
+
+/// Spans built token by token, merging neighbours of the same kind.
+#[derive(Default)]
+struct Spans(Vec<DiffSpan>);
+
+impl Spans {
+  fn push(&mut self, kind: SpanKind, token: &str) {
+    match self.0.last_mut() {
+      Some(last) if last.kind == kind => last.text.push_str(token),
+      _ => self.0.push(DiffSpan { kind, text: token.to_string() }),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rebuild(spans: &[DiffSpan], side: SpanKind) -> String {
+    spans
+      .iter()
+      .filter(|span| span.kind == SpanKind::Unchanged || span.kind == side)
+      .map(|span| span.text.as_str())
+      .collect()
+  }
+
+  fn kinds(spans: &[DiffSpan]) -> Vec<SpanKind> {
+    spans.iter().map(|span| span.kind).collect()
+  }
+
+  #[test]
+  fn equal_texts_are_one_unchanged_span() {
+    let spans = diff("Hello, world.", "Hello, world.");
+    assert_eq!(kinds(&spans), [SpanKind::Unchanged]);
+    assert_eq!(summarize(&spans), DiffSummary::default());
+    assert!(diff("", "").is_empty());
+  }
+
+  #[test]
+  fn an_empty_side_is_all_removed_or_added() {
+    let spans = diff("", "new text");
+    assert_eq!(kinds(&spans), [SpanKind::Added]);
+    assert_eq!(spans[0].text, "new text");
+    let spans = diff("old text", "");
+    assert_eq!(kinds(&spans), [SpanKind::Removed]);
+    assert_eq!(summary("old text", ""), DiffSummary { chars_added: 0, chars_removed: 8 });
+  }
+
+  #[test]
+  fn a_changed_word_is_replaced_in_place() {
+    let spans = diff("the quick fox jumps", "the slow fox jumps");
+    let texts: Vec<(SpanKind, &str)> =
+      spans.iter().map(|span| (span.kind, span.text.as_str())).collect();
+    assert_eq!(
+      texts,
+      [
+        (SpanKind::Unchanged, "the "),
+        (SpanKind::Removed, "quick"),
+        (SpanKind::Added, "slow"),
+        (SpanKind::Unchanged, " fox jumps"),
+      ]
+    );
+  }
+
+  #[test]
+  fn spans_rebuild_both_texts() {
+    let before = "Their going to the store, and buy some apples; then home.";
+    let after = "They're going to a store to buy apples, then back home!";
+    let spans = diff(before, after);
+    assert_eq!(rebuild(&spans, SpanKind::Removed), before);
+    assert_eq!(rebuild(&spans, SpanKind::Added), after);
+  }
+
+  #[test]
+  fn changes_between_unchanged_spans_are_one_removal_then_one_addition() {
+    let spans = diff("a, b c d", "x b, y d z");
+    for pair in spans.windows(2) {
+      assert_ne!(pair[0].kind, pair[1].kind);
+      assert!(!(pair[0].kind == SpanKind::Added && pair[1].kind == SpanKind::Removed));
+    }
+    assert_eq!(rebuild(&spans, SpanKind::Removed), "a, b c d");
+    assert_eq!(rebuild(&spans, SpanKind::Added), "x b, y d z");
+  }
+
+  #[test]
+  fn multibyte_text_is_diffed_by_characters() {
+    let before = "これは テスト です 🎉";
+    let after = "これは 試験 です 🎉";
+    let spans = diff(before, after);
+    assert_eq!(rebuild(&spans, SpanKind::Removed), before);
+    assert_eq!(rebuild(&spans, SpanKind::Added), after);
+    assert_eq!(summary(before, after), DiffSummary { chars_added: 2, chars_removed: 3 });
+  }
+}
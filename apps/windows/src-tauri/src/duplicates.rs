@@ -0,0 +1,200 @@
+//! Spotting actions that do the same thing, e.g. after installing presets or
+//! importing actions twice. Read-only: the cleanup UI decides what to remove.
+
+use crate::{read_json, setup_file_path, Action, SetupFile};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use tauri::AppHandle;
+use tracing::{info, instrument};
+
+/// Prompts at least this similar count as duplicates unless told otherwise.
+const DEFAULT_THRESHOLD: f64 = 0.8;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateMember {
+  id: String,
+  name: String,
+}
+
+/// Actions linked by similar prompts. A member may only be similar to
+/// another member rather than to all of them.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+  /// In the order of the action list.
+  actions: Vec<DuplicateMember>,
+  /// Lowest similarity among the links that formed the group, from 0 to 1;
+  /// 1 means identical prompts once normalized.
+  similarity: f64,
+}
+
+/// Groups of actions whose prompts are at least `threshold` (0-1, default
+/// 0.8) alike, by word overlap after lowercasing and dropping punctuation.
+/// Actions with the same prompt but different local transforms aren't
+/// duplicates; those without a prompt are skipped.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub fn find_duplicate_actions(
+  handle: AppHandle,
+  threshold: Option<f64>,
+) -> Result<Vec<DuplicateGroup>, String> {
+  let threshold = threshold.unwrap_or(DEFAULT_THRESHOLD);
+  if !(0.0..=1.0).contains(&threshold) {
+    return Err(format!("The threshold must be between 0 and 1, got {threshold}"));
+  }
+  let Some(setup) = read_json::<SetupFile>(&setup_file_path(&handle)?)? else {
+    return Ok(Vec::new());
+  };
+
+  let found = duplicate_groups(&setup.actions, threshold);
+  info!(groups = found.len(), "looked for duplicate actions");
+  Ok(found)
+}
+
+fn duplicate_groups(actions: &[Action], threshold: f64) -> Vec<DuplicateGroup> {
+  let words: Vec<HashSet<String>> = actions.iter().map(|action| words(&action.prompt)).collect();
+  let mut groups = Groups::new(actions.len());
+  for (i, a) in actions.iter().enumerate() {
+    for (j, b) in actions.iter().enumerate().skip(i + 1) {
+      if words[i].is_empty() || words[j].is_empty() || !comparable(a, b) {
+        continue;
+      }
+      let similarity = jaccard(&words[i], &words[j]);
+      if similarity >= threshold {
+        groups.join(i, j, similarity);
+      }
+    }
+  }
+  groups.collect(actions)
+}
+
+/// Only actions that would run the same way compare: with no transform, or
+/// the same one applied the same way.
+fn comparable(a: &Action, b: &Action) -> bool {
+  a.local_transform == b.local_transform && a.send_transformed == b.send_transformed
+}
+
+/// Lowercased words, without punctuation, so wording tweaks like an extra
+/// comma or capital don't count.
+fn words(prompt: &str) -> HashSet<String> {
+  prompt
+    .split(|c: char| !c.is_alphanumeric() && c != '{' && c != '}')
+    .filter(|word| !word.is_empty())
+    .map(str::to_lowercase)
+    .collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+  let shared = a.intersection(b).count();
+  let all = a.len() + b.len() - shared;
+  if all == 0 {
+    return 0.0;
+  }
+  shared as f64 / all as f64
+}
+
+/// Union-find over action indices, tracking each group's weakest link.
+struct Groups {
+  parent: Vec<usize>,
+  similarity: Vec<f64>,
+}
+
+impl Groups {
+  fn new(len: usize) -> Self {
+    Self { parent: (0..len).collect(), similarity: vec![1.0; len] }
+  }
+
+  fn root(&mut self, mut index: usize) -> usize {
+    while self.parent[index] != index {
+      self.parent[index] = self.parent[self.parent[index]];
+      index = self.parent[index];
+    }
+    index
+  }
+
+  fn join(&mut self, a: usize, b: usize, similarity: f64) {
+    let (a, b) = (self.root(a), self.root(b));
+    let weakest = self.similarity[a].min(self.similarity[b]).min(similarity);
+    let root = a.min(b);
+    self.parent[a.max(b)] = root;
+    self.similarity[root] = weakest;
+  }
+
+  /// Groups with more than one member, ordered by their first action.
+  fn collect(mut self, actions: &[Action]) -> Vec<DuplicateGroup> {
+    let mut members: BTreeMap<usize, Vec<DuplicateMember>> = BTreeMap::new();
+    for (index, action) in actions.iter().enumerate() {
+      let root = self.root(index);
+      members
+        .entry(root)
+        .or_default()
+        .push(DuplicateMember { id: action.id.clone(), name: action.name.clone() });
+    }
+    members
+      .into_iter()
+      .filter(|(_, actions)| actions.len() > 1)
+      .map(|(root, actions)| DuplicateGroup { actions, similarity: self.similarity[root] })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::transform::LocalTransform;
+
+  fn action(id: &str, prompt: &str) -> Action {
+    let (id, name, prompt) = (id.to_string(), id.to_string(), prompt.to_string());
+    Action { id, name, prompt, ..Action::default() }
+  }
+
+  fn ids(groups: &[DuplicateGroup]) -> Vec<Vec<&str>> {
+    groups
+      .iter()
+      .map(|group| group.actions.iter().map(|member| member.id.as_str()).collect())
+      .collect()
+  }
+
+  #[test]
+  fn case_and_punctuation_dont_tell_prompts_apart() {
+    let actions = [
+      action("a", "Fix the grammar of {{text}}."),
+      action("b", "fix the GRAMMAR of {{text}}"),
+      action("c", "Translate {{text}} into French"),
+    ];
+    let groups = duplicate_groups(&actions, DEFAULT_THRESHOLD);
+    assert_eq!(ids(&groups), [["a", "b"]]);
+    assert_eq!(groups[0].similarity, 1.0);
+  }
+
+  #[test]
+  fn the_threshold_is_inclusive() {
+    // Four shared words out of five: a similarity of exactly 0.8.
+    let actions = [action("a", "one two three four"), action("b", "one two three four five")];
+    assert_eq!(ids(&duplicate_groups(&actions, 0.8)), [["a", "b"]]);
+    assert!(duplicate_groups(&actions, 0.81).is_empty());
+    assert_eq!(duplicate_groups(&actions, 0.0).len(), 1);
+  }
+
+  #[test]
+  fn groups_chain_through_similar_members_and_keep_the_weakest_link() {
+    let actions = [
+      action("a", "one two three four"),
+      action("b", "one two three four five"),
+      action("c", "one two three four five six"),
+    ];
+    let groups = duplicate_groups(&actions, 0.8);
+    assert_eq!(ids(&groups), [["a", "b", "c"]]);
+    assert_eq!(groups[0].similarity, 0.8);
+  }
+
+  #[test]
+  fn empty_prompts_and_different_transforms_never_match() {
+    let mut uppercase = action("c", "same prompt");
+    uppercase.local_transform = Some(LocalTransform::Uppercase);
+    let actions = [action("a", ""), action("b", " "), action("d", "same prompt"), uppercase];
+    assert!(duplicate_groups(&actions, 0.0).is_empty());
+    assert!(duplicate_groups(&[], DEFAULT_THRESHOLD).is_empty());
+  }
+}
@@ -0,0 +1,166 @@
+//! Running an action on a text file without opening it, optionally writing
+//! the result back next to it or over it.
+
+use crate::run::{self, ActionRunResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tracing::{info, instrument};
+
+/// Larger files are refused before they are read.
+const MAX_FILE_BYTES: u64 = 2_000_000;
+
+/// Where `run_action_on_file` puts the output besides returning it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileOutput {
+  /// Replace the file's contents, keeping its encoding.
+  Overwrite,
+  /// Write `<stem>.output.<ext>` next to it, replacing an earlier one.
+  Sibling,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRunResult {
+  #[serde(flatten)]
+  run: ActionRunResult,
+  /// Set when the output was written to a file.
+  written_to: Option<String>,
+}
+
+/// Text encodings files are read in and written back with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+  Utf8 { bom: bool },
+  Utf16Le,
+  Utf16Be,
+}
+
+/// Run `action_id` on the contents of the text file at `path`. UTF-8 and,
+/// with a byte order mark, UTF-16 files are read; anything else, binary
+/// files, and files over 2 MB are refused.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub async fn run_action_on_file(
+  handle: AppHandle,
+  action_id: String,
+  path: String,
+  output: Option<FileOutput>,
+) -> Result<FileRunResult, String> {
+  let path = PathBuf::from(path);
+  let (text, encoding) = read_text(&path)?;
+  if text.trim().is_empty() {
+    return Err(format!("{} has no text to run the action on", path.display()));
+  }
+
+  // The output goes where `output` says, not to the action's destination.
+  let run = run::run_action_undelivered(&handle, &action_id, text).await?;
+  let written_to = match output {
+    None => None,
+    Some(output) => {
+      let target = match output {
+        FileOutput::Overwrite => path.clone(),
+        FileOutput::Sibling => sibling_path(&path),
+      };
+      write_text(&target, &run.output, encoding)?;
+      info!(path = %target.display(), "wrote action output to file");
+      Some(target.display().to_string())
+    }
+  };
+  Ok(FileRunResult { run, written_to })
+}
+
+fn read_text(path: &Path) -> Result<(String, Encoding), String> {
+  let metadata = fs::metadata(path)
+    .map_err(|error| format!("Failed to open {}: {error}", path.display()))?;
+  if !metadata.is_file() {
+    return Err(format!("{} is not a file", path.display()));
+  }
+  if metadata.len() > MAX_FILE_BYTES {
+    return Err(format!(
+      "{} is {:.1} MB; files over {} MB can't be run on",
+      path.display(),
+      metadata.len() as f64 / 1e6,
+      MAX_FILE_BYTES / 1_000_000
+    ));
+  }
+  let bytes =
+    fs::read(path).map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+  decode(&bytes).map_err(|error| format!("Can't read {}: {error}", path.display()))
+}
+
+/// Decode by byte order mark, defaulting to UTF-8. Text never contains NUL,
+/// so one means a binary file (or BOM-less UTF-16, which isn't guessed at).
+fn decode(bytes: &[u8]) -> Result<(String, Encoding), String> {
+  let (text, encoding) = match bytes {
+    [0xEF, 0xBB, 0xBF, rest @ ..] => (utf8(rest)?, Encoding::Utf8 { bom: true }),
+    [0xFF, 0xFE, rest @ ..] => (utf16(rest, u16::from_le_bytes)?, Encoding::Utf16Le),
+    [0xFE, 0xFF, rest @ ..] => (utf16(rest, u16::from_be_bytes)?, Encoding::Utf16Be),
+    _ if bytes.contains(&0) => return Err(binary()),
+    _ => (utf8(bytes)?, Encoding::Utf8 { bom: false }),
+  };
+  if text.contains('\0') {
+    return Err(binary());
+  }
+  Ok((text, encoding))
+}
+
+fn binary() -> String {
+  "it looks like a binary file, not text".to_string()
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, String> {
+  String::from_utf8(bytes.to_vec())
+    .map_err(|_| "it isn't UTF-8 text; save it as UTF-8 or UTF-16 first".to_string())
+}
+
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, String> {
+  if bytes.len() % 2 != 0 {
+    return Err("its UTF-16 text is cut off".to_string());
+  }
+  let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+  char::decode_utf16(units)
+    .collect::<Result<String, _>>()
+    .map_err(|_| "its UTF-16 text is malformed".to_string())
+}
+
+fn encode(text: &str, encoding: Encoding) -> Vec<u8> {
+  match encoding {
+    Encoding::Utf8 { bom } => {
+      let bom: &[u8] = if bom { &[0xEF, 0xBB, 0xBF] } else { &[] };
+      [bom, text.as_bytes()].concat()
+    }
+    Encoding::Utf16Le => {
+      [0xFF, 0xFE].into_iter().chain(text.encode_utf16().flat_map(u16::to_le_bytes)).collect()
+    }
+    Encoding::Utf16Be => {
+      [0xFE, 0xFF].into_iter().chain(text.encode_utf16().flat_map(u16::to_be_bytes)).collect()
+    }
+  }
+}
+
+/// `notes.txt` becomes `notes.output.txt`; `README` becomes `README.output`.
+fn sibling_path(path: &Path) -> PathBuf {
+  let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+  let name = match path.extension() {
+    Some(extension) => format!("{stem}.output.{}", extension.to_string_lossy()),
+    None => format!("{stem}.output"),
+  };
+  path.with_file_name(name)
+}
+
+fn write_text(path: &Path, text: &str, encoding: Encoding) -> Result<(), String> {
+  replace_file(path, &encode(text, encoding))
+}
+
+/// Through a temporary file, like `write_json`, so a failed write never
+/// leaves the original truncated.
+pub fn replace_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+  let temp_path = path.with_extension("shortcutai.tmp");
+  fs::write(&temp_path, contents)
+    .map_err(|error| format!("Failed to write {}: {error}", path.display()))?;
+  fs::rename(&temp_path, path)
+    .map_err(|error| format!("Failed to replace {}: {error}", path.display()))
+}
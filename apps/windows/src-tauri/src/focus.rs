@@ -0,0 +1,406 @@
+//! Inspection of the foreground app: its focused control and window title.
+
+/// The focused control of the foreground app, if it is a Win32 edit control.
+/// Rich edit controls count for `ES_PASSWORD` but not for their text, since
+/// their selection offsets don't line up with `WM_GETTEXT` line breaks.
+#[cfg(windows)]
+fn focused_edit_control(rich_edit: bool) -> Option<windows_sys::Win32::Foundation::HWND> {
+  use windows_sys::Win32::UI::WindowsAndMessaging::{
+    GetClassNameW, GetForegroundWindow, GetGUIThreadInfo, GetWindowThreadProcessId,
+    GUITHREADINFO,
+  };
+
+  // SAFETY: plain Win32 queries on handles owned by the system; every output
+  // buffer is a local sized as the API requires.
+  unsafe {
+    let foreground = GetForegroundWindow();
+    if foreground.is_null() {
+      return None;
+    }
+    let thread = GetWindowThreadProcessId(foreground, std::ptr::null_mut());
+
+    let mut info: GUITHREADINFO = std::mem::zeroed();
+    info.cbSize = std::mem::size_of::<GUITHREADINFO>() as u32;
+    if GetGUIThreadInfo(thread, &mut info) == 0 || info.hwndFocus.is_null() {
+      return None;
+    }
+
+    // Edit styles such as ES_PASSWORD mean something else for other classes.
+    let mut class = [0u16; 64];
+    let length = GetClassNameW(info.hwndFocus, class.as_mut_ptr(), class.len() as i32);
+    let class = String::from_utf16_lossy(&class[..length.max(0) as usize]).to_ascii_lowercase();
+    (class == "edit" || (rich_edit && class.starts_with("richedit"))).then_some(info.hwndFocus)
+  }
+}
+
+#[cfg(windows)]
+fn has_password_style(control: windows_sys::Win32::Foundation::HWND) -> bool {
+  use windows_sys::Win32::UI::WindowsAndMessaging::{GetWindowLongW, ES_PASSWORD, GWL_STYLE};
+
+  // SAFETY: reads the style bits of a window handle; a stale handle yields 0.
+  unsafe { GetWindowLongW(control, GWL_STYLE) & ES_PASSWORD != 0 }
+}
+
+/// Whether the focused control is a Win32 edit control with `ES_PASSWORD`.
+/// Best-effort: password fields drawn by browsers or custom UI toolkits are
+/// not Win32 edit controls and can't be detected this way.
+#[cfg(windows)]
+pub fn is_password_field_focused() -> bool {
+  focused_edit_control(true).is_some_and(has_password_style)
+}
+
+#[cfg(not(windows))]
+pub fn is_password_field_focused() -> bool {
+  false
+}
+
+/// Send `message` to another process's control, giving up on one that hangs.
+#[cfg(windows)]
+fn send_to(
+  control: windows_sys::Win32::Foundation::HWND,
+  message: u32,
+  wparam: usize,
+  lparam: isize,
+) -> Option<usize> {
+  use windows_sys::Win32::UI::WindowsAndMessaging::{SendMessageTimeoutW, SMTO_ABORTIFHUNG};
+  // The target app answers each message; don't wait on one that hangs.
+  const TIMEOUT_MS: u32 = 100;
+
+  let mut result = 0usize;
+  // SAFETY: `control` is a window handle; buffers passed in `lparam` come
+  // with their length and the system marshals them across processes.
+  let sent = unsafe {
+    SendMessageTimeoutW(control, message, wparam, lparam, SMTO_ABORTIFHUNG, TIMEOUT_MS, &mut result)
+  };
+  (sent != 0).then_some(result)
+}
+
+// From `Win32_UI_Controls`, which is otherwise unused.
+#[cfg(windows)]
+const EM_GETSEL: u32 = 0x00B0;
+#[cfg(windows)]
+const EM_SETSEL: u32 = 0x00B1;
+#[cfg(windows)]
+const EM_REPLACESEL: u32 = 0x00C2;
+
+/// Selection of the focused control, saved before a select-all.
+#[cfg(windows)]
+pub struct SavedSelection {
+  control: windows_sys::Win32::Foundation::HWND,
+  start: usize,
+  end: usize,
+}
+
+#[cfg(not(windows))]
+pub struct SavedSelection;
+
+/// The focused control's selection, if it is a Win32 edit or rich edit
+/// control short enough for `EM_GETSEL`'s 16-bit offsets. Other apps keep
+/// no selection this app can put back.
+#[cfg(windows)]
+pub fn saved_selection() -> Option<SavedSelection> {
+  use windows_sys::Win32::UI::WindowsAndMessaging::WM_GETTEXTLENGTH;
+
+  let control = focused_edit_control(true)?;
+  if send_to(control, WM_GETTEXTLENGTH, 0, 0)? > 0xFFFF {
+    return None;
+  }
+  let selection = send_to(control, EM_GETSEL, 0, 0)?;
+  Some(SavedSelection { control, start: selection & 0xFFFF, end: (selection >> 16) & 0xFFFF })
+}
+
+#[cfg(not(windows))]
+pub fn saved_selection() -> Option<SavedSelection> {
+  None
+}
+
+/// Put a saved selection back; whether it took isn't reported by the control.
+#[cfg(windows)]
+pub fn restore_selection(selection: SavedSelection) {
+  send_to(selection.control, EM_SETSEL, selection.start, selection.end as isize);
+}
+
+#[cfg(not(windows))]
+pub fn restore_selection(_selection: SavedSelection) {}
+
+#[cfg(windows)]
+fn has_read_only_style(control: windows_sys::Win32::Foundation::HWND) -> bool {
+  use windows_sys::Win32::UI::WindowsAndMessaging::{GetWindowLongW, ES_READONLY, GWL_STYLE};
+
+  // SAFETY: reads the style bits of a window handle; a stale handle yields 0.
+  unsafe { GetWindowLongW(control, GWL_STYLE) & ES_READONLY != 0 }
+}
+
+/// Replace the focused control's selection with `text` without the
+/// clipboard. Win32 edit and rich edit controls take it as an undoable
+/// `EM_REPLACESEL`; other controls through UI Automation's `ValuePattern`,
+/// which sets the whole value, so only when the selection covers all of it
+/// or the control is empty. `false` means the caller has to paste instead.
+/// Password and read-only controls are never written to.
+#[cfg(windows)]
+pub fn replace_selection(text: &str) -> bool {
+  match focused_edit_control(true) {
+    Some(control) if has_password_style(control) || has_read_only_style(control) => false,
+    Some(control) => {
+      let text: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+      // wParam 1 makes the replacement undoable. The string is marshalled
+      // into the target process, as for every message below WM_USER.
+      send_to(control, EM_REPLACESEL, 1, text.as_ptr() as isize).is_some()
+    }
+    None => set_value_with_ui_automation(text),
+  }
+}
+
+/// `ValuePattern.SetValue` on the focused UI Automation element.
+#[cfg(windows)]
+fn set_value_with_ui_automation(text: &str) -> bool {
+  use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+
+  // SAFETY: balanced with `CoUninitialize` below when it succeeds. A thread
+  // that already joined an apartment gets an error and keeps it, which UI
+  // Automation works in too.
+  let initialized = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) }.is_ok();
+  let result = set_focused_value(text);
+  if let Err(error) = &result {
+    tracing::debug!(%error, "focused element has no writable value");
+  }
+  if initialized {
+    // SAFETY: every COM object used above was dropped with `set_focused_value`.
+    unsafe { CoUninitialize() };
+  }
+  result.is_ok()
+}
+
+#[cfg(windows)]
+fn set_focused_value(text: &str) -> windows::core::Result<()> {
+  use windows::core::{Error, BSTR};
+  use windows::Win32::Foundation::E_ACCESSDENIED;
+  use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+  use windows::Win32::UI::Accessibility::{
+    CUIAutomation, IUIAutomation, IUIAutomationTextPattern, IUIAutomationValuePattern,
+    UIA_TextPatternId, UIA_ValuePatternId,
+  };
+
+  // SAFETY: COM calls on interfaces this function holds; each returns an
+  // owned reference that is released on drop.
+  unsafe {
+    let automation: IUIAutomation = CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER)?;
+    let element = automation.GetFocusedElement()?;
+    let refused = || Error::from(E_ACCESSDENIED);
+    if element.CurrentIsPassword()?.as_bool() {
+      return Err(refused());
+    }
+    let value: IUIAutomationValuePattern = element.GetCurrentPatternAs(UIA_ValuePatternId)?;
+    if value.CurrentIsReadOnly()?.as_bool() {
+      return Err(refused());
+    }
+
+    // SetValue replaces everything, which is only right over a whole-value
+    // selection; a partial one can't be spliced without knowing its offsets.
+    let current = value.CurrentValue()?.to_string();
+    if !current.is_empty() {
+      let text_pattern: IUIAutomationTextPattern =
+        element.GetCurrentPatternAs(UIA_TextPatternId)?;
+      let ranges = text_pattern.GetSelection()?;
+      let selected = match ranges.Length()? {
+        1 => ranges.GetElement(0)?.GetText(-1)?.to_string(),
+        _ => return Err(refused()),
+      };
+      if selected != current {
+        return Err(refused());
+      }
+    }
+    value.SetValue(&BSTR::from(text))
+  }
+}
+
+#[cfg(not(windows))]
+pub fn replace_selection(_text: &str) -> bool {
+  false
+}
+
+/// Text on either side of the selection (or cursor) in the focused control.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CursorContext {
+  pub before: String,
+  pub after: String,
+}
+
+/// Up to `max_chars` of text before and after the selection in the focused
+/// control. Only plain Win32 edit controls share their text and selection
+/// with other processes this way; browsers, most editors and password fields
+/// give `None`, and actions get the selection alone.
+#[cfg(windows)]
+pub fn cursor_context(max_chars: usize) -> Option<CursorContext> {
+  use windows_sys::Win32::UI::WindowsAndMessaging::{WM_GETTEXT, WM_GETTEXTLENGTH};
+
+  let control = focused_edit_control(false).filter(|&control| !has_password_style(control))?;
+  let send = |message: u32, wparam: usize, lparam: isize| send_to(control, message, wparam, lparam);
+
+  // Without pointers EM_GETSEL packs both offsets into 16 bits each, so
+  // longer text can't be mapped.
+  let length = send(WM_GETTEXTLENGTH, 0, 0)?;
+  if length > 0xFFFF {
+    return None;
+  }
+  let mut text = vec![0u16; length + 1];
+  let copied = send(WM_GETTEXT, text.len(), text.as_mut_ptr() as isize)?;
+  text.truncate(copied);
+  let selection = send(EM_GETSEL, 0, 0)?;
+  let (start, end) = (selection & 0xFFFF, (selection >> 16) & 0xFFFF);
+  if start > end || end > text.len() {
+    return None;
+  }
+
+  let before: Vec<char> = String::from_utf16_lossy(&text[..start]).chars().collect();
+  let after = String::from_utf16_lossy(&text[end..]);
+  Some(CursorContext {
+    before: before[before.len().saturating_sub(max_chars)..].iter().collect(),
+    after: after.chars().take(max_chars).collect(),
+  })
+}
+
+#[cfg(not(windows))]
+pub fn cursor_context(_max_chars: usize) -> Option<CursorContext> {
+  None
+}
+
+/// Title of the foreground window, if it has one.
+#[cfg(windows)]
+pub fn foreground_window_title() -> Option<String> {
+  use windows_sys::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowTextW};
+
+  // SAFETY: the buffer is a local and its length is passed along with it.
+  unsafe {
+    let foreground = GetForegroundWindow();
+    if foreground.is_null() {
+      return None;
+    }
+    let mut title = [0u16; 512];
+    let length = GetWindowTextW(foreground, title.as_mut_ptr(), title.len() as i32);
+    (length > 0).then(|| String::from_utf16_lossy(&title[..length as usize]))
+  }
+}
+
+#[cfg(not(windows))]
+pub fn foreground_window_title() -> Option<String> {
+  None
+}
+
+/// Bring the first visible window whose title contains `query`, ignoring
+/// case, to the foreground, restoring it if minimized. ShortcutAI's own
+/// windows never match. Returns the full title of the window.
+#[cfg(windows)]
+pub fn focus_window_titled(query: &str) -> Result<String, String> {
+  use windows_sys::Win32::Foundation::{HWND, LPARAM};
+  use windows_sys::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+    IsWindowVisible, SetForegroundWindow, ShowWindow, SW_RESTORE,
+  };
+
+  struct Search {
+    query: String,
+    found: Option<(HWND, String)>,
+  }
+
+  unsafe extern "system" fn visit(window: HWND, search: LPARAM) -> i32 {
+    // SAFETY: `search` is the `Search` passed to `EnumWindows` below, which
+    // outlives the enumeration.
+    let search = &mut *(search as *mut Search);
+    let mut process = 0;
+    GetWindowThreadProcessId(window, &mut process);
+    if IsWindowVisible(window) == 0 || process == std::process::id() {
+      return 1;
+    }
+    let mut title = [0u16; 512];
+    let length = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+    if length <= 0 {
+      return 1;
+    }
+    let title = String::from_utf16_lossy(&title[..length as usize]);
+    if !title.to_lowercase().contains(&search.query) {
+      return 1;
+    }
+    search.found = Some((window, title));
+    0
+  }
+
+  let mut search = Search { query: query.trim().to_lowercase(), found: None };
+  // SAFETY: `visit` only runs during the call and gets `search` back.
+  unsafe { EnumWindows(Some(visit), &mut search as *mut Search as LPARAM) };
+  let (window, title) = search
+    .found
+    .ok_or_else(|| format!("No window title contains \"{}\"", query.trim()))?;
+
+  // SAFETY: `window` came from `EnumWindows`; a window closed since makes
+  // these calls fail, which the foreground check reports.
+  unsafe {
+    if IsIconic(window) != 0 {
+      ShowWindow(window, SW_RESTORE);
+    }
+    SetForegroundWindow(window);
+    if GetForegroundWindow() != window {
+      return Err(format!("Windows didn't let ShortcutAI bring \"{title}\" to the front"));
+    }
+  }
+  Ok(title)
+}
+
+#[cfg(not(windows))]
+pub fn focus_window_titled(_query: &str) -> Result<String, String> {
+  Err("Pasting into a window by title is only supported on Windows".to_string())
+}
+
+/// Best-effort guess at the file open in an editor, from its window title.
+/// Editors put the file name (sometimes the full path) in one of the
+/// `" - "`-separated parts, marked with `*` or `●` while unsaved, e.g.
+/// `● main.rs - shortcutai - Visual Studio Code` or `*C:\notes.txt - Notepad`.
+pub fn file_from_title(title: &str) -> Option<String> {
+  title
+    .split(" - ")
+    .flat_map(|part| part.split(" \u{2014} "))
+    .map(|part| {
+      let part = part.trim().trim_start_matches(['*', '\u{25cf}', '\u{2022}']);
+      part.trim_end_matches('*').trim()
+    })
+    .find(|part| looks_like_file(part))
+    .map(str::to_string)
+}
+
+fn looks_like_file(part: &str) -> bool {
+  let name = part.rsplit(['\\', '/']).next().unwrap_or(part);
+  let Some((stem, extension)) = name.rsplit_once('.') else {
+    return false;
+  };
+  // A bare file name has no spaces; paths may, but then contain a separator.
+  let plausible_name = !name.contains(' ') || part.contains(['\\', '/']);
+  !stem.is_empty()
+    && (1..=8).contains(&extension.len())
+    && extension.chars().all(|c| c.is_ascii_alphanumeric())
+    // Rules out versions such as `v1.2`.
+    && !extension.chars().all(|c| c.is_ascii_digit())
+    && plausible_name
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn file_names_are_found_in_editor_titles() {
+    let vscode = "\u{25cf} main.rs - shortcutai - Visual Studio Code";
+    assert_eq!(file_from_title(vscode).as_deref(), Some("main.rs"));
+    assert_eq!(file_from_title("*C:\\notes.txt - Notepad").as_deref(), Some("C:\\notes.txt"));
+    let em_dash = "report.docx \u{2014} Word";
+    assert_eq!(file_from_title(em_dash).as_deref(), Some("report.docx"));
+  }
+
+  #[test]
+  fn titles_without_a_file_give_none() {
+    assert_eq!(file_from_title("Google Chrome"), None);
+    assert_eq!(file_from_title(""), None);
+    assert_eq!(file_from_title("v1.2 - Installer"), None);
+    assert_eq!(file_from_title("Setup 2.0.1 - Wizard"), None);
+    assert_eq!(file_from_title("Inbox - Mail"), None);
+  }
+}
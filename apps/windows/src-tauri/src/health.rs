@@ -0,0 +1,82 @@
+//! Keeps the main shortcut registered. Windows sometimes drops global hotkeys
+//! across sleep or a session change without telling the app, and the
+//! shortcut manager only remembers what it registered, so it can't tell.
+//! The shortcut is therefore registered afresh periodically, when the window
+//! gains focus and after the system resumes.
+
+use crate::{register_main_shortcut, unregister_main_shortcut, AppState};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use tracing::{debug, info, warn};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// A tick arriving this much later than scheduled means the machine slept.
+const RESUME_GAP: Duration = Duration::from_secs(60);
+
+/// Check the registration in the background for the lifetime of the app.
+/// There is no resume event to listen to, so a resume is inferred from the
+/// wall clock jumping ahead between two ticks.
+pub fn spawn(handle: AppHandle) {
+  let spawned = thread::Builder::new().name("shortcut-health".to_string()).spawn(move || {
+    let mut last_tick = SystemTime::now();
+    loop {
+      thread::sleep(CHECK_INTERVAL);
+      let now = SystemTime::now();
+      let gap = now.duration_since(last_tick).unwrap_or_default();
+      last_tick = now;
+
+      let result = if gap > CHECK_INTERVAL + RESUME_GAP {
+        reassert(&handle, "resume").map(|_| ())
+      } else {
+        check(&handle)
+      };
+      if let Err(error) = result {
+        warn!(%error, "main shortcut health check failed");
+      }
+    }
+  });
+  if let Err(error) = spawned {
+    warn!(%error, "failed to start shortcut health check");
+  }
+}
+
+/// Routine refresh of the main shortcut's registration. Whether Windows
+/// still holds it can't be queried, so it is always registered again.
+pub fn check(handle: &AppHandle) -> Result<(), String> {
+  if let Some(shortcut) = register_again(handle)? {
+    debug!(%shortcut, "refreshed main shortcut");
+  }
+  Ok(())
+}
+
+/// Unregister and register the main shortcut so the OS registration is fresh.
+/// Returns `false` if there was nothing to re-assert: no main shortcut, or
+/// one released on purpose while idle.
+pub fn reassert(handle: &AppHandle, reason: &str) -> Result<bool, String> {
+  let shortcut = register_again(handle)?;
+  if let Some(shortcut) = &shortcut {
+    info!(%shortcut, reason, "re-asserted main shortcut");
+  }
+  Ok(shortcut.is_some())
+}
+
+/// The shortcut registered again, if there was one to register.
+fn register_again(handle: &AppHandle) -> Result<Option<String>, String> {
+  let state = handle.state::<AppState>();
+  let active = state
+    .active_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+  let Some(shortcut) = active.as_deref() else {
+    return Ok(None);
+  };
+  if state.idle.is_released() {
+    return Ok(None);
+  }
+
+  // Fails if the registration is already gone, which is the point.
+  let _ = unregister_main_shortcut(handle, &state, shortcut);
+  register_main_shortcut(handle, &state, shortcut)?;
+  Ok(Some(shortcut.to_string()))
+}
@@ -0,0 +1,120 @@
+//! Tap-versus-hold timing for the main shortcut. Global shortcuts only report
+//! the press, so the shortcut's key is polled until it is released or the
+//! hold threshold passes.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long a hold waits for the keys to come up before capturing anyway.
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Set while a press is being timed, so auto-repeated presses are ignored.
+static TIMING: AtomicBool = AtomicBool::new(false);
+
+struct TimingGuard;
+
+impl Drop for TimingGuard {
+  fn drop(&mut self) {
+    TIMING.store(false, Ordering::Release);
+  }
+}
+
+pub enum Gesture {
+  /// Released before the threshold.
+  Tap,
+  /// Held past the threshold. Returned once the shortcut's keys are up, so
+  /// the simulated copy isn't combined with them.
+  Hold,
+  /// Another press of the shortcut is still being timed.
+  Repeat,
+}
+
+/// Time the press of `shortcut` that just fired. `None` when its key can't be
+/// polled, in which case the press should be handled as if the gesture were off.
+pub fn classify(shortcut: &str, threshold: Duration) -> Option<Gesture> {
+  let tokens: Vec<&str> = shortcut.split('+').map(str::trim).collect();
+  let key = *tokens.last()?;
+  is_down(key)?;
+
+  if TIMING.swap(true, Ordering::AcqRel) {
+    return Some(Gesture::Repeat);
+  }
+  let _timing = TimingGuard;
+
+  let pressed_at = Instant::now();
+  while is_down(key)? {
+    if pressed_at.elapsed() >= threshold {
+      wait_for_release(&tokens);
+      return Some(Gesture::Hold);
+    }
+    thread::sleep(POLL_INTERVAL);
+  }
+  Some(Gesture::Tap)
+}
+
+fn wait_for_release(tokens: &[&str]) {
+  let deadline = Instant::now() + RELEASE_TIMEOUT;
+  while Instant::now() < deadline && tokens.iter().any(|token| is_down(token).unwrap_or(false)) {
+    thread::sleep(POLL_INTERVAL);
+  }
+}
+
+/// Whether the key named by an accelerator token is down, or `None` for a
+/// token without a known virtual-key code.
+#[cfg(windows)]
+fn is_down(token: &str) -> Option<bool> {
+  use windows_sys::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+
+  let codes = virtual_keys(token)?;
+  // SAFETY: GetAsyncKeyState only reads the global key state. The high bit,
+  // i.e. a negative value, means the key is down.
+  Some(codes.iter().any(|&code| unsafe { GetAsyncKeyState(i32::from(code)) } < 0))
+}
+
+#[cfg(not(windows))]
+fn is_down(_token: &str) -> Option<bool> {
+  None
+}
+
+/// Virtual-key codes for an accelerator token; either one being down counts.
+#[cfg(windows)]
+fn virtual_keys(token: &str) -> Option<Vec<u16>> {
+  let token = token.to_ascii_lowercase();
+  let code = match token.as_str() {
+    "shift" => 0x10,
+    "ctrl" | "control" | "cmdorctrl" | "commandorcontrol" => 0x11,
+    "alt" | "option" => 0x12,
+    "super" | "meta" | "cmd" | "command" => return Some(vec![0x5B, 0x5C]),
+    "backspace" => 0x08,
+    "tab" => 0x09,
+    "enter" | "return" => 0x0D,
+    "escape" | "esc" => 0x1B,
+    "space" => 0x20,
+    "pageup" => 0x21,
+    "pagedown" => 0x22,
+    "end" => 0x23,
+    "home" => 0x24,
+    "left" => 0x25,
+    "up" => 0x26,
+    "right" => 0x27,
+    "down" => 0x28,
+    "insert" => 0x2D,
+    "delete" => 0x2E,
+    _ => match token.as_bytes() {
+      // Virtual-key codes for A-Z and 0-9 match their uppercase ASCII values.
+      [byte] if byte.is_ascii_alphanumeric() => u16::from(byte.to_ascii_uppercase()),
+      [b'f', ..] => {
+        let number: u16 = token[1..].parse().ok()?;
+        if !(1..=24).contains(&number) {
+          return None;
+        }
+        0x70 + number - 1
+      }
+      _ => return None,
+    },
+  };
+  Some(vec![code])
+}
@@ -0,0 +1,108 @@
+//! Releases the main shortcut after a long idle period, so the app doesn't
+//! hold a global hotkey hook on battery for nothing, and takes it back once
+//! the window is shown again.
+
+use crate::{current_preferences, register_main_shortcut, unregister_main_shortcut, AppState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `released` only changes while `AppState::active_shortcut` is locked.
+pub struct Monitor {
+  last_activity: Mutex<Instant>,
+  released: AtomicBool,
+}
+
+impl Default for Monitor {
+  fn default() -> Self {
+    Self {
+      last_activity: Mutex::new(Instant::now()),
+      released: AtomicBool::new(false),
+    }
+  }
+}
+
+impl Monitor {
+  pub fn touch(&self) {
+    if let Ok(mut last_activity) = self.last_activity.lock() {
+      *last_activity = Instant::now();
+    }
+  }
+
+  fn idle_for(&self) -> Duration {
+    self.last_activity.lock().map_or(Duration::ZERO, |last_activity| last_activity.elapsed())
+  }
+
+  pub fn is_released(&self) -> bool {
+    self.released.load(Ordering::SeqCst)
+  }
+
+  /// Whether the main shortcut was released, clearing the flag.
+  pub fn take_released(&self) -> bool {
+    self.released.swap(false, Ordering::SeqCst)
+  }
+}
+
+/// Check for idleness in the background for the lifetime of the app.
+pub fn spawn(handle: AppHandle) {
+  let spawned = thread::Builder::new().name("idle-monitor".to_string()).spawn(move || loop {
+    thread::sleep(CHECK_INTERVAL);
+    let Some(minutes) = current_preferences(&handle).release_shortcut_after_idle_mins else {
+      continue;
+    };
+    let idle_for = handle.state::<AppState>().idle.idle_for();
+    if idle_for >= Duration::from_secs(minutes * 60) {
+      release(&handle, idle_for);
+    }
+  });
+  if let Err(error) = spawned {
+    warn!(%error, "failed to start idle monitor");
+  }
+}
+
+fn release(handle: &AppHandle, idle_for: Duration) {
+  let state = handle.state::<AppState>();
+  let Ok(active) = state.active_shortcut.lock() else {
+    return;
+  };
+  let Some(shortcut) = active.as_deref() else {
+    return;
+  };
+  if state.idle.released.load(Ordering::SeqCst) {
+    return;
+  }
+
+  match unregister_main_shortcut(handle, &state, shortcut) {
+    Ok(()) => {
+      state.idle.released.store(true, Ordering::SeqCst);
+      info!(%shortcut, idle_secs = idle_for.as_secs(), "released main shortcut while idle");
+    }
+    Err(error) => warn!(%error, "failed to release main shortcut while idle"),
+  }
+}
+
+/// Record activity and register the main shortcut again if it was released.
+pub fn resume(handle: &AppHandle) {
+  let state = handle.state::<AppState>();
+  state.idle.touch();
+
+  let Ok(active) = state.active_shortcut.lock() else {
+    return;
+  };
+  let Some(shortcut) = active.as_deref() else {
+    return;
+  };
+  if !state.idle.take_released() {
+    return;
+  }
+
+  match register_main_shortcut(handle, &state, shortcut) {
+    Ok(()) => info!(%shortcut, "registered main shortcut again after idle"),
+    Err(error) => warn!(%error, "failed to register main shortcut again after idle"),
+  }
+}
@@ -0,0 +1,162 @@
+//! Keyboard simulation, with the failures enigo itself swallows turned into errors.
+
+use crate::Preferences;
+use enigo::{Enigo, Key, KeyboardControllable};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Whether simulated keystrokes can reach the foreground app right now.
+pub fn availability() -> Result<(), String> {
+  catch_unwind(Enigo::new).map_err(|_| "Keyboard simulation failed to start".to_string())?;
+  input_desktop_available()
+}
+
+/// A key combination from an accelerator string such as `Ctrl+Shift+C`:
+/// modifiers held down, in order, around one final key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChord {
+  modifiers: Vec<Key>,
+  key: Key,
+  /// As written, for error messages.
+  label: String,
+}
+
+/// Parse an accelerator made of modifiers (Ctrl, Shift, Alt, Win) and one
+/// letter, digit, F-key or named key like `Insert` or `Enter`.
+pub fn parse_chord(accelerator: &str) -> Result<KeyChord, String> {
+  let tokens: Vec<&str> = accelerator.split('+').map(str::trim).collect();
+  let Some((last, modifiers)) = tokens.split_last().filter(|_| !tokens.contains(&"")) else {
+    return Err(format!("{accelerator:?} is not a key combination like Ctrl+Shift+C"));
+  };
+  let modifiers = modifiers
+    .iter()
+    .map(|token| {
+      modifier(token).ok_or_else(|| format!("{token} in {accelerator} is not a modifier"))
+    })
+    .collect::<Result<Vec<Key>, String>>()?;
+  if modifier(last).is_some() {
+    return Err(format!("{accelerator} has no key besides its modifiers"));
+  }
+  let key = key(last).ok_or_else(|| format!("Unknown key {last} in {accelerator}"))?;
+  Ok(KeyChord { modifiers, key, label: accelerator.trim().to_string() })
+}
+
+fn modifier(token: &str) -> Option<Key> {
+  match token.to_ascii_lowercase().as_str() {
+    "ctrl" | "control" => Some(Key::Control),
+    "shift" => Some(Key::Shift),
+    "alt" | "option" => Some(Key::Alt),
+    "win" | "super" | "meta" => Some(Key::Meta),
+    _ => None,
+  }
+}
+
+fn key(token: &str) -> Option<Key> {
+  let lower = token.to_ascii_lowercase();
+  let key = match lower.as_str() {
+    "enter" | "return" => Key::Return,
+    "tab" => Key::Tab,
+    "space" => Key::Space,
+    "backspace" => Key::Backspace,
+    "delete" => Key::Delete,
+    // enigo has no Insert variant; this is its virtual-key code.
+    "insert" => Key::Raw(0x2D),
+    "escape" | "esc" => Key::Escape,
+    "home" => Key::Home,
+    "end" => Key::End,
+    "pageup" => Key::PageUp,
+    "pagedown" => Key::PageDown,
+    "left" => Key::LeftArrow,
+    "right" => Key::RightArrow,
+    "up" => Key::UpArrow,
+    "down" => Key::DownArrow,
+    _ => match lower.as_bytes() {
+      [byte] if byte.is_ascii_alphanumeric() => Key::Layout(char::from(*byte)),
+      [b'f', ..] => {
+        let number: u16 = lower[1..].parse().ok()?;
+        if !(1..=24).contains(&number) {
+          return None;
+        }
+        // F1 is 0x70 and the rest follow in order.
+        Key::Raw(0x70 + number - 1)
+      }
+      _ => return None,
+    },
+  };
+  Some(key)
+}
+
+/// Simulate `<modifier>+<letter>` using the configured modifier and key mode.
+pub fn send_modified_key(preferences: &Preferences, letter: char) -> Result<(), String> {
+  let chord = KeyChord {
+    modifiers: vec![preferences.copy_paste_modifier.key()],
+    key: Key::Layout(letter),
+    label: letter.to_string(),
+  };
+  send_chord(preferences, &chord)
+}
+
+/// Simulate `keys`, an action's override of the copy or paste shortcut, or
+/// `<modifier>+<letter>` without one.
+pub fn send_shortcut(
+  preferences: &Preferences,
+  keys: Option<&str>,
+  letter: char,
+) -> Result<(), String> {
+  match keys {
+    Some(keys) => send_chord(preferences, &parse_chord(keys)?),
+    None => send_modified_key(preferences, letter),
+  }
+}
+
+/// Press the modifiers in order, click the key, and release the modifiers in
+/// reverse. Letters and digits follow the configured key mode.
+pub fn send_chord(preferences: &Preferences, chord: &KeyChord) -> Result<(), String> {
+  input_desktop_available()?;
+
+  let key = match chord.key {
+    // Virtual-key codes for A-Z and 0-9 match their uppercase ASCII values.
+    Key::Layout(c) if preferences.use_physical_keys && c.is_ascii_alphanumeric() => {
+      Key::Raw(c.to_ascii_uppercase() as u16)
+    }
+    key => key,
+  };
+
+  catch_unwind(AssertUnwindSafe(|| {
+    let mut enigo = Enigo::new();
+    for &modifier in &chord.modifiers {
+      enigo.key_down(modifier);
+    }
+    enigo.key_click(key);
+    for &modifier in chord.modifiers.iter().rev() {
+      enigo.key_up(modifier);
+    }
+  }))
+  .map_err(|_| format!("Keyboard simulation failed while sending {}", chord.label))
+}
+
+/// Input sent while the lock screen or a UAC prompt owns the input desktop
+/// is dropped without an error, so check that this process can reach it.
+#[cfg(windows)]
+fn input_desktop_available() -> Result<(), String> {
+  use windows_sys::Win32::System::StationsAndDesktops::{
+    CloseDesktop, OpenInputDesktop, DESKTOP_JOURNALPLAYBACK,
+  };
+
+  // SAFETY: the handle is checked for null and closed before returning.
+  unsafe {
+    let desktop = OpenInputDesktop(0, 0, DESKTOP_JOURNALPLAYBACK);
+    if desktop.is_null() {
+      return Err(
+        "Keyboard simulation is blocked: the lock screen or an administrator prompt is active"
+          .to_string(),
+      );
+    }
+    CloseDesktop(desktop);
+  }
+  Ok(())
+}
+
+#[cfg(not(windows))]
+fn input_desktop_available() -> Result<(), String> {
+  Ok(())
+}
@@ -1,28 +1,97 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod archive;
+mod autosave;
+mod benchmark;
+mod cache;
+mod classify;
+mod clipboard;
+mod clipboard_watch;
+mod deep_link;
+mod diff;
+mod duplicates;
+mod file_run;
+mod focus;
+mod health;
+mod hold;
+mod idle;
+mod input;
+mod notify;
+mod placement;
+mod presets;
+mod profile;
+mod provider;
+mod raw_response;
+mod redact;
+mod reset;
+mod resource_stats;
+mod restore_check;
+mod run;
+mod self_test;
+mod setup_check;
+mod share;
+mod startup;
+mod telemetry;
+mod text_stats;
+mod tools;
+mod trace;
+mod transform;
+mod tray;
+mod usage;
+mod worker;
+
 use arboard::Clipboard;
-use enigo::{Enigo, Key, KeyboardControllable};
+use enigo::Key;
 use keyring::Entry;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tauri::{
-  AppHandle, CustomMenuItem, GlobalShortcutManager, Manager, State, SystemTray, SystemTrayEvent,
-  SystemTrayMenu, SystemTrayMenuItem,
+  AppHandle, GlobalShortcutManager, Manager, State, SystemTray, SystemTrayEvent,
+  UserAttentionType, WindowEvent,
 };
+use tracing::{debug, info, instrument, warn};
+use tracing_appender::non_blocking::WorkerGuard;
+
+/// Result of probing one capability, with a hint for fixing it when not ready.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PermissionCheck {
+  ready: bool,
+  hint: Option<String>,
+}
+
+impl PermissionCheck {
+  fn new(ready: bool, hint: &str) -> Self {
+    Self {
+      ready,
+      hint: (!ready).then(|| hint.to_string()),
+    }
+  }
+}
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct PermissionStatus {
   global_shortcut_ready: bool,
   clipboard_ready: bool,
+  global_shortcut: PermissionCheck,
+  clipboard_read: PermissionCheck,
+  clipboard_write: PermissionCheck,
+  input_simulation: PermissionCheck,
+  keyring: PermissionCheck,
+  /// Not ready when settings are kept in a fallback directory.
+  data_dir: PermissionCheck,
   note: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Action {
   id: String,
@@ -30,6 +99,572 @@ struct Action {
   prompt: String,
   created_at: String,
   last_used_at: Option<String>,
+  /// Successful runs, kept by the backend; saves from the UI don't change it.
+  #[serde(default)]
+  run_count: u64,
+  /// Fail over to `fallback_providers` when the primary provider is down.
+  #[serde(default)]
+  use_fallback: bool,
+  /// Put the result on the clipboard instead of pasting it. Superseded by
+  /// `output_destination` when that is set.
+  #[serde(default)]
+  copy_only: bool,
+  /// Where results go after a run; see `Action::destination`.
+  #[serde(default)]
+  output_destination: Option<OutputDestination>,
+  /// Absolute path results are written to with `OutputDestination::File`,
+  /// replacing its contents.
+  #[serde(default)]
+  output_path: Option<String>,
+  /// Always answer in this language (one of `OUTPUT_LANGUAGES`).
+  #[serde(default)]
+  output_language: Option<String>,
+  /// Passed to models that reason; ignored by the rest.
+  #[serde(default)]
+  reasoning_effort: Option<provider::ReasoningEffort>,
+  /// Tags attached to this action's requests. Only OpenAI accepts them.
+  #[serde(default)]
+  metadata: HashMap<String, String>,
+  /// Fixed sampling seed, for comparing prompt changes on stable outputs.
+  #[serde(default)]
+  seed: Option<i64>,
+  /// Listed in the favorites bar and the tray, in the order of `actions`.
+  #[serde(default)]
+  pinned: bool,
+  /// Serve repeated runs on the same text from the response cache.
+  #[serde(default)]
+  cache_responses: bool,
+  /// Generation stops at any of these. Ignored by providers without support.
+  #[serde(default)]
+  stop: Vec<String>,
+  /// The preset this action was installed from, so it isn't installed twice.
+  #[serde(default)]
+  preset_id: Option<String>,
+  /// Overrides `Preferences::request_timeout_ms` for this action.
+  #[serde(default)]
+  timeout_ms: Option<u64>,
+  /// Reformat the text locally. On its own this is the whole action and no
+  /// provider (or API key) is involved.
+  #[serde(default)]
+  local_transform: Option<transform::LocalTransform>,
+  /// Send the transformed text to the provider instead of using it as is.
+  #[serde(default)]
+  send_transformed: bool,
+  /// Name of an entry in `parameter_presets`; without one, provider defaults apply.
+  #[serde(default)]
+  parameter_preset: Option<String>,
+  /// When the model rejects the input as too long for its context, retry once
+  /// with the input cut down to fit.
+  #[serde(default)]
+  truncate_on_context_overflow: bool,
+  /// Local tools the model may call before answering.
+  #[serde(default)]
+  tools: Vec<tools::Tool>,
+  /// Select all (Ctrl+A) before copying, to run on the whole document. This
+  /// replaces the user's selection: it is put back in plain Win32 edit
+  /// controls, elsewhere everything stays selected, so a paste replaces the
+  /// whole document unless `copy_only` is set. Applies to captures that know
+  /// their action up front: action shortcuts, deep links and silent runs.
+  #[serde(default)]
+  select_all_first: bool,
+  /// Asked before every run of this action; the run waits for `confirm_run`.
+  #[serde(default)]
+  confirm_message: Option<String>,
+  /// Accelerator sent instead of Ctrl+C to copy the selection, for apps that
+  /// copy with something else, e.g. `Ctrl+Shift+C` in a terminal. Used by the
+  /// same captures as `select_all_first`.
+  #[serde(default)]
+  copy_keys: Option<String>,
+  /// Accelerator sent instead of Ctrl+V to paste this action's results, e.g.
+  /// `Shift+Insert`.
+  #[serde(default)]
+  paste_keys: Option<String>,
+  /// Diff each output against its input and log how much changed, for
+  /// "fix grammar" style actions.
+  #[serde(default)]
+  log_diff: bool,
+}
+
+/// Where an action's results go once a native run completes. Runs the
+/// window makes from the frontend always show their result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum OutputDestination {
+  /// Into the app the text was captured from.
+  Paste,
+  /// Onto the clipboard only.
+  Clipboard,
+  /// Nowhere; the window shows it.
+  Display,
+  /// Into `Action::output_path`.
+  File,
+}
+
+/// Most times `Preferences::request_retries` may resend a request.
+const MAX_REQUEST_RETRIES: u32 = 5;
+
+/// Accepted range for `Action::timeout_ms`.
+const ACTION_TIMEOUT_MS: std::ops::RangeInclusive<u64> = 1_000..=300_000;
+
+/// OpenAI's limits on request metadata.
+const MAX_METADATA_PAIRS: usize = 16;
+const MAX_METADATA_KEY_CHARS: usize = 64;
+const MAX_METADATA_VALUE_CHARS: usize = 512;
+
+/// Languages accepted for `Action::output_language`.
+const OUTPUT_LANGUAGES: &[&str] = &[
+  "Arabic",
+  "Chinese (Simplified)",
+  "Chinese (Traditional)",
+  "Dutch",
+  "English",
+  "French",
+  "German",
+  "Hindi",
+  "Indonesian",
+  "Italian",
+  "Japanese",
+  "Korean",
+  "Polish",
+  "Portuguese",
+  "Russian",
+  "Spanish",
+  "Swedish",
+  "Thai",
+  "Turkish",
+  "Ukrainian",
+  "Vietnamese",
+];
+
+/// Replaced in action prompts by the file open in the source app, if any.
+const FILE_PLACEHOLDER: &str = "{{file}}";
+
+/// Replaced in action prompts by the text before and after the selection,
+/// for "continue writing" style actions. See `focus::cursor_context`.
+const BEFORE_PLACEHOLDER: &str = "{{before}}";
+const AFTER_PLACEHOLDER: &str = "{{after}}";
+
+/// Most characters captured on each side of the selection.
+const CURSOR_CONTEXT_CHARS: usize = 2000;
+
+impl Action {
+  /// The action prompt with `{{file}}`, `{{before}}` and `{{after}}` filled
+  /// in (empty when nothing was detected), and the output language
+  /// instruction appended last so it wins over any language hints earlier in
+  /// the prompt.
+  fn system_prompt(&self, file: Option<&str>, cursor: Option<&focus::CursorContext>) -> String {
+    let (before, after) = cursor.map_or(("", ""), |cursor| (&cursor.before, &cursor.after));
+    let prompt = self
+      .prompt
+      .replace(FILE_PLACEHOLDER, file.unwrap_or_default())
+      .replace(BEFORE_PLACEHOLDER, before)
+      .replace(AFTER_PLACEHOLDER, after);
+    match self.output_language.as_deref() {
+      None => prompt,
+      Some(language) if prompt.trim().is_empty() => format!("Respond in {language}."),
+      Some(language) => format!(
+        "{}\n\nRespond in {language}, regardless of the language of the input.",
+        prompt.trim_end()
+      ),
+    }
+  }
+
+  /// `output_destination`, or for actions saved before it existed, pasting
+  /// unless `copy_only` is set.
+  fn destination(&self) -> OutputDestination {
+    match self.output_destination {
+      Some(destination) => destination,
+      None if self.copy_only => OutputDestination::Clipboard,
+      None => OutputDestination::Paste,
+    }
+  }
+
+  fn validate(&self) -> Result<(), String> {
+    if self.output_destination == Some(OutputDestination::File) {
+      let path = self.output_path.as_deref().map(str::trim).unwrap_or_default();
+      if path.is_empty() {
+        return Err(format!("{} writes its output to a file but has no file path", self.name));
+      }
+      if !Path::new(path).is_absolute() {
+        return Err(format!("Output file of {} must be an absolute path: {path}", self.name));
+      }
+    }
+    if let Some(language) = &self.output_language {
+      if !OUTPUT_LANGUAGES.iter().any(|known| known.eq_ignore_ascii_case(language)) {
+        return Err(format!("Unsupported output language for {}: {language}", self.name));
+      }
+    }
+    if self.metadata.len() > MAX_METADATA_PAIRS {
+      return Err(format!("{} has more than {MAX_METADATA_PAIRS} metadata tags", self.name));
+    }
+    for (key, value) in &self.metadata {
+      if key.is_empty()
+        || key.chars().count() > MAX_METADATA_KEY_CHARS
+        || value.chars().count() > MAX_METADATA_VALUE_CHARS
+      {
+        return Err(format!("Metadata tag {key:?} of {} is empty or too long", self.name));
+      }
+    }
+    if self.stop.iter().any(String::is_empty) {
+      return Err(format!("Stop sequences of {} can't be empty", self.name));
+    }
+    if let Some(transform) = &self.local_transform {
+      transform.validate().map_err(|error| format!("{}: {error}", self.name))?;
+    }
+    if let Some(timeout_ms) = self.timeout_ms.filter(|ms| !ACTION_TIMEOUT_MS.contains(ms)) {
+      return Err(format!(
+        "Timeout of {} must be between 1 and 300 seconds, got {timeout_ms} ms",
+        self.name
+      ));
+    }
+    for (what, keys) in [("Copy", &self.copy_keys), ("Paste", &self.paste_keys)] {
+      if let Some(keys) = keys {
+        input::parse_chord(keys).map_err(|error| format!("{what} keys of {}: {error}", self.name))?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Headers ShortcutAI sets itself; custom headers may not override them.
+const RESERVED_HEADERS: &[&str] = &["authorization", "content-type", "x-api-key"];
+
+/// Per-provider HTTP settings, keyed by provider name in setup. They apply
+/// whether the provider is primary or part of the fallback chain.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ProviderSettings {
+  /// Extra headers merged into every request, e.g. a gateway's `X-Org-Id`.
+  custom_headers: HashMap<String, String>,
+  /// Only OpenAI offers a choice.
+  openai_endpoint: provider::OpenAIEndpoint,
+  /// Sent as `OpenAI-Organization`, for keys in more than one organization.
+  openai_organization: Option<String>,
+  /// Sent as `OpenAI-Project`, to bill a project other than the key's default.
+  openai_project: Option<String>,
+  /// Read Anthropic answers as they stream in, except for actions with
+  /// tools. Ollama always streams, so no other provider takes the setting.
+  /// Streamed text comes in `action-chunk` events as it arrives; the output
+  /// still arrives whole in `action-done`.
+  stream: bool,
+}
+
+impl std::fmt::Debug for ProviderSettings {
+  // Header values may carry credentials, so only the names are printed.
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let mut names: Vec<&String> = self.custom_headers.keys().collect();
+    names.sort();
+    f.debug_struct("ProviderSettings")
+      .field("custom_headers", &names)
+      .field("openai_endpoint", &self.openai_endpoint)
+      .field("openai_organization", &self.openai_organization)
+      .field("openai_project", &self.openai_project)
+      .field("stream", &self.stream)
+      .finish()
+  }
+}
+
+impl ProviderSettings {
+  fn validate(&self, provider: &str) -> Result<(), String> {
+    if self.openai_endpoint != provider::OpenAIEndpoint::default()
+      && provider::Provider::parse(provider).ok() != Some(provider::Provider::OpenAI)
+    {
+      return Err(format!("Only OpenAI has a choice of endpoint, not {provider}"));
+    }
+    let anthropic = provider::Provider::parse(provider).ok() == Some(provider::Provider::Anthropic);
+    if self.stream && !anthropic {
+      return Err(format!("Only Anthropic has a streaming setting, not {provider}"));
+    }
+    for (name, value) in self.openai_scope() {
+      if provider::Provider::parse(provider).ok() != Some(provider::Provider::OpenAI) {
+        return Err(format!("{name} only applies to OpenAI, not {provider}"));
+      }
+      if value.contains(['\r', '\n']) {
+        return Err(format!("{name} for {provider} can't contain line breaks"));
+      }
+      reqwest::header::HeaderValue::from_str(value)
+        .map_err(|_| format!("Invalid value for header {name} of {provider}"))?;
+    }
+    for (name, value) in &self.custom_headers {
+      if RESERVED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+        return Err(format!("Header {name} for {provider} is managed by ShortcutAI"));
+      }
+      reqwest::header::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("Invalid header name for {provider}: {name}"))?;
+      reqwest::header::HeaderValue::from_str(value)
+        .map_err(|_| format!("Invalid value for header {name} of {provider}"))?;
+    }
+    Ok(())
+  }
+
+  /// The organization and project headers that are set, skipping blank ones.
+  fn openai_scope(&self) -> impl Iterator<Item = (&'static str, &str)> {
+    [("OpenAI-Organization", &self.openai_organization), ("OpenAI-Project", &self.openai_project)]
+      .into_iter()
+      .filter_map(|(name, value)| Some((name, value.as_deref()?.trim())))
+      .filter(|(_, value)| !value.is_empty())
+  }
+
+  /// Every header sent to the provider besides authentication: the custom
+  /// headers plus OpenAI's organization and project scoping.
+  fn request_headers(&self) -> HashMap<String, String> {
+    let mut headers = self.custom_headers.clone();
+    for (name, value) in self.openai_scope() {
+      headers.insert(name.to_string(), value.to_string());
+    }
+    headers
+  }
+}
+
+/// A provider in the fallback chain. Its key lives in the keyring under the
+/// provider name (see `save_provider_api_key`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProviderConfig {
+  provider: String,
+  /// Overrides the provider's default model.
+  #[serde(default)]
+  model: Option<String>,
+}
+
+/// Modifier held down while simulating the copy/paste keystrokes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "keyCode")]
+enum ShortcutModifier {
+  #[default]
+  Control,
+  Meta,
+  Alt,
+  /// Raw Windows virtual-key code, for remapped keyboards.
+  Custom(u16),
+}
+
+impl ShortcutModifier {
+  fn key(self) -> Key {
+    match self {
+      ShortcutModifier::Control => Key::Control,
+      ShortcutModifier::Meta => Key::Meta,
+      ShortcutModifier::Alt => Key::Alt,
+      ShortcutModifier::Custom(code) => Key::Raw(code),
+    }
+  }
+}
+
+/// What the main shortcut does with the captured selection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum ShortcutMode {
+  /// Show the window so the user can pick an action.
+  #[default]
+  OpenPicker,
+  /// Run the default action and paste the result without showing the window.
+  RunDefaultSilently,
+}
+
+/// Extra modifier that, added to the main shortcut, always opens the picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum PickerOverrideModifier {
+  Shift,
+  Ctrl,
+  Alt,
+}
+
+impl PickerOverrideModifier {
+  fn accelerator_token(self) -> &'static str {
+    match self {
+      PickerOverrideModifier::Shift => "Shift",
+      PickerOverrideModifier::Ctrl => "Ctrl",
+      PickerOverrideModifier::Alt => "Alt",
+    }
+  }
+
+  fn aliases(self) -> &'static [&'static str] {
+    match self {
+      PickerOverrideModifier::Shift => &["shift"],
+      PickerOverrideModifier::Ctrl => &["ctrl", "control", "cmdorctrl", "commandorcontrol"],
+      PickerOverrideModifier::Alt => &["alt", "option"],
+    }
+  }
+
+  /// The override variant of `shortcut`, or `None` when the shortcut already
+  /// uses this modifier and so can't be distinguished from it.
+  fn apply_to(self, shortcut: &str) -> Option<String> {
+    let already_used = shortcut
+      .split('+')
+      .any(|token| self.aliases().contains(&token.trim().to_ascii_lowercase().as_str()));
+    (!already_used).then(|| format!("{}+{shortcut}", self.accelerator_token()))
+  }
+}
+
+/// How the window announces a finished action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum CompletionNotice {
+  /// Restore and focus the window.
+  #[default]
+  Focus,
+  /// Show the window but leave keyboard focus where it is.
+  ShowWithoutFocus,
+  /// Only flash the taskbar button.
+  Flash,
+}
+
+/// How results get into the foreground app.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum PasteMethod {
+  /// Put the text on the clipboard and simulate Ctrl+V.
+  #[default]
+  Clipboard,
+  /// Replace the focused control's selection directly, for apps that ignore
+  /// Ctrl+V: Win32 edit controls, or UI Automation's `ValuePattern` when the
+  /// whole value is selected. Others fall back to the clipboard.
+  Direct,
+}
+
+/// Payload of `text-pasted`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TextPasted {
+  /// The method that was used, after any fallback.
+  method: PasteMethod,
+  chars: usize,
+}
+
+/// User preferences that affect native behavior.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Preferences {
+  copy_paste_modifier: ShortcutModifier,
+  /// Send C/V as virtual-key codes instead of layout characters, so the
+  /// physical key is used regardless of the active keyboard layout.
+  use_physical_keys: bool,
+  /// Auto-paste over a selection longer than this asks for confirmation first.
+  /// Zero disables the check.
+  confirm_paste_over_chars: usize,
+  /// How long a pending paste waits for confirmation before it is cancelled.
+  confirm_paste_timeout_ms: u64,
+  paste_method: PasteMethod,
+  shortcut_mode: ShortcutMode,
+  /// Holding this modifier together with the main shortcut opens the picker
+  /// even in silent mode. Global hotkeys match their modifiers exactly, so
+  /// this is implemented as a second registered accelerator.
+  picker_override_modifier: Option<PickerOverrideModifier>,
+  /// How completion is announced for actions that wait for the user.
+  /// Auto-paste completions never take focus regardless of this setting.
+  completion_notice: CompletionNotice,
+  /// Trace filter in `RUST_LOG` syntax, e.g. `info` or `shortcutai_windows=debug`.
+  /// The `RUST_LOG` environment variable overrides it.
+  log_level: String,
+  /// How long to wait for the foreground app to answer the simulated Ctrl+C.
+  capture_max_wait_ms: u64,
+  /// Put a placeholder on the clipboard before the simulated Ctrl+C and wait
+  /// for the copy to replace it. Off, the copy is noticed by the clipboard changing
+  /// instead, so clipboard managers don't record or flash the placeholder.
+  /// Without a change counter (outside Windows) that means different text:
+  /// copying what is already on the clipboard then looks like no answer.
+  capture_clear_clipboard: bool,
+  /// Show a desktop notification when a run finishes while the window is
+  /// in the background.
+  notify_on_complete: bool,
+  /// Connect to the provider at launch so the first run doesn't pay for DNS
+  /// and the TLS handshake.
+  warm_up_on_launch: bool,
+  /// Hide the window this long after an auto-paste run revealed it, unless
+  /// the user interacts with it first. `None` leaves it up.
+  auto_hide_after_paste_ms: Option<u64>,
+  /// Unregister the main shortcut after this many idle minutes, to save power
+  /// on battery. Showing the window registers it again. `None` keeps it.
+  release_shortcut_after_idle_mins: Option<u64>,
+  /// Outputs kept for actions with `cache_responses`. Zero disables the cache.
+  response_cache_size: usize,
+  /// Cached outputs older than this are not reused.
+  response_cache_ttl_secs: u64,
+  /// Closing the window hides it to the tray, keeping the shortcuts alive.
+  /// Otherwise closing it quits the app.
+  close_to_tray: bool,
+  /// Keep each native run's input in the log so it can be replayed. Off by
+  /// default, since the log otherwise holds no captured text.
+  log_inputs: bool,
+  /// Matches of these regexes in the prompt and input are replaced with
+  /// `[REDACTED]` before a run is logged, so replays see the redacted input.
+  log_redaction_patterns: Vec<String>,
+  /// Write the execution log without indentation. Setup stays pretty-printed
+  /// for hand editing.
+  compact_logs: bool,
+  /// Once the execution log file is larger than this, it moves to
+  /// `logs-archive/` and a new one starts. Zero keeps a single log trimmed to
+  /// the newest 500 entries instead.
+  log_archive_bytes: u64,
+  /// Archives kept before the oldest is deleted. Zero keeps them all.
+  log_archive_count: usize,
+  /// Provider requests with a larger body are refused. Zero disables the limit.
+  max_request_bytes: usize,
+  /// Captures longer than this emit `large-input-warning` and are never run
+  /// silently, so the UI can confirm first. Zero disables the warning.
+  large_input_warning_chars: usize,
+  /// How long a provider request may take; actions can override it.
+  request_timeout_ms: u64,
+  /// Which failures are sent again to the same provider (`request_retries`).
+  /// Fallback providers are tried on any outage regardless.
+  retry_policy: provider::RetryPolicy,
+  /// Times a request the policy allows is resent to the same provider before
+  /// moving on.
+  request_retries: u32,
+  /// Holding the main shortcut this long captures and runs the default action
+  /// silently, while a quicker tap opens the picker, regardless of
+  /// `shortcut_mode`. `None` turns the gesture off.
+  hold_to_capture_ms: Option<u64>,
+  /// Send anonymized run counts and error categories, never any text, to
+  /// `telemetry_endpoint`. Off until the user opts in.
+  telemetry_enabled: bool,
+  telemetry_endpoint: Option<String>,
+  /// Experimental: offer to run an action whenever new text is copied. Off
+  /// until the user opts in, since it reads everything they copy.
+  clipboard_watch_enabled: bool,
+  /// How often the clipboard watch checks for new text.
+  clipboard_watch_interval_ms: u64,
+}
+
+impl Default for Preferences {
+  fn default() -> Self {
+    Self {
+      copy_paste_modifier: ShortcutModifier::default(),
+      use_physical_keys: false,
+      confirm_paste_over_chars: 500,
+      confirm_paste_timeout_ms: 15_000,
+      paste_method: PasteMethod::default(),
+      shortcut_mode: ShortcutMode::default(),
+      picker_override_modifier: Some(PickerOverrideModifier::Shift),
+      completion_notice: CompletionNotice::default(),
+      log_level: "info".to_string(),
+      capture_max_wait_ms: 150,
+      capture_clear_clipboard: true,
+      notify_on_complete: false,
+      warm_up_on_launch: false,
+      auto_hide_after_paste_ms: None,
+      release_shortcut_after_idle_mins: None,
+      response_cache_size: 32,
+      response_cache_ttl_secs: 600,
+      close_to_tray: true,
+      log_inputs: false,
+      log_redaction_patterns: Vec::new(),
+      compact_logs: false,
+      log_archive_bytes: 1_000_000,
+      log_archive_count: 20,
+      max_request_bytes: 1_000_000,
+      large_input_warning_chars: 20_000,
+      request_timeout_ms: 30_000,
+      retry_policy: provider::RetryPolicy::default(),
+      request_retries: 1,
+      hold_to_capture_ms: None,
+      telemetry_enabled: false,
+      telemetry_endpoint: None,
+      clipboard_watch_enabled: false,
+      clipboard_watch_interval_ms: 1_000,
+    }
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +675,22 @@ struct SetupPayload {
   actions: Vec<Action>,
   default_action_id: Option<String>,
   setup_completed_at: String,
+  #[serde(default)]
+  preferences: Preferences,
+  /// Providers tried in order after the primary one, for actions that opt in.
+  #[serde(default)]
+  fallback_providers: Vec<ProviderConfig>,
+  #[serde(default)]
+  provider_settings: HashMap<String, ProviderSettings>,
+  /// Named sampling parameters, referenced by `Action::parameter_preset`.
+  #[serde(default)]
+  parameter_presets: HashMap<String, provider::ModelParameters>,
+  /// Opt-in: send `user_id` with requests so providers can attribute usage.
+  #[serde(default)]
+  send_user_id: bool,
+  /// Random anonymous id, generated by `save_setup` once the user opts in.
+  #[serde(default)]
+  user_id: Option<String>,
 }
 
 /// Internal structure for storing setup without API key in JSON.
@@ -50,11 +701,131 @@ struct SetupFile {
   actions: Vec<Action>,
   default_action_id: Option<String>,
   setup_completed_at: String,
+  #[serde(default)]
+  preferences: Preferences,
+  #[serde(default)]
+  fallback_providers: Vec<ProviderConfig>,
+  #[serde(default)]
+  provider_settings: HashMap<String, ProviderSettings>,
+  #[serde(default)]
+  parameter_presets: HashMap<String, provider::ModelParameters>,
+  #[serde(default)]
+  send_user_id: bool,
+  #[serde(default)]
+  user_id: Option<String>,
   /// Legacy field for backward compatibility migration.
   #[serde(skip_serializing_if = "Option::is_none")]
   api_key: Option<String>,
 }
 
+impl SetupFile {
+  fn into_payload(self, api_key: String) -> SetupPayload {
+    SetupPayload {
+      provider: self.provider,
+      api_key,
+      actions: self.actions,
+      default_action_id: self.default_action_id,
+      setup_completed_at: self.setup_completed_at,
+      preferences: self.preferences,
+      fallback_providers: self.fallback_providers,
+      provider_settings: self.provider_settings,
+      parameter_presets: self.parameter_presets,
+      send_user_id: self.send_user_id,
+      user_id: self.user_id,
+    }
+  }
+
+  /// The id to send with requests, if the user opted in.
+  fn request_user_id(&self) -> Option<&str> {
+    self.user_id.as_deref().filter(|_| self.send_user_id)
+  }
+
+  /// The parameters of the action's preset, or provider defaults without one.
+  fn parameters_for(&self, action: &Action) -> Result<provider::ModelParameters, String> {
+    match &action.parameter_preset {
+      None => Ok(provider::ModelParameters::default()),
+      Some(name) => self
+        .parameter_presets
+        .get(name)
+        .copied()
+        .ok_or_else(|| format!("{} uses unknown parameter preset {name}", action.name)),
+    }
+  }
+
+  /// Pinned actions in pin order, which is their order in `actions`.
+  fn pinned_actions(&self) -> Vec<Action> {
+    self.actions.iter().filter(|action| action.pinned).cloned().collect()
+  }
+
+  /// Checks run by `save_setup` before anything is written.
+  fn validate(&self) -> Result<(), String> {
+    redact::validate(&self.preferences.log_redaction_patterns)?;
+    if self.preferences.request_retries > MAX_REQUEST_RETRIES {
+      return Err(format!(
+        "Request retries must be at most {MAX_REQUEST_RETRIES}, got {}",
+        self.preferences.request_retries
+      ));
+    }
+    telemetry::validate(&self.preferences)?;
+    clipboard_watch::validate(&self.preferences)?;
+    for (name, parameters) in &self.parameter_presets {
+      if name.trim().is_empty() {
+        return Err("Parameter preset names can't be empty".to_string());
+      }
+      parameters.validate().map_err(|error| format!("Parameter preset {name}: {error}"))?;
+    }
+    for action in &self.actions {
+      action.validate()?;
+      self.validate_stop_count(action)?;
+      self.parameters_for(action)?;
+    }
+    for (provider, settings) in &self.provider_settings {
+      settings.validate(provider)?;
+    }
+    Ok(())
+  }
+
+  /// Check `action.stop` against every provider the action may run on.
+  fn validate_stop_count(&self, action: &Action) -> Result<(), String> {
+    let fallbacks = self.fallback_providers.iter().filter(|_| action.use_fallback);
+    let providers = std::iter::once(&self.provider)
+      .chain(fallbacks.map(|config| &config.provider))
+      .filter_map(|name| provider::Provider::parse(name).ok());
+    for provider in providers {
+      match provider.max_stop_sequences() {
+        Some(limit) if action.stop.len() > limit => {
+          return Err(format!(
+            "{} allows at most {limit} stop sequences, {} has {}",
+            provider.name(),
+            action.name,
+            action.stop.len()
+          ));
+        }
+        _ => {}
+      }
+    }
+    Ok(())
+  }
+}
+
+impl From<SetupPayload> for SetupFile {
+  fn from(setup: SetupPayload) -> Self {
+    SetupFile {
+      provider: setup.provider,
+      actions: setup.actions,
+      default_action_id: setup.default_action_id,
+      setup_completed_at: setup.setup_completed_at,
+      preferences: setup.preferences,
+      fallback_providers: setup.fallback_providers,
+      provider_settings: setup.provider_settings,
+      parameter_presets: setup.parameter_presets,
+      send_user_id: setup.send_user_id,
+      user_id: setup.user_id,
+      api_key: None, // Never store API key in JSON
+    }
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ExecutionLogEntry {
@@ -70,15 +841,211 @@ struct ExecutionLogEntry {
   output_length: u32,
   success: bool,
   error_message: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  reasoning_tokens: Option<u32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  system_fingerprint: Option<String>,
+  /// Served from the response cache without calling the provider.
+  #[serde(default)]
+  cached: bool,
+  /// The text the action ran on, kept only with `log_inputs`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  input: Option<String>,
+  /// The run failed because the provider didn't answer in time.
+  #[serde(default)]
+  timed_out: bool,
+  /// The input was too long for the model and the run was retried on a
+  /// truncated copy (`truncate_on_context_overflow`).
+  #[serde(default)]
+  truncated_retry: bool,
+  /// The run was stopped with `cancel_action`.
+  #[serde(default)]
+  cancelled: bool,
+  /// What the input looked like; see `classify`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  content: Option<classify::ContentClass>,
+  /// How much the output changed from the input, for actions with `log_diff`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  diff: Option<diff::DiffSummary>,
+  /// How the output was pasted, once it was; see `record_paste_method`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  paste_method: Option<PasteMethod>,
+}
+
+/// An auto-paste held for confirmation.
+#[derive(Debug, Clone)]
+struct PendingPaste {
+  text: String,
+  /// The action's `paste_keys`, if it has them.
+  paste_keys: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PasteConfirmRequest {
+  execution_id: String,
+  characters: usize,
+}
+
+/// Payload of `large-input-warning`: the capture is long enough to be costly.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LargeInputWarning {
+  chars: usize,
+  threshold: usize,
+}
+
+/// Payload of `capture-timed-out`: the app never answered the simulated copy.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CaptureTimedOut {
+  waited_ms: u64,
+}
+
+/// Payload of `capture-failed`: the simulated copy could not be sent.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CaptureFailed {
+  error: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ActionShortcutTriggered {
+  action_id: String,
+  /// What the capture looked like, `None` when it was blank.
+  content: Option<classify::ContentClass>,
+  text: String,
+}
+
+impl ActionShortcutTriggered {
+  fn new(action_id: String, text: String) -> Self {
+    Self { action_id, content: classify::classify(&text), text }
+  }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum AutoPasteOutcome {
+  Pasted,
+  AwaitingConfirmation,
 }
 
 #[derive(Default)]
 struct AppState {
   logs: Mutex<Vec<ExecutionLogEntry>>,
   active_shortcut: Mutex<Option<String>>,
+  /// Main shortcut plus the picker-override modifier, when registered.
+  picker_override_shortcut: Mutex<Option<String>>,
+  /// Auxiliary shortcut that jumps straight to the execution log.
+  logs_shortcut: Mutex<Option<String>>,
+  preferences: Mutex<Preferences>,
+  /// Auto-paste results waiting for the user to confirm, keyed by execution id.
+  pending_pastes: Mutex<HashMap<String, PendingPaste>>,
+  /// Accelerators bound to individual actions, keyed by action id.
+  action_shortcuts: Mutex<HashMap<String, String>>,
+  /// Output of the most recent successful run, for `get_last_output`.
+  last_output: Mutex<Option<run::LastOutput>>,
+  /// Shared HTTP client so provider calls reuse pooled connections.
+  http_client: reqwest::Client,
+  /// Keeps the non-blocking trace writer alive; dropping it flushes the file.
+  trace_guard: Mutex<Option<WorkerGuard>>,
+  /// Why tracing couldn't be set up at launch, if it couldn't.
+  trace_error: Option<String>,
+  /// Runs capture and run tasks triggered by shortcuts and deep links.
+  workers: worker::Pool,
+  /// Bumped to cancel a pending auto-hide; a timer only hides the window if
+  /// the generation it was scheduled with is still current.
+  auto_hide_generation: AtomicU64,
+  /// Recent outputs of actions that opt into caching.
+  response_cache: Mutex<cache::ResponseCache>,
+  /// File the source app had open at the last shortcut capture, guessed from
+  /// its window title. Fills `{{file}}` in prompts.
+  active_file: Mutex<Option<String>>,
+  /// Text around the selection at the last shortcut capture, when the focused
+  /// control exposes it. Fills `{{before}}` and `{{after}}`.
+  cursor_context: Mutex<Option<focus::CursorContext>>,
+  /// Tracks activity to release the main shortcut after a long idle period.
+  idle: idle::Monitor,
+  /// Action run times waiting to be written to setup.
+  usage: usage::Tracker,
+  /// Run counts waiting to be sent, when telemetry is on.
+  telemetry: telemetry::Tracker,
+  /// Native runs that haven't finished yet.
+  runs_in_flight: AtomicUsize,
+  /// Writes of the execution log file in progress, for `can_update`.
+  log_writes: AtomicUsize,
+  /// Cancellation tokens of running provider requests: action id and token,
+  /// keyed by execution id.
+  cancellations: Mutex<HashMap<String, (String, tokio_util::sync::CancellationToken)>>,
+  /// Runs waiting for `confirm_run`, keyed by execution id.
+  pending_confirmations: Mutex<HashMap<String, tokio::sync::oneshot::Sender<bool>>>,
+  /// The main window's logical size, kept across DPI changes.
+  placement: placement::Placement,
+  /// Setup edits waiting for `save_setup_debounced` to write them.
+  autosave: autosave::Saver,
+  /// The last provider response, kept while debug logging is on.
+  last_raw_response: raw_response::Slot,
+  /// Session pause of the experimental clipboard watch.
+  clipboard_watch: clipboard_watch::Watcher,
+}
+
+/// Portable config directory from `--config-dir <path>` (or `--config-dir=<path>`),
+/// falling back to the `SHORTCUTAI_CONFIG_DIR` environment variable.
+fn config_dir_override() -> Option<&'static Path> {
+  static OVERRIDE: OnceLock<Option<PathBuf>> = OnceLock::new();
+  OVERRIDE
+    .get_or_init(|| {
+      let mut args = std::env::args().skip(1);
+      while let Some(arg) = args.next() {
+        if arg == "--config-dir" {
+          return args.next().map(PathBuf::from);
+        }
+        if let Some(value) = arg.strip_prefix("--config-dir=") {
+          return Some(PathBuf::from(value));
+        }
+      }
+      std::env::var_os("SHORTCUTAI_CONFIG_DIR")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+    })
+    .as_deref()
+}
+
+fn is_writable_dir(dir: &Path) -> bool {
+  let probe = dir.join(".shortcutai-write-probe");
+  let writable = fs::write(&probe, b"").is_ok();
+  let _ = fs::remove_file(&probe);
+  writable
 }
 
+/// Directory holding `setup.json`: the portable override if given, otherwise
+/// the platform app data directory.
 fn app_data_dir(handle: &AppHandle) -> Result<PathBuf, String> {
+  if let Some(dir) = config_dir_override() {
+    // The portable directory may live on read-only media, so only create it
+    // when it doesn't exist yet.
+    if !dir.exists() {
+      fs::create_dir_all(dir)
+        .map_err(|error| format!("Failed to create config directory: {error}"))?;
+    }
+    return Ok(dir.to_path_buf());
+  }
+
+  if let Some(Some(fallback)) = DATA_DIR_FALLBACK.get() {
+    return Ok(fallback.clone());
+  }
+  platform_data_dir(handle).or_else(|error| {
+    DATA_DIR_FALLBACK.get_or_init(|| fallback_data_dir(&error)).clone().ok_or(error)
+  })
+}
+
+/// Used instead of the platform app data directory once that couldn't be
+/// resolved or created, for the rest of the session, so the app stays
+/// usable on a misconfigured system. Settings kept there may not persist.
+static DATA_DIR_FALLBACK: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+fn platform_data_dir(handle: &AppHandle) -> Result<PathBuf, String> {
   let dir = tauri::api::path::app_data_dir(&handle.config())
     .ok_or_else(|| "Unable to resolve app data directory".to_string())?;
 
@@ -88,12 +1055,59 @@ fn app_data_dir(handle: &AppHandle) -> Result<PathBuf, String> {
   Ok(dir)
 }
 
+/// The first writable of the temp directory and the executable's directory.
+fn fallback_data_dir(error: &str) -> Option<PathBuf> {
+  let exe_dir = std::env::current_exe()
+    .ok()
+    .and_then(|exe| exe.parent().map(|dir| dir.join("ShortcutAI-data")));
+  let dir = std::iter::once(std::env::temp_dir().join("ShortcutAI"))
+    .chain(exe_dir)
+    .find(|dir| fs::create_dir_all(dir).is_ok() && is_writable_dir(dir));
+  match &dir {
+    Some(dir) => {
+      warn!(error, dir = %dir.display(), "app data directory unavailable, using fallback");
+    }
+    None => warn!(error, "app data directory unavailable and no fallback is writable"),
+  }
+  dir
+}
+
+/// Directory for files the app writes continuously (logs, traces). This is
+/// the config directory unless a portable config directory is read-only, in
+/// which case a temp directory is used instead.
+fn writable_data_dir(handle: &AppHandle) -> Result<PathBuf, String> {
+  static READ_ONLY_FALLBACK: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+  let dir = app_data_dir(handle)?;
+  if config_dir_override().is_none() {
+    return Ok(dir);
+  }
+
+  let fallback = READ_ONLY_FALLBACK.get_or_init(|| {
+    if is_writable_dir(&dir) {
+      None
+    } else {
+      Some(std::env::temp_dir().join("ShortcutAI"))
+    }
+  });
+
+  match fallback {
+    Some(temp) => {
+      fs::create_dir_all(temp)
+        .map_err(|error| format!("Failed to create temp data directory: {error}"))?;
+      Ok(temp.clone())
+    }
+    None => Ok(dir),
+  }
+}
+
+/// Setup file of the active profile.
 fn setup_file_path(handle: &AppHandle) -> Result<PathBuf, String> {
-  Ok(app_data_dir(handle)?.join("setup.json"))
+  profile::setup_path(handle, &profile::active())
 }
 
 fn logs_file_path(handle: &AppHandle) -> Result<PathBuf, String> {
-  Ok(app_data_dir(handle)?.join("execution-logs.json"))
+  Ok(writable_data_dir(handle)?.join("execution-logs.json"))
 }
 
 fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Option<T>, String> {
@@ -110,116 +1124,472 @@ fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Option<T>, Str
   Ok(Some(parsed))
 }
 
-fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
-  let raw = serde_json::to_string_pretty(value)
-    .map_err(|error| format!("Failed to serialize JSON for {}: {error}", path.display()))?;
+/// Whitespace of a file written by `write_json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsonFormat {
+  Pretty,
+  Compact,
+}
+
+/// Writes to a temporary file first and renames it over `path`, so a write
+/// cut short by the process exiting never leaves a truncated file behind.
+/// Files people may edit by hand are pretty-printed; the log can be compact.
+fn write_json<T: Serialize>(path: &Path, value: &T, format: JsonFormat) -> Result<(), String> {
+  let raw = match format {
+    JsonFormat::Pretty => serde_json::to_string_pretty(value),
+    JsonFormat::Compact => serde_json::to_string(value),
+  }
+  .map_err(|error| format!("Failed to serialize JSON for {}: {error}", path.display()))?;
 
-  fs::write(path, raw)
+  let temp_path = path.with_extension("json.tmp");
+  fs::write(&temp_path, raw)
     .map_err(|error| format!("Failed to write JSON file {}: {error}", path.display()))?;
+  fs::rename(&temp_path, path)
+    .map_err(|error| format!("Failed to replace JSON file {}: {error}", path.display()))?;
 
   Ok(())
 }
 
+fn logs_json_format(handle: &AppHandle) -> JsonFormat {
+  if current_preferences(handle).compact_logs {
+    JsonFormat::Compact
+  } else {
+    JsonFormat::Pretty
+  }
+}
+
 fn load_logs_from_disk(handle: &AppHandle) -> Vec<ExecutionLogEntry> {
   match logs_file_path(handle).and_then(|path| read_json::<Vec<ExecutionLogEntry>>(&path)) {
     Ok(Some(logs)) => logs,
-    _ => Vec::new(),
+    Ok(None) => Vec::new(),
+    Err(error) => {
+      // The next recorded run overwrites the file, so keep the malformed copy
+      // around for `repair_logs`.
+      warn!(%error, "execution log is unreadable; run repair_logs to salvage it");
+      if let Ok(path) = logs_file_path(handle) {
+        if let Err(error) = fs::copy(&path, corrupt_logs_path(&path)) {
+          warn!(%error, "failed to back up unreadable execution log");
+        }
+      }
+      Vec::new()
+    }
   }
 }
 
-/// Get keyring entry for secure API key storage.
+/// Where a malformed `execution-logs.json` is copied before it is replaced.
+fn corrupt_logs_path(logs_path: &Path) -> PathBuf {
+  logs_path.with_extension("corrupt.json")
+}
+
+fn load_preferences_from_disk(handle: &AppHandle) -> Preferences {
+  match setup_file_path(handle).and_then(|path| read_json::<SetupFile>(&path)) {
+    Ok(Some(setup)) => setup.preferences,
+    _ => Preferences::default(),
+  }
+}
+
+fn current_preferences(handle: &AppHandle) -> Preferences {
+  handle
+    .state::<AppState>()
+    .preferences
+    .lock()
+    .map(|preferences| preferences.clone())
+    .unwrap_or_default()
+}
+
+/// Get keyring entry for secure API key storage, in the active profile.
 fn get_keyring_entry() -> Result<Entry, String> {
-  Entry::new("ShortcutAI", "api_key")
-    .map_err(|error| format!("Failed to access keyring: {error}"))
+  profile::keyring_entry("api_key", &profile::active())
 }
 
-/// Save API key securely to Windows Credential Manager.
+/// Save API key securely to Windows Credential Manager. Whitespace around
+/// it, as pasting often adds, is dropped.
 fn save_api_key_secure(api_key: &str) -> Result<(), String> {
   let entry = get_keyring_entry()?;
   entry
-    .set_password(api_key)
+    .set_password(api_key.trim())
     .map_err(|error| format!("Failed to save API key to keyring: {error}"))
 }
 
-/// Load API key securely from Windows Credential Manager.
+/// Load API key securely from Windows Credential Manager. Keys saved before
+/// saves trimmed them are trimmed here.
 fn load_api_key_secure() -> Result<Option<String>, String> {
   let entry = get_keyring_entry()?;
   match entry.get_password() {
-    Ok(password) => Ok(Some(password)),
+    Ok(password) => Ok(Some(password.trim().to_string())),
     Err(keyring::Error::NoEntry) => Ok(None),
     Err(error) => Err(format!("Failed to load API key from keyring: {error}")),
   }
 }
 
-/// Delete API key from Windows Credential Manager.
-#[allow(dead_code)]
-fn delete_api_key_secure() -> Result<(), String> {
-  let entry = get_keyring_entry()?;
-  match entry.delete_password() {
-    Ok(()) => Ok(()),
-    Err(keyring::Error::NoEntry) => Ok(()), // Already deleted
-    Err(error) => Err(format!("Failed to delete API key from keyring: {error}")),
-  }
+/// Keyring entry for a fallback provider's key. The primary provider's key
+/// stays in the original `api_key` entry.
+fn get_provider_keyring_entry(provider: &str) -> Result<Entry, String> {
+  profile::keyring_entry(&format!("api_key:{provider}"), &profile::active())
 }
 
-/// Capture selected text from the foreground application via Ctrl+C simulation.
-/// Returns the captured text, or an empty string if nothing was selected.
-fn capture_selected_text() -> String {
-  // Save current clipboard contents so we can restore after capture.
-  let mut board = match Clipboard::new() {
-    Ok(b) => b,
-    Err(_) => return String::new(),
-  };
-  let previous = board.get_text().unwrap_or_default();
-
-  // Clear clipboard so we can detect whether Ctrl+C produced a new value.
-  let _ = board.set_text("");
-
-  // Simulate Ctrl+C to copy the selected text.
-  let mut enigo = Enigo::new();
-  enigo.key_down(Key::Control);
-  enigo.key_click(Key::Layout('c'));
-  enigo.key_up(Key::Control);
+/// Load the key for `provider`: the primary key if it is the setup's
+/// provider, otherwise its own keyring entry.
+fn load_provider_api_key_secure(setup: &SetupFile, provider: &str) -> Result<Option<String>, String> {
+  if setup.provider == provider {
+    return load_api_key_secure();
+  }
 
-  // Wait for the target application to write to the clipboard.
-  thread::sleep(Duration::from_millis(150));
+  let entry = get_provider_keyring_entry(provider)?;
+  match entry.get_password() {
+    Ok(password) => Ok(Some(password.trim().to_string())),
+    Err(keyring::Error::NoEntry) => Ok(None),
+    Err(error) => Err(format!("Failed to load API key from keyring: {error}")),
+  }
+}
 
-  // Read the (possibly new) clipboard value.
-  let captured = board.get_text().unwrap_or_default();
+/// Store the key for a fallback provider in the Windows Credential Manager.
+#[tauri::command]
+#[instrument(skip(api_key), err)]
+fn save_provider_api_key(provider: String, api_key: String) -> Result<(), String> {
+  provider::Provider::parse(&provider)?;
+  get_provider_keyring_entry(&provider)?
+    .set_password(api_key.trim())
+    .map_err(|error| format!("Failed to save API key to keyring: {error}"))
+}
 
-  // Restore the previous clipboard content.
-  let _ = board.set_text(&previous);
+/// Whether a key is stored for `provider`, for showing "key configured"
+/// without loading it. The key itself never leaves this command.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+fn has_api_key(handle: AppHandle, provider: String) -> Result<bool, String> {
+  provider::Provider::parse(&provider)?;
+  let primary = read_json::<SetupFile>(&setup_file_path(&handle)?)?
+    .is_some_and(|setup| setup.provider == provider);
+  let entry = if primary { get_keyring_entry()? } else { get_provider_keyring_entry(&provider)? };
+  keyring_entry_exists(&entry)
+}
 
-  captured
+/// Whether `entry` holds a key. On Windows only the credential's attributes
+/// are read; other keyrings can't tell without reading the secret, which is
+/// dropped right away.
+fn keyring_entry_exists(entry: &Entry) -> Result<bool, String> {
+  let failed = |error| format!("Failed to check the keyring: {error}");
+  #[cfg(windows)]
+  if let Some(credential) =
+    entry.get_credential().downcast_ref::<keyring::windows::WinCredential>()
+  {
+    return match credential.get_credential() {
+      Ok(_) => Ok(true),
+      Err(keyring::Error::NoEntry) => Ok(false),
+      Err(error) => Err(failed(error)),
+    };
+  }
+  match entry.get_password() {
+    Ok(password) => Ok(!password.trim().is_empty()),
+    Err(keyring::Error::NoEntry) => Ok(false),
+    Err(error) => Err(failed(error)),
+  }
 }
 
+/// What `provider` supports for `model` (its default model when omitted), so
+/// the UI only shows controls that will take effect.
 #[tauri::command]
-fn check_windows_permissions(handle: AppHandle) -> PermissionStatus {
-  let probe_shortcut = "Ctrl+Shift+Alt+9";
-  let mut shortcut_manager = handle.global_shortcut_manager();
+#[instrument(err)]
+fn provider_capabilities(
+  provider: String,
+  model: Option<String>,
+) -> Result<provider::Capabilities, String> {
+  let provider = provider::Provider::parse(&provider)?;
+  let model = model.filter(|model| !model.trim().is_empty());
+  Ok(provider.capabilities(model.as_deref().unwrap_or(provider.default_model())))
+}
 
-  let global_shortcut_ready = match shortcut_manager.register(probe_shortcut, || {}) {
-    Ok(()) => {
-      let _ = shortcut_manager.unregister(probe_shortcut);
-      true
-    }
-    Err(_) => false,
-  };
+/// Why `api_key` obviously can't be a key for `provider`, or `None` if it
+/// looks right, so the UI can warn before saving or a network round trip.
+/// Only the format is checked; `validate_setup` asks the provider.
+#[tauri::command]
+#[instrument(skip(api_key), err)]
+fn check_api_key_format(provider: String, api_key: String) -> Result<Option<String>, String> {
+  Ok(provider::Provider::parse(&provider)?.key_format_warning(&api_key))
+}
 
-  let clipboard_ready = Clipboard::new().is_ok();
+/// Delete API key from Windows Credential Manager.
+fn delete_api_key_secure() -> Result<(), String> {
+  delete_keyring_entry(get_keyring_entry()?)
+}
 
-  PermissionStatus {
-    global_shortcut_ready,
-    clipboard_ready,
-    note: "Permission probe complete.".to_string(),
+fn delete_keyring_entry(entry: Entry) -> Result<(), String> {
+  match entry.delete_password() {
+    Ok(()) => Ok(()),
+    Err(keyring::Error::NoEntry) => Ok(()), // Already deleted
+    Err(error) => Err(format!("Failed to delete API key from keyring: {error}")),
   }
 }
 
+/// Remove the stored key for `provider`, e.g. when signing out or switching
+/// accounts. Clearing the primary provider's key can also blank the provider
+/// in setup so the UI returns to the key setup step.
 #[tauri::command]
-fn register_global_shortcut(
+#[instrument(skip(handle), err)]
+fn clear_api_key(
   handle: AppHandle,
-  state: State<'_, AppState>,
-  shortcut: String,
+  provider: String,
+  clear_provider: Option<bool>,
+) -> Result<(), String> {
+  delete_keyring_entry(get_provider_keyring_entry(&provider)?)?;
+
+  let path = setup_file_path(&handle)?;
+  let Some(mut setup) = read_json::<SetupFile>(&path)? else {
+    return Ok(());
+  };
+  if setup.provider != provider {
+    return Ok(());
+  }
+
+  delete_api_key_secure()?;
+  if clear_provider.unwrap_or(false) {
+    setup.provider.clear();
+    write_json(&path, &setup, JsonFormat::Pretty)?;
+  }
+  Ok(())
+}
+
+/// Outcome of simulating Ctrl+C in the foreground application.
+enum Capture {
+  /// The clipboard changed; the text is empty if nothing was selected.
+  Text(String),
+  /// The clipboard still held the sentinel, or didn't change, when the wait
+  /// ran out.
+  TimedOut,
+  /// The clipboard couldn't be opened or the copy shortcut could not be
+  /// simulated.
+  Failed(String),
+}
+
+/// Written to the clipboard before Ctrl+C so a copy of an empty selection
+/// can be told apart from an app that never answered.
+const CAPTURE_SENTINEL: &str = "\u{2063}shortcutai-capture\u{2063}";
+
+/// Capture selected text from the foreground application via Ctrl+C
+/// simulation (or the action's `copy_keys`), selecting everything first when
+/// `select_all` is set.
+fn capture_selected_text(preferences: &Preferences, options: &InputOptions) -> Capture {
+  let select_all = options.select_all;
+  let _own_write = clipboard_watch::own_write();
+  // Save current clipboard contents so we can restore after capture.
+  let board = match Clipboard::new() {
+    Ok(board) => board,
+    Err(error) => return Capture::Failed(format!("Clipboard init failed: {error}")),
+  };
+  let mut board = RestoreClipboard::new(board);
+
+  // Replace the clipboard with the sentinel so we can detect whether Ctrl+C
+  // produced a new value, or leave it be and watch for it to change.
+  let clear_clipboard = preferences.capture_clear_clipboard;
+  let sequence = clipboard::sequence_number();
+  if clear_clipboard {
+    let _ = board.board.set_text(CAPTURE_SENTINEL);
+  } else {
+    board.untouched = true;
+  }
+
+  // Keystrokes are queued in order, so the copy sees the new selection.
+  let saved_selection = select_all.then(focus::saved_selection).flatten();
+  if select_all {
+    if let Err(error) = input::send_modified_key(preferences, 'a') {
+      return Capture::Failed(error);
+    }
+  }
+
+  // Simulate the copy shortcut to copy the selected text.
+  if let Err(error) = input::send_shortcut(preferences, options.copy_keys.as_deref(), 'c') {
+    return Capture::Failed(error);
+  }
+
+  // Poll until the target application writes to the clipboard.
+  let deadline = Instant::now() + Duration::from_millis(preferences.capture_max_wait_ms);
+  let captured = loop {
+    let copied = match sequence {
+      _ if clear_clipboard => Some(clipboard::read_text(&mut board.board))
+        .filter(|read| !matches!(read, Ok(text) if text == CAPTURE_SENTINEL)),
+      Some(sequence) if clipboard::sequence_number() == Some(sequence) => None,
+      Some(_) => Some(clipboard::read_text(&mut board.board)),
+      None => clipboard::read_text(&mut board.board)
+        .ok()
+        .filter(|text| board.previous.as_ref() != Some(text))
+        .map(Ok),
+    };
+    match copied {
+      Some(Ok(text)) => break Capture::Text(text),
+      // Reading fails when the clipboard holds no text, e.g. after copying
+      // an empty selection in some apps.
+      Some(Err(arboard::Error::ContentNotAvailable)) => break Capture::Text(String::new()),
+      _ if Instant::now() >= deadline => break Capture::TimedOut,
+      _ => thread::sleep(Duration::from_millis(10)),
+    }
+  };
+  if matches!(captured, Capture::Text(_)) {
+    board.untouched = false;
+  }
+
+  // Restore the previous clipboard content.
+  drop(board);
+
+  match saved_selection {
+    Some(selection) => focus::restore_selection(selection),
+    None if select_all => warn!("select-all capture left the whole document selected"),
+    None => {}
+  }
+  captured
+}
+
+/// The clipboard during a capture. Dropping it puts back the text that was
+/// there before, so every way out of a capture restores it, early returns
+/// and panics included. Quitting waits for it; see `shutdown`.
+struct RestoreClipboard {
+  board: Clipboard,
+  /// `None` when the clipboard held no text, which leaves it empty.
+  previous: Option<String>,
+  /// Nothing was written to the clipboard, so there is nothing to put back.
+  untouched: bool,
+}
+
+impl RestoreClipboard {
+  fn new(mut board: Clipboard) -> Self {
+    let previous = board.get_text().ok();
+    Self { board, previous, untouched: false }
+  }
+}
+
+impl Drop for RestoreClipboard {
+  fn drop(&mut self) {
+    if self.untouched {
+      return;
+    }
+    let restored = match self.previous.take() {
+      Some(previous) => self.board.set_text(previous),
+      None => self.board.clear(),
+    };
+    if let Err(error) = restored {
+      warn!(%error, "failed to restore the clipboard after a capture");
+    }
+  }
+}
+
+/// Capture the selection, emitting `capture-timed-out` if the app never
+/// answered so the UI can suggest raising `captureMaxWaitMs`, and
+/// `capture-failed` if the copy shortcut could not be sent at all.
+fn capture_or_report(
+  handle: &AppHandle,
+  preferences: &Preferences,
+  options: &InputOptions,
+) -> Option<String> {
+  match capture_selected_text(preferences, options) {
+    Capture::Text(text) => Some(text),
+    Capture::TimedOut => {
+      let waited_ms = preferences.capture_max_wait_ms;
+      warn!(waited_ms, "timed out waiting for the copied selection");
+      let _ = handle.emit_all("capture-timed-out", CaptureTimedOut { waited_ms });
+      None
+    }
+    Capture::Failed(error) => {
+      warn!(%error, "failed to simulate the copy shortcut");
+      let _ = handle.emit_all("capture-failed", CaptureFailed { error });
+      None
+    }
+  }
+}
+
+#[tauri::command]
+#[instrument(skip_all)]
+fn check_windows_permissions(handle: AppHandle) -> PermissionStatus {
+  let probe_shortcut = "Ctrl+Shift+Alt+9";
+  let mut shortcut_manager = handle.global_shortcut_manager();
+
+  let global_shortcut_ready = match shortcut_manager.register(probe_shortcut, || {}) {
+    Ok(()) => {
+      let _ = shortcut_manager.unregister(probe_shortcut);
+      true
+    }
+    Err(_) => false,
+  };
+
+  let (clipboard_read_ready, clipboard_write_ready) = probe_clipboard();
+  let clipboard_ready = clipboard_read_ready && clipboard_write_ready;
+  let input_simulation_ready = match input::availability() {
+    Ok(()) => true,
+    Err(error) => {
+      warn!(%error, "keyboard simulation unavailable");
+      false
+    }
+  };
+  let keyring_ready = get_keyring_entry()
+    .map(|entry| matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)))
+    .unwrap_or(false);
+  let data_dir_hint = match app_data_dir(&handle) {
+    Ok(dir) if DATA_DIR_FALLBACK.get().is_some_and(Option::is_some) => Some(format!(
+      "The app data directory is unavailable, so settings are kept in {}, where they may not \
+       persist. Check that your user profile folder exists and is writable.",
+      dir.display()
+    )),
+    Ok(_) => None,
+    Err(error) => Some(format!("{error}. Settings can't be saved anywhere.")),
+  };
+  info!(
+    global_shortcut_ready,
+    clipboard_read_ready,
+    clipboard_write_ready,
+    input_simulation_ready,
+    keyring_ready,
+    data_dir_ready = data_dir_hint.is_none(),
+    "permission probe complete"
+  );
+
+  PermissionStatus {
+    global_shortcut_ready,
+    clipboard_ready,
+    global_shortcut: PermissionCheck::new(
+      global_shortcut_ready,
+      "Another application may own the hotkey hook. Close other hotkey tools and re-check.",
+    ),
+    clipboard_read: PermissionCheck::new(
+      clipboard_read_ready,
+      "The clipboard is locked by another application. Close clipboard managers and re-check.",
+    ),
+    clipboard_write: PermissionCheck::new(
+      clipboard_write_ready,
+      "The clipboard can't be written. Close clipboard managers or remote-desktop tools and re-check.",
+    ),
+    input_simulation: PermissionCheck::new(
+      input_simulation_ready,
+      "Keyboard simulation is blocked. Close any administrator prompt or unlock the desktop. If the target app runs as administrator, run ShortcutAI as administrator too.",
+    ),
+    keyring: PermissionCheck::new(
+      keyring_ready,
+      "Windows Credential Manager is unavailable. Make sure the Credential Manager service is running.",
+    ),
+    data_dir: PermissionCheck { ready: data_dir_hint.is_none(), hint: data_dir_hint },
+    note: "Permission probe complete.".to_string(),
+  }
+}
+
+/// Probe clipboard read and write access without disturbing its contents.
+/// Writing is only tested by putting back the text that was just read; a
+/// clipboard without text is considered writable once it opens.
+fn probe_clipboard() -> (bool, bool) {
+  let Ok(mut board) = Clipboard::new() else {
+    return (false, false);
+  };
+
+  match board.get_text() {
+    Ok(previous) => (true, board.set_text(previous).is_ok()),
+    Err(arboard::Error::ContentNotAvailable) => (true, true),
+    Err(_) => (false, true),
+  }
+}
+
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+fn register_global_shortcut(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  shortcut: String,
 ) -> Result<(), String> {
   let normalized = shortcut.trim().to_string();
   if normalized.is_empty() {
@@ -233,31 +1603,344 @@ fn register_global_shortcut(
 
   let mut shortcut_manager = handle.global_shortcut_manager();
 
+  // A shortcut released while idle has to be registered again even if unchanged.
+  let released = state.idle.take_released();
+  if let Some(previous) = registered.as_ref() {
+    if previous == &normalized && !released {
+      return Ok(());
+    }
+  }
+
+  if let Some(action_id) = action_bound_to(&state, &normalized)? {
+    return Err(format!("Shortcut {normalized} is already bound to action {action_id}"));
+  }
+  if is_logs_shortcut(&state, &normalized)? {
+    return Err(format!("Shortcut {normalized} is already used to show the logs"));
+  }
+
+  if let Some(previous) = registered.as_ref() {
+    let _ = shortcut_manager.unregister(previous);
+  }
+
+  register_main_shortcut(&handle, &state, &normalized)?;
+
+  *registered = Some(normalized);
+  Ok(())
+}
+
+/// Register the main shortcut's accelerator and its picker-override variant,
+/// without touching `active_shortcut`.
+fn register_main_shortcut(
+  handle: &AppHandle,
+  state: &AppState,
+  shortcut: &str,
+) -> Result<(), String> {
+  let app_handle = handle.clone();
+  handle
+    .global_shortcut_manager()
+    .register(shortcut, move || spawn_capture(app_handle.clone(), CaptureTarget::Main))
+    .map_err(|error| format!("Failed to register shortcut: {error}"))?;
+
+  register_picker_override(handle, state, shortcut)
+}
+
+/// Counterpart of `register_main_shortcut`.
+fn unregister_main_shortcut(
+  handle: &AppHandle,
+  state: &AppState,
+  shortcut: &str,
+) -> Result<(), String> {
+  let mut shortcut_manager = handle.global_shortcut_manager();
+  shortcut_manager
+    .unregister(shortcut)
+    .map_err(|error| format!("Failed to unregister shortcut: {error}"))?;
+
+  if let Some(variant) = state
+    .picker_override_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?
+    .take()
+  {
+    let _ = shortcut_manager.unregister(&variant);
+  }
+  Ok(())
+}
+
+/// (Re)register the picker-override variant of the main shortcut. Failure to
+/// register it is logged but doesn't fail the main registration.
+fn register_picker_override(
+  handle: &AppHandle,
+  state: &AppState,
+  main_shortcut: &str,
+) -> Result<(), String> {
+  let mut override_shortcut = state
+    .picker_override_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+
+  let mut shortcut_manager = handle.global_shortcut_manager();
+  if let Some(previous) = override_shortcut.take() {
+    let _ = shortcut_manager.unregister(&previous);
+  }
+
+  let Some(variant) = current_preferences(handle)
+    .picker_override_modifier
+    .and_then(|modifier| modifier.apply_to(main_shortcut))
+  else {
+    return Ok(());
+  };
+
+  let app_handle = handle.clone();
+  match shortcut_manager.register(&variant, move || {
+    spawn_capture(app_handle.clone(), CaptureTarget::Picker)
+  }) {
+    Ok(()) => *override_shortcut = Some(variant),
+    Err(error) => warn!(%variant, %error, "failed to register picker override shortcut"),
+  }
+  Ok(())
+}
+
+/// What a shortcut press should do with the captured selection.
+enum CaptureTarget {
+  /// The main shortcut: behavior follows the configured `ShortcutMode`.
+  Main,
+  /// Always show the action picker.
+  Picker,
+  /// Run the default action silently, whatever the `ShortcutMode`.
+  DefaultAction,
+  /// Hand the selection to one action.
+  Action(String),
+}
+
+/// Capture the selection on a shortcut worker and hand it to the frontend, or
+/// run the default action directly in silent mode. Capturing for a specific
+/// action emits `action-shortcut-triggered` so the UI can run it directly
+/// instead of showing the picker.
+/// Presses arriving while the worker pool is saturated are dropped.
+fn spawn_capture(handle: AppHandle, target: CaptureTarget) {
+  handle.state::<AppState>().idle.touch();
+  let workers = &handle.state::<AppState>().workers;
+  let submitted = workers.submit({
+    let handle = handle.clone();
+    move || capture_and_dispatch(handle, target)
+  });
+  if let Err(error) = submitted {
+    warn!(%error, "ignoring shortcut press");
+  }
+}
+
+fn capture_and_dispatch(handle: AppHandle, target: CaptureTarget) {
+  let preferences = current_preferences(&handle);
+  let target = match target {
+    CaptureTarget::Main => match main_gesture(&handle, &preferences) {
+      Some(hold::Gesture::Tap) => CaptureTarget::Picker,
+      Some(hold::Gesture::Hold) => CaptureTarget::DefaultAction,
+      Some(hold::Gesture::Repeat) => return,
+      None => CaptureTarget::Main,
+    },
+    target => target,
+  };
+
+  // Capture selected text while the original app still has focus.
+  if let Ok(mut file) = handle.state::<AppState>().active_file.lock() {
+    *file = focus::foreground_window_title().and_then(|title| focus::file_from_title(&title));
+  }
+  if let Ok(mut cursor) = handle.state::<AppState>().cursor_context.lock() {
+    *cursor = focus::cursor_context(CURSOR_CONTEXT_CHARS);
+  }
+  let options = capture_options(&handle, &target, &preferences);
+  let text = capture_or_report(&handle, &preferences, &options).unwrap_or_default();
+  debug!(chars = text.len(), "shortcut fired, captured selection");
+
+  let threshold = preferences.large_input_warning_chars;
+  let chars = text.chars().count();
+  let large = threshold > 0 && chars > threshold;
+  if large {
+    warn!(chars, threshold, "captured a large selection");
+    let _ = handle.emit_all("large-input-warning", LargeInputWarning { chars, threshold });
+  }
+
+  match target {
+    CaptureTarget::Main
+      if preferences.shortcut_mode == ShortcutMode::RunDefaultSilently
+        && !text.is_empty()
+        && !large =>
+    {
+      run_default_silently(&handle, text);
+      return;
+    }
+    CaptureTarget::DefaultAction if !text.is_empty() && !large => {
+      run_default_silently(&handle, text);
+      return;
+    }
+    CaptureTarget::Action(action_id) => {
+      let _ = handle.emit_all(
+        "action-shortcut-triggered",
+        ActionShortcutTriggered::new(action_id, text),
+      );
+    }
+    CaptureTarget::Main | CaptureTarget::Picker | CaptureTarget::DefaultAction => {
+      // Emit the captured text to the frontend.
+      let _ = handle.emit_all("text-captured", &text);
+    }
+  }
+
+  // Bring the ShortcutAI window into view.
+  show_main_window(&handle);
+}
+
+/// How an action's captures and pastes drive the foreground app.
+#[derive(Debug, Clone, Default)]
+struct InputOptions {
+  select_all: bool,
+  copy_keys: Option<String>,
+  paste_keys: Option<String>,
+}
+
+/// Input options of the action a capture goes to. The picker's action is
+/// only chosen after the capture, so picker captures use the defaults.
+fn capture_options(
+  handle: &AppHandle,
+  target: &CaptureTarget,
+  preferences: &Preferences,
+) -> InputOptions {
+  let silent_main = preferences.shortcut_mode == ShortcutMode::RunDefaultSilently;
+  let action_id = match target {
+    CaptureTarget::Action(action_id) => Some(action_id.clone()),
+    CaptureTarget::DefaultAction => run::default_action_id(handle).ok(),
+    CaptureTarget::Main if silent_main => run::default_action_id(handle).ok(),
+    CaptureTarget::Main | CaptureTarget::Picker => None,
+  };
+  action_id.map(|action_id| input_options(handle, &action_id)).unwrap_or_default()
+}
+
+/// Defaults for an unknown action. Blank key overrides count as unset.
+fn input_options(handle: &AppHandle, action_id: &str) -> InputOptions {
+  let keys = |keys: &Option<String>| keys.clone().filter(|keys| !keys.trim().is_empty());
+  setup_file_path(handle)
+    .and_then(|path| read_json::<SetupFile>(&path))
+    .ok()
+    .flatten()
+    .and_then(|setup| setup.actions.into_iter().find(|action| action.id == action_id))
+    .map(|action| InputOptions {
+      select_all: action.select_all_first,
+      copy_keys: keys(&action.copy_keys),
+      paste_keys: keys(&action.paste_keys),
+    })
+    .unwrap_or_default()
+}
+
+/// Time the main shortcut's press when `hold_to_capture_ms` is set.
+fn main_gesture(handle: &AppHandle, preferences: &Preferences) -> Option<hold::Gesture> {
+  let threshold = preferences.hold_to_capture_ms?;
+  let shortcut = handle.state::<AppState>().active_shortcut.lock().ok()?.clone()?;
+  hold::classify(&shortcut, Duration::from_millis(threshold))
+}
+
+/// Capture + run + paste without showing the picker. Failures flash the
+/// window; the `action-done` event carries the details.
+fn run_default_silently(handle: &AppHandle, text: String) {
+  let replaced_length = text.chars().count();
+  let result = run::default_action_id(handle).and_then(|action_id| {
+    tauri::async_runtime::block_on(run::run_action(handle, &action_id, text))
+      .map(|run| (action_id, run))
+  });
+
+  let outcome = result.and_then(|(action_id, run)| match run.output_destination {
+    OutputDestination::Paste => {
+      let paste_keys = input_options(handle, &action_id).paste_keys;
+      paste_or_confirm(handle, run.execution_id, run.output, replaced_length, paste_keys)
+        .map(|_| ())
+    }
+    // Already delivered by `run_action`.
+    OutputDestination::Clipboard | OutputDestination::File => {
+      reveal_main_window(handle, CompletionNotice::Flash);
+      Ok(())
+    }
+    OutputDestination::Display => {
+      show_main_window(handle);
+      Ok(())
+    }
+  });
+  if let Err(error) = outcome {
+    warn!(%error, "silent run failed");
+    reveal_main_window(handle, CompletionNotice::Flash);
+  }
+}
+
+/// Return the action already bound to `shortcut`, if any.
+fn action_bound_to(state: &AppState, shortcut: &str) -> Result<Option<String>, String> {
+  let bindings = state
+    .action_shortcuts
+    .lock()
+    .map_err(|_| "Failed to lock action shortcut state".to_string())?;
+
+  Ok(
+    bindings
+      .iter()
+      .find(|(_, accelerator)| accelerator.eq_ignore_ascii_case(shortcut))
+      .map(|(action_id, _)| action_id.clone()),
+  )
+}
+
+fn is_logs_shortcut(state: &AppState, shortcut: &str) -> Result<bool, String> {
+  Ok(
+    state
+      .logs_shortcut
+      .lock()
+      .map_err(|_| "Failed to lock shortcut state".to_string())?
+      .as_ref()
+      .is_some_and(|logs| logs.eq_ignore_ascii_case(shortcut)),
+  )
+}
+
+/// Bind a global shortcut that shows the window on the execution log by
+/// emitting `show-logs`. It never captures the selection.
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+fn register_logs_shortcut(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  shortcut: String,
+) -> Result<(), String> {
+  let normalized = shortcut.trim().to_string();
+  if normalized.is_empty() {
+    return Err("Shortcut cannot be empty".to_string());
+  }
+
+  // Checked before taking the logs lock: `register_global_shortcut` holds the
+  // main shortcut lock while it looks at this one.
+  let main_shortcut = state
+    .active_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?
+    .clone();
+  if main_shortcut.is_some_and(|main| main.eq_ignore_ascii_case(&normalized)) {
+    return Err(format!("Shortcut {normalized} is already used as the main shortcut"));
+  }
+  if let Some(action_id) = action_bound_to(&state, &normalized)? {
+    return Err(format!("Shortcut {normalized} is already bound to action {action_id}"));
+  }
+
+  let mut registered = state
+    .logs_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+
+  let mut shortcut_manager = handle.global_shortcut_manager();
   if let Some(previous) = registered.as_ref() {
     if previous == &normalized {
       return Ok(());
     }
     let _ = shortcut_manager.unregister(previous);
+    *registered = None;
   }
 
   let app_handle = handle.clone();
   shortcut_manager
     .register(&normalized, move || {
-      let h = app_handle.clone();
-      thread::spawn(move || {
-        // Capture selected text while the original app still has focus.
-        let text = capture_selected_text();
-
-        // Emit the captured text to the frontend.
-        let _ = h.emit_all("text-captured", &text);
-
-        // Bring the ShortcutAI window into view.
-        if let Some(window) = h.get_window("main") {
-          let _ = window.show();
-          let _ = window.unminimize();
-          let _ = window.set_focus();
-        }
-      });
+      let _ = app_handle.emit_all("show-logs", ());
+      show_main_window(&app_handle);
     })
     .map_err(|error| format!("Failed to register shortcut: {error}"))?;
 
@@ -266,6 +1949,129 @@ fn register_global_shortcut(
 }
 
 #[tauri::command]
+#[instrument(skip_all, err)]
+fn unregister_logs_shortcut(handle: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+  let mut registered = state
+    .logs_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+
+  let Some(existing) = registered.clone() else {
+    return Ok(());
+  };
+
+  handle
+    .global_shortcut_manager()
+    .unregister(&existing)
+    .map_err(|error| format!("Failed to unregister shortcut: {error}"))?;
+
+  *registered = None;
+  Ok(())
+}
+
+/// Bind a global shortcut that captures the selection for one action.
+/// Rebinding an action replaces its previous accelerator.
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+fn register_action_shortcut(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  action_id: String,
+  shortcut: String,
+) -> Result<(), String> {
+  let normalized = shortcut.trim().to_string();
+  if normalized.is_empty() {
+    return Err("Shortcut cannot be empty".to_string());
+  }
+
+  let main_shortcut = state
+    .active_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?
+    .clone();
+  if main_shortcut.is_some_and(|main| main.eq_ignore_ascii_case(&normalized)) {
+    return Err(format!("Shortcut {normalized} is already used as the main shortcut"));
+  }
+  if is_logs_shortcut(&state, &normalized)? {
+    return Err(format!("Shortcut {normalized} is already used to show the logs"));
+  }
+
+  let mut bindings = state
+    .action_shortcuts
+    .lock()
+    .map_err(|_| "Failed to lock action shortcut state".to_string())?;
+
+  if let Some((other, _)) = bindings
+    .iter()
+    .find(|(id, accelerator)| *id != &action_id && accelerator.eq_ignore_ascii_case(&normalized))
+  {
+    return Err(format!("Shortcut {normalized} is already bound to action {other}"));
+  }
+
+  let mut shortcut_manager = handle.global_shortcut_manager();
+  if let Some(previous) = bindings.get(&action_id) {
+    if previous == &normalized {
+      return Ok(());
+    }
+    let _ = shortcut_manager.unregister(previous);
+    bindings.remove(&action_id);
+  }
+
+  let app_handle = handle.clone();
+  let bound_action_id = action_id.clone();
+  shortcut_manager
+    .register(&normalized, move || {
+      spawn_capture(app_handle.clone(), CaptureTarget::Action(bound_action_id.clone()))
+    })
+    .map_err(|error| format!("Failed to register shortcut: {error}"))?;
+
+  bindings.insert(action_id, normalized);
+  Ok(())
+}
+
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+fn unregister_action_shortcut(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  action_id: String,
+) -> Result<(), String> {
+  let mut bindings = state
+    .action_shortcuts
+    .lock()
+    .map_err(|_| "Failed to lock action shortcut state".to_string())?;
+
+  let Some(existing) = bindings.get(&action_id).cloned() else {
+    return Ok(());
+  };
+
+  handle
+    .global_shortcut_manager()
+    .unregister(&existing)
+    .map_err(|error| format!("Failed to unregister shortcut: {error}"))?;
+
+  bindings.remove(&action_id);
+  Ok(())
+}
+
+/// Return the accelerator currently bound to an action, or `None` if unbound.
+#[tauri::command]
+fn get_action_shortcut(
+  state: State<'_, AppState>,
+  action_id: String,
+) -> Result<Option<String>, String> {
+  Ok(
+    state
+      .action_shortcuts
+      .lock()
+      .map_err(|_| "Failed to lock action shortcut state".to_string())?
+      .get(&action_id)
+      .cloned(),
+  )
+}
+
+#[tauri::command]
+#[instrument(skip_all, err)]
 fn unregister_global_shortcut(
   handle: AppHandle,
   state: State<'_, AppState>,
@@ -279,39 +2085,422 @@ fn unregister_global_shortcut(
     return Ok(());
   };
 
-  let mut shortcut_manager = handle.global_shortcut_manager();
-  shortcut_manager
-    .unregister(&existing)
-    .map_err(|error| format!("Failed to unregister shortcut: {error}"))?;
+  // Already released while idle.
+  if !state.idle.take_released() {
+    unregister_main_shortcut(&handle, &state, &existing)?;
+  }
+
+  *registered = None;
+  Ok(())
+}
+
+/// Register the main shortcut afresh, e.g. after hotkeys stopped responding.
+/// Returns whether there was a shortcut to re-assert.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+fn reassert_shortcut(handle: AppHandle) -> Result<bool, String> {
+  health::reassert(&handle, "requested")
+}
+
+/// Every accelerator ShortcutAI believes it holds.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RegisteredShortcuts {
+  main: Option<String>,
+  /// `false` if the main shortcut was released while idle.
+  main_active: bool,
+  picker_override: Option<String>,
+  logs: Option<String>,
+  /// Keyed by action id.
+  actions: HashMap<String, String>,
+}
+
+#[tauri::command]
+#[instrument(skip_all, err)]
+fn list_registered_shortcuts(state: State<'_, AppState>) -> Result<RegisteredShortcuts, String> {
+  let main = state
+    .active_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+  let actions = state
+    .action_shortcuts
+    .lock()
+    .map_err(|_| "Failed to lock action shortcut state".to_string())?;
+  let logs = state
+    .logs_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+  let picker_override = state
+    .picker_override_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+
+  Ok(RegisteredShortcuts {
+    main: main.clone(),
+    main_active: main.is_some() && !state.idle.is_released(),
+    picker_override: picker_override.clone(),
+    logs: logs.clone(),
+    actions: actions.clone(),
+  })
+}
+
+/// Drop every global shortcut, including any this process registered but lost
+/// track of, and clear the bookkeeping. For recovering from stuck hotkeys;
+/// the UI registers the configured shortcuts again afterwards.
+#[tauri::command]
+#[instrument(skip_all, err)]
+fn unregister_all_shortcuts(handle: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+  // Same lock order as `register_global_shortcut`.
+  let mut main = state
+    .active_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+  let mut actions = state
+    .action_shortcuts
+    .lock()
+    .map_err(|_| "Failed to lock action shortcut state".to_string())?;
+  let mut logs = state
+    .logs_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+  let mut picker_override = state
+    .picker_override_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?;
+
+  handle
+    .global_shortcut_manager()
+    .unregister_all()
+    .map_err(|error| format!("Failed to unregister shortcuts: {error}"))?;
+
+  info!(
+    main = ?main.take(),
+    actions = actions.drain().count(),
+    logs = ?logs.take(),
+    picker_override = ?picker_override.take(),
+    "unregistered all shortcuts"
+  );
+  state.idle.take_released();
+  Ok(())
+}
+
+fn show_main_window(handle: &AppHandle) {
+  if let Some(window) = handle.get_window("main") {
+    placement::fit_to_monitor(&window, &handle.state::<AppState>().placement);
+    let _ = window.show();
+    let _ = window.unminimize();
+    let _ = window.set_focus();
+  }
+}
+
+/// Announce a result without necessarily stealing focus from the user.
+fn reveal_main_window(handle: &AppHandle, notice: CompletionNotice) {
+  let Some(window) = handle.get_window("main") else {
+    return;
+  };
+
+  match notice {
+    CompletionNotice::Focus => show_main_window(handle),
+    CompletionNotice::ShowWithoutFocus => {
+      // A minimized window can't be shown without restoring (and activating)
+      // it, so fall back to flashing the taskbar button.
+      if window.is_minimized().unwrap_or(false) {
+        let _ = window.request_user_attention(Some(UserAttentionType::Informational));
+      } else {
+        let _ = window.show();
+      }
+    }
+    CompletionNotice::Flash => {
+      let _ = window.request_user_attention(Some(UserAttentionType::Informational));
+    }
+  }
+}
+
+/// Returned instead of pasting when the focused control is a password field,
+/// so the UI can tell this refusal apart from other paste failures.
+const PASSWORD_FIELD_ERROR: &str = "Refusing to paste into a password field";
+
+/// Write `text` to the clipboard, then simulate Ctrl+V to paste it into the
+/// foreground application, unless `paste_method` is `Direct` and the
+/// focused control takes the text without the clipboard. Emits
+/// `text-pasted` with the method that was used and records it on the log
+/// entry of `execution_id`.
+fn paste_into_foreground(
+  handle: &AppHandle,
+  text: &str,
+  paste_keys: Option<&str>,
+  execution_id: Option<&str>,
+) -> Result<PasteMethod, String> {
+  let preferences = current_preferences(handle);
+  if focus::is_password_field_focused() {
+    warn!("focused control is a password field, not pasting");
+    return Err(PASSWORD_FIELD_ERROR.to_string());
+  }
+
+  let method = match preferences.paste_method {
+    PasteMethod::Direct if focus::replace_selection(text) => PasteMethod::Direct,
+    PasteMethod::Direct => {
+      debug!("focused control can't take text directly, pasting instead");
+      paste_with_clipboard(&preferences, text, paste_keys)?;
+      PasteMethod::Clipboard
+    }
+    PasteMethod::Clipboard => {
+      paste_with_clipboard(&preferences, text, paste_keys)?;
+      PasteMethod::Clipboard
+    }
+  };
+  let chars = text.chars().count();
+  info!(?method, chars, "pasted result");
+  let _ = handle.emit_all("text-pasted", TextPasted { method, chars });
+  if let Some(execution_id) = execution_id {
+    if let Err(error) = record_paste_method(handle, execution_id, method) {
+      warn!(%error, "failed to record the paste method");
+    }
+  }
+  Ok(method)
+}
+
+fn paste_with_clipboard(
+  preferences: &Preferences,
+  text: &str,
+  paste_keys: Option<&str>,
+) -> Result<(), String> {
+  let _own_write = clipboard_watch::own_write();
+  let mut board =
+    Clipboard::new().map_err(|error| format!("Clipboard init failed: {error}"))?;
+
+  board
+    .set_text(text)
+    .map_err(|error| format!("Clipboard write failed: {error}"))?;
+
+  // Small delay to let the clipboard settle before simulating the paste.
+  thread::sleep(Duration::from_millis(80));
+
+  input::send_shortcut(preferences, paste_keys, 'v')
+}
+
+/// Hide the window, give focus back to the previous application, then paste.
+fn hide_and_paste(
+  handle: &AppHandle,
+  text: &str,
+  paste_keys: Option<&str>,
+  execution_id: Option<&str>,
+) -> Result<PasteMethod, String> {
+  if let Some(window) = handle.get_window("main") {
+    window
+      .hide()
+      .map_err(|error| format!("Failed to hide window: {error}"))?;
+  }
+  thread::sleep(Duration::from_millis(200));
+  paste_into_foreground(handle, text, paste_keys, execution_id)
+}
+
+/// Put `text` on the clipboard without pasting. The previous clipboard content
+/// is intentionally not restored: keeping the result there is the point.
+#[tauri::command]
+#[instrument(skip_all, fields(chars = text.len()), err)]
+fn copy_to_clipboard(text: String) -> Result<(), String> {
+  let _own_write = clipboard_watch::own_write();
+  Clipboard::new()
+    .and_then(|mut board| board.set_text(text))
+    .map_err(|error| format!("Clipboard write failed: {error}"))
+}
+
+/// Write `text` to the clipboard, then simulate Ctrl+V to paste it into the
+/// foreground application.  The window must have been hidden or blurred first
+/// so that the original application receives the paste event. With
+/// `action_id`, that action's `paste_keys` are used. With `target_window`,
+/// the first window whose title contains it, ignoring case, is brought to
+/// the front and pasted into instead; no match is an error. With
+/// `execution_id`, the method used is recorded on that run's log entry.
+#[tauri::command]
+#[instrument(skip_all, fields(chars = text.len()), err)]
+fn paste_text(
+  handle: AppHandle,
+  text: String,
+  action_id: Option<String>,
+  target_window: Option<String>,
+  execution_id: Option<String>,
+) -> Result<PasteMethod, String> {
+  let paste_keys = action_id.and_then(|action_id| input_options(&handle, &action_id).paste_keys);
+  if let Some(query) = target_window.filter(|query| !query.trim().is_empty()) {
+    let title = focus::focus_window_titled(&query)?;
+    debug!(%title, "focused target window");
+    // Give the window a moment to take keyboard focus.
+    thread::sleep(Duration::from_millis(100));
+  }
+  paste_into_foreground(&handle, &text, paste_keys.as_deref(), execution_id.as_deref())
+}
+
+/// Paste a result over the captured selection without user interaction.
+/// Replacing a selection longer than the configured threshold instead shows
+/// the window and emits `paste-confirm-required`; the paste then waits for
+/// `confirm_paste` and is cancelled if not confirmed in time. With
+/// `action_id`, that action's `paste_keys` are used.
+#[tauri::command]
+#[instrument(skip(handle, text), err)]
+fn auto_paste(
+  handle: AppHandle,
+  execution_id: String,
+  text: String,
+  replaced_length: usize,
+  action_id: Option<String>,
+) -> Result<AutoPasteOutcome, String> {
+  let paste_keys = action_id.and_then(|action_id| input_options(&handle, &action_id).paste_keys);
+  paste_or_confirm(&handle, execution_id, text, replaced_length, paste_keys)
+}
+
+fn paste_or_confirm(
+  handle: &AppHandle,
+  execution_id: String,
+  text: String,
+  replaced_length: usize,
+  paste_keys: Option<String>,
+) -> Result<AutoPasteOutcome, String> {
+  let preferences = current_preferences(handle);
+  let threshold = preferences.confirm_paste_over_chars;
+  if threshold == 0 || replaced_length <= threshold {
+    hide_and_paste(handle, &text, paste_keys.as_deref(), Some(&execution_id))?;
+    return Ok(AutoPasteOutcome::Pasted);
+  }
+
+  handle
+    .state::<AppState>()
+    .pending_pastes
+    .lock()
+    .map_err(|_| "Failed to lock pending paste state".to_string())?
+    .insert(execution_id.clone(), PendingPaste { text, paste_keys });
+
+  show_main_window(handle);
+  let _ = handle.emit_all(
+    "paste-confirm-required",
+    PasteConfirmRequest {
+      execution_id: execution_id.clone(),
+      characters: replaced_length,
+    },
+  );
+
+  let timeout = Duration::from_millis(preferences.confirm_paste_timeout_ms);
+  let app_handle = handle.clone();
+  thread::spawn(move || {
+    thread::sleep(timeout);
+    let expired = app_handle
+      .state::<AppState>()
+      .pending_pastes
+      .lock()
+      .map(|mut pending| pending.remove(&execution_id).is_some())
+      .unwrap_or(false);
+    if expired {
+      warn!(%execution_id, "pending paste was not confirmed in time");
+      let _ = app_handle.emit_all("paste-cancelled", &execution_id);
+    }
+  });
+
+  Ok(AutoPasteOutcome::AwaitingConfirmation)
+}
+
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+fn confirm_paste(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  execution_id: String,
+) -> Result<PasteMethod, String> {
+  let pending = state
+    .pending_pastes
+    .lock()
+    .map_err(|_| "Failed to lock pending paste state".to_string())?
+    .remove(&execution_id)
+    .ok_or_else(|| {
+      format!("No pending paste for execution {execution_id} (it may have timed out)")
+    })?;
 
-  *registered = None;
+  hide_and_paste(&handle, &pending.text, pending.paste_keys.as_deref(), Some(&execution_id))
+}
+
+#[tauri::command]
+#[instrument(skip(state), err)]
+fn cancel_paste(state: State<'_, AppState>, execution_id: String) -> Result<(), String> {
+  state
+    .pending_pastes
+    .lock()
+    .map_err(|_| "Failed to lock pending paste state".to_string())?
+    .remove(&execution_id);
   Ok(())
 }
 
-/// Write `text` to the clipboard, then simulate Ctrl+V to paste it into the
-/// foreground application.  The window must have been hidden or blurred first
-/// so that the original application receives the paste event.
+/// Let a run waiting on its action's `confirm_message` go ahead.
 #[tauri::command]
-fn paste_text(text: String) -> Result<(), String> {
-  let mut board =
-    Clipboard::new().map_err(|error| format!("Clipboard init failed: {error}"))?;
+#[instrument(skip(state), err)]
+fn confirm_run(state: State<'_, AppState>, execution_id: String) -> Result<(), String> {
+  answer_confirmation(&state, &execution_id, true)
+}
 
-  board
-    .set_text(&text)
-    .map_err(|error| format!("Clipboard write failed: {error}"))?;
+/// Call off a run waiting on its action's `confirm_message`.
+#[tauri::command]
+#[instrument(skip(state), err)]
+fn decline_run(state: State<'_, AppState>, execution_id: String) -> Result<(), String> {
+  answer_confirmation(&state, &execution_id, false)
+}
 
-  // Small delay to let the clipboard settle before simulating the paste.
-  thread::sleep(Duration::from_millis(80));
+fn answer_confirmation(state: &AppState, execution_id: &str, confirmed: bool) -> Result<(), String> {
+  let waiting = state
+    .pending_confirmations
+    .lock()
+    .map_err(|_| "Failed to lock confirmation state".to_string())?
+    .remove(execution_id)
+    .ok_or_else(|| {
+      format!("No run is waiting for confirmation as {execution_id} (it may have timed out)")
+    })?;
+  // Fails only if the run stopped waiting a moment ago; it reports that itself.
+  let _ = waiting.send(confirmed);
+  Ok(())
+}
 
-  let mut enigo = Enigo::new();
-  enigo.key_down(Key::Control);
-  enigo.key_click(Key::Layout('v'));
-  enigo.key_up(Key::Control);
+/// Called by the UI when an action finishes. Auto-paste runs never take
+/// focus, so the user can keep typing in the target application.
+#[tauri::command]
+#[instrument(skip(handle))]
+fn notify_action_complete(handle: AppHandle, auto_paste: bool) {
+  let notice = match current_preferences(&handle).completion_notice {
+    CompletionNotice::Focus if auto_paste => CompletionNotice::ShowWithoutFocus,
+    notice => notice,
+  };
+  reveal_main_window(&handle, notice);
+  if auto_paste {
+    schedule_auto_hide(&handle);
+  }
+}
 
-  Ok(())
+/// Hide the window after `auto_hide_after_paste_ms`, unless `cancel_auto_hide`
+/// runs (or the window gains focus) first.
+fn schedule_auto_hide(handle: &AppHandle) {
+  let Some(delay_ms) = current_preferences(handle).auto_hide_after_paste_ms else {
+    return;
+  };
+  let state = handle.state::<AppState>();
+  let generation = state.auto_hide_generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+  let handle = handle.clone();
+  thread::spawn(move || {
+    thread::sleep(Duration::from_millis(delay_ms));
+    if handle.state::<AppState>().auto_hide_generation.load(Ordering::SeqCst) == generation {
+      debug!("auto-hiding window after paste");
+      if let Err(error) = hide_window(handle.clone()) {
+        warn!(%error, "auto-hide failed");
+      }
+    }
+  });
+}
+
+/// Keep the window up: the UI calls this when the user interacts with it.
+#[tauri::command]
+fn cancel_auto_hide(state: State<'_, AppState>) {
+  state.auto_hide_generation.fetch_add(1, Ordering::SeqCst);
 }
 
 #[tauri::command]
+#[instrument(skip_all, err)]
 fn hide_window(handle: AppHandle) -> Result<(), String> {
   if let Some(window) = handle.get_window("main") {
     window
@@ -322,7 +2511,11 @@ fn hide_window(handle: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
+#[instrument(skip_all, err)]
 fn load_setup(handle: AppHandle) -> Result<Option<SetupPayload>, String> {
+  if let Err(error) = autosave::flush(&handle) {
+    warn!(%error, "failed to write auto-saved setup before loading it");
+  }
   let path = setup_file_path(&handle)?;
   let setup_file = match read_json::<SetupFile>(&path)? {
     Some(s) => s,
@@ -332,51 +2525,88 @@ fn load_setup(handle: AppHandle) -> Result<Option<SetupPayload>, String> {
   // Migration: If api_key exists in JSON (legacy), move it to keyring.
   if let Some(legacy_api_key) = &setup_file.api_key {
     if !legacy_api_key.is_empty() {
+      info!("migrating legacy API key from setup.json to keyring");
       save_api_key_secure(legacy_api_key)?;
 
       // Remove api_key from JSON file after migration.
       let migrated = SetupFile {
-        provider: setup_file.provider.clone(),
-        actions: setup_file.actions.clone(),
-        default_action_id: setup_file.default_action_id.clone(),
-        setup_completed_at: setup_file.setup_completed_at.clone(),
         api_key: None,
+        ..setup_file.clone()
       };
-      write_json(&path, &migrated)?;
+      write_json(&path, &migrated, JsonFormat::Pretty)?;
     }
   }
 
   // Load API key from keyring.
   let api_key = load_api_key_secure()?.unwrap_or_default();
 
-  Ok(Some(SetupPayload {
-    provider: setup_file.provider,
-    api_key,
-    actions: setup_file.actions,
-    default_action_id: setup_file.default_action_id,
-    setup_completed_at: setup_file.setup_completed_at,
-  }))
+  Ok(Some(setup_file.into_payload(api_key)))
 }
 
 #[tauri::command]
-fn save_setup(handle: AppHandle, setup: SetupPayload) -> Result<(), String> {
-  // Save API key to Windows Credential Manager.
-  save_api_key_secure(&setup.api_key)?;
+#[instrument(skip_all, err)]
+fn save_setup(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  setup: SetupPayload,
+) -> Result<(), String> {
+  // This save supersedes any edit still waiting to be auto-saved.
+  autosave::discard(&state);
+  write_setup(&handle, &state, setup)
+}
+
+/// Validate and write setup, touching the keyring only when the key changed.
+fn write_setup(handle: &AppHandle, state: &AppState, setup: SetupPayload) -> Result<(), String> {
+  let api_key = setup.api_key.clone();
+  let mut setup_file = SetupFile::from(setup);
+  setup_file.validate()?;
+
+  let path = setup_file_path(handle)?;
+  let stored = read_json::<SetupFile>(&path).ok().flatten();
+
+  // Keep the user id stable across saves: reuse the stored one if the UI
+  // didn't send it back, mint one on opt-in, and forget it on opt-out.
+  if !setup_file.send_user_id {
+    setup_file.user_id = None;
+  } else if setup_file.user_id.is_none() {
+    let stored = stored.as_ref().and_then(|stored| stored.user_id.clone());
+    setup_file.user_id = Some(stored.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()));
+  }
+  if let Some(stored) = &stored {
+    usage::keep_usage(&mut setup_file, stored);
+  }
+
+  // Save API key to Windows Credential Manager, unless it is already there:
+  // every write to the Credential Manager is slow and logged.
+  if load_api_key_secure().ok().flatten().as_deref() != Some(api_key.trim()) {
+    save_api_key_secure(&api_key)?;
+  }
 
   // Save everything else to JSON file (without API key).
-  let setup_file = SetupFile {
-    provider: setup.provider,
-    actions: setup.actions,
-    default_action_id: setup.default_action_id,
-    setup_completed_at: setup.setup_completed_at,
-    api_key: None, // Never store API key in JSON
-  };
+  write_json(&path, &setup_file, JsonFormat::Pretty)?;
 
-  let path = setup_file_path(&handle)?;
-  write_json(&path, &setup_file)
+  if !setup_file.preferences.telemetry_enabled {
+    telemetry::discard(state);
+  }
+  *state
+    .preferences
+    .lock()
+    .map_err(|_| "Failed to lock preferences state".to_string())? = setup_file.preferences;
+
+  tray::refresh(handle);
+  Ok(())
+}
+
+/// Actions pinned to the favorites bar, in pin order.
+#[tauri::command]
+#[instrument(skip_all, err)]
+fn list_pinned_actions(handle: AppHandle) -> Result<Vec<Action>, String> {
+  let setup = read_json::<SetupFile>(&setup_file_path(&handle)?)?;
+  Ok(setup.map(|setup| setup.pinned_actions()).unwrap_or_default())
 }
 
 #[tauri::command]
+#[instrument(skip_all, err)]
 fn load_execution_logs(state: State<'_, AppState>) -> Result<Vec<ExecutionLogEntry>, String> {
   let logs = state
     .logs
@@ -387,86 +2617,766 @@ fn load_execution_logs(state: State<'_, AppState>) -> Result<Vec<ExecutionLogEnt
   Ok(logs)
 }
 
+/// A window into the execution log, oldest entry first.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ExecutionLogPage {
+  entries: Vec<ExecutionLogEntry>,
+  offset: usize,
+  total: usize,
+}
+
+/// Like `load_execution_logs`, but only clones the requested slice.
 #[tauri::command]
-fn append_execution_log(
+#[instrument(skip(state), err)]
+fn load_execution_logs_page(
+  state: State<'_, AppState>,
+  offset: usize,
+  limit: usize,
+) -> Result<ExecutionLogPage, String> {
+  let logs = state
+    .logs
+    .lock()
+    .map_err(|_| "Failed to lock log state".to_string())?;
+
+  let start = offset.min(logs.len());
+  let end = start.saturating_add(limit).min(logs.len());
+
+  Ok(ExecutionLogPage {
+    entries: logs[start..end].to_vec(),
+    offset: start,
+    total: logs.len(),
+  })
+}
+
+/// Write the execution log to `path` as JSON Lines, one entry per line,
+/// oldest first, and return how many were written. Entries are serialized
+/// straight from memory into a buffered file, so large histories aren't
+/// copied first. An existing file is replaced.
+#[tauri::command]
+#[instrument(skip(state), err)]
+fn export_logs_ndjson(state: State<'_, AppState>, path: String) -> Result<usize, String> {
+  let path = PathBuf::from(path);
+  let display = path.display();
+  if path.is_dir() {
+    return Err(format!("{display} is a folder; choose a file to export to"));
+  }
+  let file =
+    fs::File::create(&path).map_err(|error| format!("Failed to create {display}: {error}"))?;
+  let mut writer = std::io::BufWriter::new(file);
+  let write_failed = |error: std::io::Error| format!("Failed to write {display}: {error}");
+
+  let logs = state
+    .logs
+    .lock()
+    .map_err(|_| "Failed to lock log state".to_string())?;
+  for entry in logs.iter() {
+    serde_json::to_writer(&mut writer, entry).map_err(|error| write_failed(error.into()))?;
+    writer.write_all(b"\n").map_err(write_failed)?;
+  }
+  writer.flush().map_err(write_failed)?;
+
+  info!(entries = logs.len(), "exported execution log as JSON Lines");
+  Ok(logs.len())
+}
+
+/// At most this many entries are returned by `search_logs`.
+const MAX_LOG_SEARCH_RESULTS: usize = 50;
+
+/// Entries whose action name, prompt, or error mention `query`
+/// (case-insensitive), best match first. Name matches outrank prompt matches,
+/// which outrank error matches; ties go to the newest entry. Outputs aren't
+/// logged, so they can't be searched.
+#[tauri::command]
+#[instrument(skip(state), err)]
+fn search_logs(
+  state: State<'_, AppState>,
+  query: String,
+) -> Result<Vec<ExecutionLogEntry>, String> {
+  let query = query.trim().to_lowercase();
+  if query.is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let logs = state
+    .logs
+    .lock()
+    .map_err(|_| "Failed to lock log state".to_string())?;
+
+  let matches = |field: &str| field.to_lowercase().contains(&query);
+  let mut ranked: Vec<(u8, usize)> = logs
+    .iter()
+    .enumerate()
+    .filter_map(|(index, entry)| {
+      let score = u8::from(matches(&entry.action_name)) * 4
+        + u8::from(matches(&entry.prompt)) * 2
+        + u8::from(entry.error_message.as_deref().is_some_and(matches));
+      (score > 0).then_some((score, index))
+    })
+    .collect();
+  ranked.sort_unstable_by(|a, b| b.cmp(a));
+
+  Ok(
+    ranked
+      .into_iter()
+      .take(MAX_LOG_SEARCH_RESULTS)
+      .map(|(_, index)| logs[index].clone())
+      .collect(),
+  )
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionLogSummary {
+  count: usize,
+  last_run_at: Option<String>,
+  /// Share of successful runs, from 0 to 1.
+  success_rate: f64,
+  /// The action's `run_count`, which outlives trimmed and archived entries.
+  /// `None` for deleted actions.
+  run_count: Option<u64>,
+}
+
+/// One action's history, oldest entry first.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct ActionLogGroup {
+  summary: ActionLogSummary,
+  entries: Vec<ExecutionLogEntry>,
+}
+
+/// The execution log grouped by action id, for per-action history panels.
+/// Entries of deleted actions are still grouped under their old id.
+#[tauri::command]
+#[instrument(skip_all, err)]
+fn logs_grouped_by_action(
   handle: AppHandle,
   state: State<'_, AppState>,
+) -> Result<HashMap<String, ActionLogGroup>, String> {
+  let run_counts = usage::run_counts(&handle)?;
+  let logs = state
+    .logs
+    .lock()
+    .map_err(|_| "Failed to lock log state".to_string())?;
+
+  let mut groups: HashMap<String, ActionLogGroup> = HashMap::new();
+  for entry in logs.iter() {
+    groups.entry(entry.action_id.clone()).or_default().entries.push(entry.clone());
+  }
+
+  for (action_id, group) in &mut groups {
+    let succeeded = group.entries.iter().filter(|entry| entry.success).count();
+    group.summary = ActionLogSummary {
+      count: group.entries.len(),
+      last_run_at: group.entries.last().map(|entry| entry.timestamp.clone()),
+      success_rate: succeeded as f64 / group.entries.len() as f64,
+      run_count: run_counts.get(action_id).copied(),
+    };
+  }
+  Ok(groups)
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AppendedExecutionLog {
   entry: ExecutionLogEntry,
-) -> Result<Vec<ExecutionLogEntry>, String> {
+  total: usize,
+}
+
+#[tauri::command]
+#[instrument(skip_all, fields(action_id = %entry.action_id), err)]
+fn append_execution_log(
+  handle: AppHandle,
+  entry: ExecutionLogEntry,
+) -> Result<AppendedExecutionLog, String> {
+  let total = record_execution(&handle, entry.clone())?;
+  Ok(AppendedExecutionLog { entry, total })
+}
+
+/// Append a log entry and persist, archiving the log once it outgrows
+/// `log_archive_bytes` (or, with archiving off, trimming it to the newest
+/// 500). Returns the number of entries now held.
+fn record_execution(handle: &AppHandle, entry: ExecutionLogEntry) -> Result<usize, String> {
+  let format = logs_json_format(handle);
+  let preferences = current_preferences(handle);
+  let state = handle.state::<AppState>();
   let mut logs = state
     .logs
     .lock()
     .map_err(|_| "Failed to lock log state".to_string())?;
 
   logs.push(entry);
-  if logs.len() > 500 {
+  let archiving = preferences.log_archive_bytes > 0;
+  if !archiving && logs.len() > 500 {
     let trim_count = logs.len() - 500;
     logs.drain(0..trim_count);
   }
 
-  let updated = logs.clone();
+  let path = logs_file_path(handle)?;
+  state.log_writes.fetch_add(1, Ordering::SeqCst);
+  let written = write_json(&path, &*logs, format).and_then(|()| {
+    if archiving {
+      archive::rotate_if_needed(handle, &preferences, &path, &mut logs, format)?;
+    }
+    Ok(())
+  });
+  state.log_writes.fetch_sub(1, Ordering::SeqCst);
+  written?;
+
+  Ok(logs.len())
+}
+
+/// Set `paste_method` on the log entry of `execution_id`. An entry already
+/// archived, or a run that wasn't logged, is left alone.
+fn record_paste_method(
+  handle: &AppHandle,
+  execution_id: &str,
+  method: PasteMethod,
+) -> Result<(), String> {
+  let format = logs_json_format(handle);
+  let state = handle.state::<AppState>();
+  let mut logs = state
+    .logs
+    .lock()
+    .map_err(|_| "Failed to lock log state".to_string())?;
+  let Some(entry) = logs.iter_mut().rev().find(|entry| entry.id == execution_id) else {
+    return Ok(());
+  };
+  entry.paste_method = Some(method);
+
+  let path = logs_file_path(handle)?;
+  state.log_writes.fetch_add(1, Ordering::SeqCst);
+  let written = write_json(&path, &*logs, format);
+  state.log_writes.fetch_sub(1, Ordering::SeqCst);
+  written
+}
+
+/// Outcome of `repair_logs`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LogRepairReport {
+  recovered: usize,
+  /// Entries that were cut off or otherwise could not be read.
+  lost: usize,
+  /// Copy of the malformed file, kept when a repair rewrote it.
+  backup_path: Option<String>,
+}
+
+/// Read every well-formed entry out of a truncated or partly corrupted log
+/// array. Entries are flat objects, so after a bad fragment parsing resumes
+/// at the next `{`; fragments that look like an entry start count as lost.
+fn salvage_log_entries(raw: &str) -> (Vec<ExecutionLogEntry>, usize) {
+  let mut entries = Vec::new();
+  let mut lost = 0;
+  let mut rest = raw;
+
+  while let Some(start) = rest.find('{') {
+    rest = &rest[start..];
+    let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<ExecutionLogEntry>();
+    match stream.next() {
+      Some(Ok(entry)) => {
+        entries.push(entry);
+        rest = &rest[stream.byte_offset()..];
+      }
+      _ => {
+        if rest[1..].trim_start().starts_with('"') {
+          lost += 1;
+        }
+        rest = &rest[1..];
+      }
+    }
+  }
+
+  (entries, lost)
+}
+
+/// Salvage what can be read from a malformed `execution-logs.json` (or the
+/// copy kept when it failed to load at launch) and rewrite the log with the
+/// recovered entries followed by anything recorded since.
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+fn repair_logs(handle: AppHandle, state: State<'_, AppState>) -> Result<LogRepairReport, String> {
   let path = logs_file_path(&handle)?;
-  write_json(&path, &updated)?;
+  let backup = corrupt_logs_path(&path);
+  let read = |path: &Path| {
+    fs::read_to_string(path)
+      .map_err(|error| format!("Failed to read JSON file {}: {error}", path.display()))
+  };
 
-  Ok(updated)
+  let raw = if path.exists() { read(&path)? } else { String::new() };
+  let raw = match serde_json::from_str::<Vec<ExecutionLogEntry>>(&raw) {
+    Err(_) if path.exists() => {
+      fs::copy(&path, &backup)
+        .map_err(|error| format!("Failed to back up {}: {error}", path.display()))?;
+      raw
+    }
+    _ if backup.exists() => read(&backup)?,
+    Ok(entries) => {
+      return Ok(LogRepairReport { recovered: entries.len(), lost: 0, backup_path: None });
+    }
+    Err(_) => return Ok(LogRepairReport { recovered: 0, lost: 0, backup_path: None }),
+  };
+
+  let (mut recovered, lost) = salvage_log_entries(&raw);
+  let recovered_count = recovered.len();
+  let format = logs_json_format(&handle);
+  let preferences = current_preferences(&handle);
+
+  let mut logs = state
+    .logs
+    .lock()
+    .map_err(|_| "Failed to lock log state".to_string())?;
+  let known: HashSet<String> = recovered.iter().map(|entry| entry.id.clone()).collect();
+  recovered.extend(logs.iter().filter(|entry| !known.contains(&entry.id)).cloned());
+  // As in `record_execution`: archiving keeps everything, rotating instead.
+  let archiving = preferences.log_archive_bytes > 0;
+  if !archiving && recovered.len() > 500 {
+    let trim_count = recovered.len() - 500;
+    recovered.drain(0..trim_count);
+  }
+
+  *logs = recovered;
+  state.log_writes.fetch_add(1, Ordering::SeqCst);
+  let written = write_json(&path, &*logs, format).and_then(|()| {
+    if archiving {
+      archive::rotate_if_needed(&handle, &preferences, &path, &mut logs, format)?;
+    }
+    Ok(())
+  });
+  state.log_writes.fetch_sub(1, Ordering::SeqCst);
+  written?;
+  info!(recovered = recovered_count, lost, "repaired execution log");
+
+  Ok(LogRepairReport {
+    recovered: recovered_count,
+    lost,
+    backup_path: Some(backup.display().to_string()),
+  })
 }
 
-fn main() {
-  let show_item = CustomMenuItem::new("show", "Show ShortcutAI");
-  let quit_item = CustomMenuItem::new("quit", "Quit");
+#[tauri::command]
+async fn run_action(
+  handle: AppHandle,
+  action_id: String,
+  text: String,
+) -> Result<run::ActionRunResult, String> {
+  run::run_action(&handle, &action_id, text).await
+}
+
+/// Full text of the most recent successful run. Long outputs are fetched
+/// this way instead of being pushed through events.
+#[tauri::command]
+fn get_last_output(state: State<'_, AppState>) -> Result<Option<run::LastOutput>, String> {
+  Ok(
+    state
+      .last_output
+      .lock()
+      .map_err(|_| "Failed to lock output state".to_string())?
+      .clone(),
+  )
+}
+
+/// Abort the running runs of `action_id`. Returns whether any was running.
+#[tauri::command]
+#[instrument(skip(state), err)]
+fn cancel_action(state: State<'_, AppState>, action_id: String) -> Result<bool, String> {
+  Ok(run::cancel(&state, &action_id)? > 0)
+}
+
+/// Run a past execution again on the same input, for the history view.
+#[tauri::command]
+async fn replay_log_entry(
+  handle: AppHandle,
+  log_id: String,
+) -> Result<run::ActionRunResult, String> {
+  run::replay(&handle, &log_id).await
+}
+
+/// Run a free-form prompt on `input`, e.g. the capture, without a stored
+/// action.
+#[tauri::command]
+async fn run_adhoc(
+  handle: AppHandle,
+  prompt: String,
+  input: String,
+) -> Result<run::ActionRunResult, String> {
+  run::run_adhoc(&handle, prompt, input).await
+}
+
+/// Run an action against several providers at once and return their
+/// outputs side by side; see `run::compare_providers`.
+#[tauri::command]
+async fn compare_providers(
+  handle: AppHandle,
+  action_id: String,
+  input: String,
+  providers: Vec<String>,
+) -> Result<Vec<run::ProviderComparison>, String> {
+  run::compare_providers(&handle, &action_id, input, providers).await
+}
+
+/// Second phase of the capture preview: run on the text as the user left it
+/// in the popup, which may differ from what `text-captured` delivered.
+#[tauri::command]
+#[instrument(skip(handle, text), fields(chars = text.len()), err)]
+async fn run_with_text(
+  handle: AppHandle,
+  action_id: String,
+  text: String,
+) -> Result<run::ActionRunResult, String> {
+  if text.trim().is_empty() {
+    return Err("There is no text to run the action on".to_string());
+  }
+
+  run::run_action(&handle, &action_id, text).await
+}
+
+/// Run an action on whatever is already on the clipboard, without simulating
+/// Ctrl+C. Useful in apps where selection capture doesn't work.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+async fn run_action_on_clipboard(
+  handle: AppHandle,
+  action_id: String,
+) -> Result<run::ActionRunResult, String> {
+  let text = Clipboard::new()
+    .and_then(|mut board| clipboard::read_text(&mut board))
+    .map_err(|error| format!("Clipboard read failed: {error}"))?;
+  if text.trim().is_empty() {
+    return Err("Clipboard does not contain any text".to_string());
+  }
+
+  run::run_action(&handle, &action_id, text).await
+}
+
+/// Return the tail of the backend trace log, for attaching to bug reports.
+/// If tracing couldn't be set up at launch, the first line says why.
+#[tauri::command]
+fn get_recent_trace(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  max_lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+  let mut lines = trace::recent_lines(&writable_data_dir(&handle)?, max_lines.unwrap_or(200))?;
+  if let Some(error) = &state.trace_error {
+    lines.insert(0, format!("Tracing is off: {error}"));
+  }
+  Ok(lines)
+}
+
+/// Work a restart would cut short.
+#[derive(Debug)]
+struct PendingWork {
+  runs_in_flight: usize,
+  log_write: bool,
+  usage_write: bool,
+  setup_write: bool,
+}
+
+impl PendingWork {
+  fn of(state: &AppState) -> Self {
+    Self {
+      runs_in_flight: state.runs_in_flight.load(Ordering::SeqCst),
+      log_write: state.log_writes.load(Ordering::SeqCst) > 0,
+      usage_write: state.usage.has_pending(),
+      setup_write: state.autosave.has_pending(),
+    }
+  }
+
+  fn is_idle(&self) -> bool {
+    self.runs_in_flight == 0 && !self.log_write && !self.usage_write && !self.setup_write
+  }
+}
+
+/// Whether the app can restart for an update without interrupting a native
+/// run or a log write. Pending last-used times and auto-saves count too,
+/// although quitting through `shutdown` saves them anyway.
+#[tauri::command]
+#[instrument(skip_all)]
+fn can_update(state: State<'_, AppState>) -> bool {
+  let pending = PendingWork::of(&state);
+  debug!(?pending, "update readiness checked");
+  pending.is_idle()
+}
+
+/// Quit without cutting a log write short. Holding the log lock waits for a
+/// write in progress and keeps new ones from starting until the process ends.
+fn shutdown(handle: &AppHandle) {
+  let pending = PendingWork::of(&handle.state::<AppState>());
+  if !pending.is_idle() {
+    info!(?pending, "quitting with work in progress");
+  }
+  if let Err(error) = autosave::flush(handle) {
+    warn!(%error, "failed to write auto-saved setup on quit");
+  }
+  if let Err(error) = usage::flush(handle) {
+    warn!(%error, "failed to save last-used times on quit");
+  }
+
+  // A capture in progress still has the user's clipboard to put back.
+  let clipboard_wait = Duration::from_millis(current_preferences(handle).capture_max_wait_ms);
+  if !clipboard_watch::wait_for_own_writes(clipboard_wait + Duration::from_millis(500)) {
+    warn!("quitting while the clipboard is still in use");
+  }
+
+  let state = handle.state::<AppState>();
+  raw_response::clear(&state);
+  let _logs = state.logs.lock();
+
+  if let Err(error) = handle.global_shortcut_manager().unregister_all() {
+    warn!(%error, "failed to unregister shortcuts on quit");
+  }
+
+  // Drop the trace writer guard so buffered lines reach the file.
+  if let Ok(mut guard) = state.trace_guard.lock() {
+    guard.take();
+  }
+
+  // Exits the process with the log lock still held, so no run can start a write.
+  handle.exit(0);
+}
 
-  let tray_menu = SystemTrayMenu::new()
-    .add_item(show_item)
-    .add_native_item(SystemTrayMenuItem::Separator)
-    .add_item(quit_item);
+fn main() {
+  // Forwards a deep link to an already running instance and exits if there is one.
+  tauri_plugin_deep_link::prepare("ai.shortcut.app");
 
-  let system_tray = SystemTray::new().with_menu(tray_menu);
+  // Pinned actions are added once the active profile is known, in `setup`.
+  let system_tray = SystemTray::new().with_menu(tray::menu(&[]));
 
   tauri::Builder::default()
     .system_tray(system_tray)
-    .on_system_tray_event(|app, event| match event {
-      SystemTrayEvent::LeftClick { .. } => {
-        if let Some(window) = app.get_window("main") {
-          let _ = window.show();
-          let _ = window.unminimize();
-          let _ = window.set_focus();
+    .on_window_event(|event| match event.event() {
+      // Clicking into the window counts as interacting with it.
+      WindowEvent::Focused(true) => {
+        let handle = event.window().app_handle();
+        handle.state::<AppState>().auto_hide_generation.fetch_add(1, Ordering::SeqCst);
+        idle::resume(&handle);
+        if let Err(error) = health::check(&handle) {
+          warn!(%error, "main shortcut health check failed");
         }
       }
-      SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
-        "show" => {
-          if let Some(window) = app.get_window("main") {
-            let _ = window.show();
-            let _ = window.unminimize();
-            let _ = window.set_focus();
-          }
+      WindowEvent::Resized(size) => {
+        let handle = event.window().app_handle();
+        placement::resized(event.window(), *size, &handle.state::<AppState>().placement);
+      }
+      WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+        let handle = event.window().app_handle();
+        let placement = &handle.state::<AppState>().placement;
+        placement::scale_changed(event.window(), *scale_factor, placement);
+      }
+      // Either way the close is taken over: hiding keeps the app in the tray,
+      // and quitting goes through `shutdown` so no log write is cut short.
+      WindowEvent::CloseRequested { api, .. } => {
+        api.prevent_close();
+        let handle = event.window().app_handle();
+        if current_preferences(&handle).close_to_tray {
+          debug!("window closed to tray");
+          let _ = event.window().hide();
+        } else {
+          info!("quit requested by closing the window");
+          shutdown(&handle);
         }
+      }
+      _ => {}
+    })
+    .on_system_tray_event(|app, event| match event {
+      SystemTrayEvent::LeftClick { .. } => show_main_window(app),
+      SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+        "show" => show_main_window(app),
         "quit" => {
-          std::process::exit(0);
+          info!("quit requested from tray");
+          shutdown(app);
+        }
+        id => {
+          if let Some(action_id) = tray::pinned_action_id(id) {
+            tray::run_pinned(app, action_id);
+          }
         }
-        _ => {}
       },
       _ => {}
     })
     .setup(|app| {
       let app_handle = app.handle();
+      profile::init(&app_handle);
       let logs = load_logs_from_disk(&app_handle);
+      let preferences = load_preferences_from_disk(&app_handle);
+      // Tracing is best-effort: the app must still start if the log file is unavailable.
+      let trace = writable_data_dir(&app_handle)
+        .and_then(|dir| trace::init(&dir, &preferences.log_level));
+      let (trace_guard, trace_error) = match trace {
+        Ok(guard) => (Some(guard), None),
+        Err(error) => (None, Some(error)),
+      };
+      let warm_up = preferences.warm_up_on_launch;
+      info!(
+        log_entries = logs.len(),
+        config_dir = ?config_dir_override(),
+        "ShortcutAI starting"
+      );
       app.manage(AppState {
         logs: Mutex::new(logs),
         active_shortcut: Mutex::new(None),
+        picker_override_shortcut: Mutex::new(None),
+        logs_shortcut: Mutex::new(None),
+        preferences: Mutex::new(preferences),
+        pending_pastes: Mutex::new(HashMap::new()),
+        action_shortcuts: Mutex::new(HashMap::new()),
+        last_output: Mutex::new(None),
+        http_client: provider::http_client(),
+        trace_guard: Mutex::new(trace_guard),
+        trace_error,
+        workers: worker::Pool::default(),
+        auto_hide_generation: AtomicU64::new(0),
+        response_cache: Mutex::new(cache::ResponseCache::default()),
+        active_file: Mutex::new(None),
+        cursor_context: Mutex::new(None),
+        idle: idle::Monitor::default(),
+        usage: usage::Tracker::default(),
+        telemetry: telemetry::Tracker::default(),
+        runs_in_flight: AtomicUsize::new(0),
+        log_writes: AtomicUsize::new(0),
+        cancellations: Mutex::new(HashMap::new()),
+        pending_confirmations: Mutex::new(HashMap::new()),
+        placement: placement::Placement::default(),
+        autosave: autosave::Saver::default(),
+        last_raw_response: Default::default(),
+        clipboard_watch: clipboard_watch::Watcher::default(),
       });
+      deep_link::register(&app_handle);
+      tray::refresh(&app_handle);
+      idle::spawn(app_handle.clone());
+      health::spawn(app_handle.clone());
+      telemetry::spawn(app_handle.clone());
+      clipboard_watch::spawn(app_handle.clone());
+      if warm_up {
+        run::spawn_warm_up(&app_handle);
+      }
       Ok(())
     })
     .invoke_handler(tauri::generate_handler![
       check_windows_permissions,
+      classify::classify_text,
+      diff::diff_text,
+      text_stats::analyze_text,
+      resource_stats::get_resource_stats,
+      clipboard_watch::set_clipboard_watch_paused,
+      clipboard_watch::clipboard_watch_status,
+      restore_check::check_clipboard_restore,
+      self_test::run_self_test,
+      usage::most_used_actions,
       register_global_shortcut,
       unregister_global_shortcut,
+      reassert_shortcut,
+      list_registered_shortcuts,
+      unregister_all_shortcuts,
+      register_action_shortcut,
+      unregister_action_shortcut,
+      get_action_shortcut,
+      register_logs_shortcut,
+      unregister_logs_shortcut,
+      save_provider_api_key,
+      has_api_key,
+      provider_capabilities,
+      check_api_key_format,
+      clear_api_key,
+      copy_to_clipboard,
       paste_text,
+      auto_paste,
+      confirm_paste,
+      confirm_run,
+      decline_run,
+      cancel_paste,
+      notify_action_complete,
       hide_window,
+      cancel_auto_hide,
+      can_update,
       load_setup,
       save_setup,
+      autosave::save_setup_debounced,
+      raw_response::get_last_raw_response,
+      list_pinned_actions,
       load_execution_logs,
-      append_execution_log
+      load_execution_logs_page,
+      export_logs_ndjson,
+      search_logs,
+      logs_grouped_by_action,
+      append_execution_log,
+      repair_logs,
+      run_action,
+      run_with_text,
+      replay_log_entry,
+      run_adhoc,
+      compare_providers,
+      cancel_action,
+      run_action_on_clipboard,
+      file_run::run_action_on_file,
+      get_last_output,
+      get_recent_trace,
+      archive::query_log_archives,
+      benchmark::benchmark_roundtrip,
+      presets::install_preset_actions,
+      duplicates::find_duplicate_actions,
+      setup_check::validate_setup,
+      share::export_action,
+      share::import_action,
+      startup::get_launch_at_startup,
+      startup::set_launch_at_startup,
+      telemetry::telemetry_status,
+      reset::factory_reset,
+      profile::list_profiles,
+      profile::create_profile,
+      profile::switch_profile,
+      profile::delete_profile
     ])
     .run(tauri::generate_context!())
     .expect("error while running shortcutai windows app");
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(id: &str) -> String {
+    format!(
+      concat!(
+        r#"{{"id":"{id}","timestamp":"2026-01-01T00:00:00Z","actionId":"a","actionName":"A","#,
+        r#""prompt":"p","durationMs":1.0,"inputLength":1,"outputLength":1,"success":true}}"#,
+      ),
+      id = id
+    )
+  }
+
+  fn ids(raw: &str) -> (Vec<String>, usize) {
+    let (entries, lost) = salvage_log_entries(raw);
+    (entries.into_iter().map(|entry| entry.id).collect(), lost)
+  }
+
+  #[test]
+  fn valid_logs_are_read_whole() {
+    let raw = format!("[\n{},\n{}\n]", entry("a"), entry("b"));
+    assert_eq!(ids(&raw), (vec!["a".to_string(), "b".to_string()], 0));
+  }
+
+  #[test]
+  fn trailing_garbage_is_ignored() {
+    let raw = format!("[{},{}]\0\0 xyz {{", entry("a"), entry("b"));
+    assert_eq!(ids(&raw), (vec!["a".to_string(), "b".to_string()], 0));
+  }
+
+  #[test]
+  fn an_entry_cut_off_midway_is_lost() {
+    let raw = format!("[{},{},{{\"id\":\"c\",\"timest", entry("a"), entry("b"));
+    assert_eq!(ids(&raw), (vec!["a".to_string(), "b".to_string()], 1));
+  }
+
+  #[test]
+  fn a_malformed_entry_between_good_ones_is_skipped() {
+    let raw = format!("[{},{{\"id\":\"b\",\"timestamp\":}},{}]", entry("a"), entry("c"));
+    assert_eq!(ids(&raw), (vec!["a".to_string(), "c".to_string()], 1));
+  }
+
+  #[test]
+  fn an_empty_file_has_nothing_to_salvage() {
+    assert_eq!(ids(""), (Vec::new(), 0));
+  }
+}
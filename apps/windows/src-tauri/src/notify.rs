@@ -0,0 +1,94 @@
+//! Desktop notifications for actions that finish in the background.
+
+#[cfg(windows)]
+use crate::show_main_window;
+use crate::current_preferences;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+/// Longer error messages are cut so the toast stays readable.
+const MAX_BODY_CHARS: usize = 120;
+
+/// Announce a finished run if `notify_on_complete` is enabled and the main
+/// window isn't already in front of the user.
+pub fn action_completed(handle: &AppHandle, action_name: &str, result: &Result<f64, String>) {
+  if !current_preferences(handle).notify_on_complete {
+    return;
+  }
+  let focused = handle
+    .get_window("main")
+    .and_then(|window| window.is_focused().ok())
+    .unwrap_or(false);
+  if focused {
+    return;
+  }
+
+  let body = match result {
+    Ok(duration_ms) => format!("Done in {:.1}s", duration_ms / 1000.0),
+    Err(error) => {
+      let message = format!("Failed: {error}");
+      match message.char_indices().nth(MAX_BODY_CHARS) {
+        Some((cut, _)) => format!("{}…", &message[..cut]),
+        None => message,
+      }
+    }
+  };
+
+  if let Err(error) = show(handle, action_name, &body) {
+    warn!(%error, "failed to show completion notification");
+  }
+}
+
+/// Offer to run an action on newly copied text, for the clipboard watch.
+/// The text itself stays out of the toast, which Windows keeps in the
+/// notification center.
+pub fn clipboard_suggestion(handle: &AppHandle, chars: usize) {
+  let focused = handle
+    .get_window("main")
+    .and_then(|window| window.is_focused().ok())
+    .unwrap_or(false);
+  if focused {
+    return;
+  }
+
+  let body = format!("Copied {chars} characters. Click to pick an action to run on them.");
+  if let Err(error) = show(handle, "Run an action on the copied text?", &body) {
+    warn!(%error, "failed to show clipboard suggestion");
+  }
+}
+
+/// Tauri's notification API has no click callback, so Windows talks to the
+/// toast API directly to focus the main window when the toast is clicked.
+#[cfg(windows)]
+fn show(handle: &AppHandle, title: &str, body: &str) -> Result<(), String> {
+  use tauri_winrt_notification::Toast;
+
+  // Toasts from an uninstalled build need a registered app id; mirror Tauri
+  // and borrow PowerShell's for `target/` builds.
+  let installed = std::env::current_exe()
+    .ok()
+    .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+    .is_some_and(|dir| !dir.ends_with("target/debug") && !dir.ends_with("target/release"));
+  let identifier = handle.config().tauri.bundle.identifier.clone();
+  let app_id = if installed { identifier.as_str() } else { Toast::POWERSHELL_APP_ID };
+
+  let handle = handle.clone();
+  Toast::new(app_id)
+    .title(title)
+    .text1(body)
+    .on_activated(move |_| {
+      show_main_window(&handle);
+      Ok(())
+    })
+    .show()
+    .map_err(|error| format!("Failed to show notification: {error}"))
+}
+
+#[cfg(not(windows))]
+fn show(handle: &AppHandle, title: &str, body: &str) -> Result<(), String> {
+  tauri::api::notification::Notification::new(&handle.config().tauri.bundle.identifier)
+    .title(title)
+    .body(body)
+    .show()
+    .map_err(|error| format!("Failed to show notification: {error}"))
+}
@@ -0,0 +1,96 @@
+//! Keeps the main window the same logical size across monitors with
+//! different DPI scales. Windows resizes a window crossing into a monitor
+//! with another scale, and the result is often off, so the last size the
+//! window had at a settled scale is remembered and put back.
+
+use std::sync::Mutex;
+use tauri::{LogicalPosition, LogicalSize, PhysicalSize, Window};
+use tracing::{debug, warn};
+
+/// Scale factors closer than this count as equal.
+const SCALE_EPSILON: f64 = 0.001;
+
+#[derive(Default)]
+pub struct Placement {
+  remembered: Mutex<Option<Remembered>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Remembered {
+  scale_factor: f64,
+  size: LogicalSize<f64>,
+}
+
+fn same_scale(a: f64, b: f64) -> bool {
+  (a - b).abs() < SCALE_EPSILON
+}
+
+/// On `WindowEvent::Resized`: remember the logical size, unless the resize
+/// came with a scale change that `scale_changed` hasn't seen yet.
+pub fn resized(window: &Window, size: PhysicalSize<u32>, placement: &Placement) {
+  let Ok(scale_factor) = window.scale_factor() else {
+    return;
+  };
+  let Ok(mut remembered) = placement.remembered.lock() else {
+    return;
+  };
+  if remembered.map_or(true, |remembered| same_scale(remembered.scale_factor, scale_factor)) {
+    *remembered = Some(Remembered { scale_factor, size: size.to_logical(scale_factor) });
+  }
+}
+
+/// On `WindowEvent::ScaleFactorChanged`: put the remembered logical size
+/// back at the new scale.
+pub fn scale_changed(window: &Window, scale_factor: f64, placement: &Placement) {
+  let Ok(mut remembered) = placement.remembered.lock() else {
+    return;
+  };
+  let Some(previous) = *remembered else {
+    return;
+  };
+  if same_scale(previous.scale_factor, scale_factor) {
+    return;
+  }
+  *remembered = Some(Remembered { scale_factor, ..previous });
+  debug!(from = previous.scale_factor, to = scale_factor, "window moved to another DPI scale");
+  if let Err(error) = window.set_size(previous.size) {
+    warn!(%error, "failed to restore the window size after a DPI change");
+  }
+}
+
+/// Before showing the window from a shortcut: size it to its remembered
+/// logical size at the scale of the monitor it is on, shrunk to fit that
+/// monitor, and move it back onto the monitor if part of it is off-screen.
+pub fn fit_to_monitor(window: &Window, placement: &Placement) {
+  let Ok(Some(monitor)) = window.current_monitor() else {
+    return;
+  };
+  let scale_factor = monitor.scale_factor();
+  let size = match placement.remembered.lock().ok().and_then(|remembered| *remembered) {
+    Some(remembered) => remembered.size,
+    None => match window.inner_size() {
+      Ok(size) => size.to_logical(scale_factor),
+      Err(_) => return,
+    },
+  };
+
+  let area: LogicalSize<f64> = monitor.size().to_logical(scale_factor);
+  let origin: LogicalPosition<f64> = monitor.position().to_logical(scale_factor);
+  let fitted = LogicalSize::new(size.width.min(area.width), size.height.min(area.height));
+  if let Err(error) = window.set_size(fitted) {
+    warn!(%error, "failed to size the window for its monitor");
+    return;
+  }
+
+  let Ok(position) = window.outer_position() else {
+    return;
+  };
+  let position: LogicalPosition<f64> = position.to_logical(scale_factor);
+  let off_screen = position.x < origin.x
+    || position.y < origin.y
+    || position.x + fitted.width > origin.x + area.width
+    || position.y + fitted.height > origin.y + area.height;
+  if off_screen {
+    let _ = window.center();
+  }
+}
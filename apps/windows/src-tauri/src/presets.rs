@@ -0,0 +1,85 @@
+//! Curated starter actions, so new users don't face an empty action list.
+
+use crate::{read_json, setup_file_path, write_json, Action, JsonFormat, SetupFile};
+use tauri::AppHandle;
+use tracing::{info, instrument};
+
+struct Preset {
+  id: &'static str,
+  name: &'static str,
+  prompt: &'static str,
+}
+
+const PRESETS: &[Preset] = &[
+  Preset {
+    id: "summarize",
+    name: "Summarize",
+    prompt: "Summarize the text in a few sentences. Keep the key facts and drop the rest. \
+             Reply with the summary only.",
+  },
+  Preset {
+    id: "translate",
+    name: "Translate to English",
+    prompt: "Translate the text into natural English, keeping its tone and formatting. \
+             Reply with the translation only.",
+  },
+  Preset {
+    id: "fix-grammar",
+    name: "Fix grammar",
+    prompt: "Correct spelling, grammar, and punctuation without changing the meaning or \
+             style. Reply with the corrected text only.",
+  },
+  Preset {
+    id: "explain",
+    name: "Explain",
+    prompt: "Explain the text in plain language for someone new to the topic, in a short \
+             paragraph.",
+  },
+];
+
+/// Add the chosen presets (all of them by default) to the saved actions.
+/// Each gets a fresh id; presets installed before are skipped, so running it
+/// again is harmless. Returns the resulting action list.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub fn install_preset_actions(
+  handle: AppHandle,
+  preset_ids: Option<Vec<String>>,
+) -> Result<Vec<Action>, String> {
+  if let Some(unknown) = preset_ids
+    .iter()
+    .flatten()
+    .find(|id| !PRESETS.iter().any(|preset| preset.id == id.as_str()))
+  {
+    return Err(format!("Unknown preset: {unknown}"));
+  }
+
+  let path = setup_file_path(&handle)?;
+  let mut setup = read_json::<SetupFile>(&path)?
+    .ok_or_else(|| "Setup has not been completed".to_string())?;
+
+  let created_at = chrono::Utc::now().to_rfc3339();
+  let mut installed = 0;
+  for preset in PRESETS {
+    let wanted = preset_ids.as_ref().map_or(true, |ids| ids.iter().any(|id| id == preset.id));
+    let present = setup.actions.iter().any(|action| action.preset_id.as_deref() == Some(preset.id));
+    if !wanted || present {
+      continue;
+    }
+    setup.actions.push(Action {
+      id: uuid::Uuid::new_v4().to_string(),
+      name: preset.name.to_string(),
+      prompt: preset.prompt.to_string(),
+      created_at: created_at.clone(),
+      preset_id: Some(preset.id.to_string()),
+      ..Action::default()
+    });
+    installed += 1;
+  }
+
+  if installed > 0 {
+    write_json(&path, &setup, JsonFormat::Pretty)?;
+    info!(installed, "installed preset actions");
+  }
+  Ok(setup.actions)
+}
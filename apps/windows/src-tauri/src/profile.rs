@@ -0,0 +1,277 @@
+//! Named configuration profiles. Each profile has its own setup file under
+//! `profiles/<name>.json` and its own keyring entries; logs are shared.
+
+use crate::{
+  app_data_dir, autosave, delete_keyring_entry, load_preferences_from_disk, read_json,
+  register_picker_override, tray, write_json, AppState, JsonFormat, SetupFile,
+};
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{AppHandle, GlobalShortcutManager, Manager, State};
+use tracing::{info, instrument, warn};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Name of the active profile. Kept outside `AppState` because keyring and
+/// setup paths are resolved before the state is managed.
+static ACTIVE: RwLock<String> = RwLock::new(String::new());
+
+/// `profiles.json`: which profiles exist and which one is active.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileIndex {
+  current_profile: String,
+  profiles: Vec<String>,
+}
+
+impl Default for ProfileIndex {
+  fn default() -> Self {
+    Self {
+      current_profile: DEFAULT_PROFILE.to_string(),
+      profiles: vec![DEFAULT_PROFILE.to_string()],
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ProfileSwitched {
+  profile: String,
+}
+
+pub fn active() -> String {
+  match ACTIVE.read() {
+    Ok(name) if !name.is_empty() => name.clone(),
+    _ => DEFAULT_PROFILE.to_string(),
+  }
+}
+
+fn set_active(name: &str) {
+  if let Ok(mut active) = ACTIVE.write() {
+    *active = name.to_string();
+  }
+}
+
+/// Keyring user for `base` in `profile`. The default profile keeps the
+/// un-namespaced names so existing keys keep working.
+pub fn keyring_entry(base: &str, profile: &str) -> Result<Entry, String> {
+  let user = if profile == DEFAULT_PROFILE {
+    base.to_string()
+  } else {
+    format!("{base}@{profile}")
+  };
+  Entry::new("ShortcutAI", &user).map_err(|error| format!("Failed to access keyring: {error}"))
+}
+
+fn index_path(handle: &AppHandle) -> Result<PathBuf, String> {
+  Ok(app_data_dir(handle)?.join("profiles.json"))
+}
+
+fn profiles_dir(handle: &AppHandle) -> Result<PathBuf, String> {
+  Ok(app_data_dir(handle)?.join("profiles"))
+}
+
+/// Setup file of `profile`. Before the first profile operation the default
+/// profile still lives in the legacy `setup.json`.
+pub fn setup_path(handle: &AppHandle, profile: &str) -> Result<PathBuf, String> {
+  let path = profiles_dir(handle)?.join(format!("{profile}.json"));
+  let legacy = app_data_dir(handle)?.join("setup.json");
+  if profile == DEFAULT_PROFILE && !path.exists() && legacy.exists() {
+    return Ok(legacy);
+  }
+  Ok(path)
+}
+
+fn read_index(handle: &AppHandle) -> Result<ProfileIndex, String> {
+  Ok(read_json::<ProfileIndex>(&index_path(handle)?)?.unwrap_or_default())
+}
+
+fn write_index(handle: &AppHandle, index: &ProfileIndex) -> Result<(), String> {
+  fs::create_dir_all(profiles_dir(handle)?)
+    .map_err(|error| format!("Failed to create profiles directory: {error}"))?;
+  write_json(&index_path(handle)?, index, JsonFormat::Pretty)
+}
+
+/// Restore the active profile at launch and move a legacy `setup.json` into
+/// `profiles/default.json`. A read-only config directory keeps using it.
+pub fn init(handle: &AppHandle) {
+  let index = read_index(handle).unwrap_or_else(|error| {
+    warn!(%error, "profile index is unreadable, using the default profile");
+    ProfileIndex::default()
+  });
+  set_active(&index.current_profile);
+
+  let (Ok(legacy), Ok(dir)) = (setup_path(handle, DEFAULT_PROFILE), profiles_dir(handle)) else {
+    return;
+  };
+  if let Err(error) = fs::create_dir_all(&dir) {
+    warn!(%error, "failed to create profiles directory");
+    return;
+  }
+  if legacy.ends_with("setup.json") {
+    match fs::rename(&legacy, dir.join(format!("{DEFAULT_PROFILE}.json"))) {
+      Ok(()) => info!("moved setup.json into the default profile"),
+      Err(error) => warn!(%error, "failed to move setup.json into the default profile"),
+    }
+  }
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+  let valid = !name.is_empty()
+    && name.len() <= 32
+    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+  if valid {
+    Ok(())
+  } else {
+    Err("Profile names use 1-32 letters, digits, '-' or '_'".to_string())
+  }
+}
+
+#[tauri::command]
+pub fn list_profiles(handle: AppHandle) -> Result<ProfileIndex, String> {
+  read_index(&handle)
+}
+
+/// Add a profile, optionally starting from a copy of the active profile's
+/// setup. API keys are never copied.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub fn create_profile(
+  handle: AppHandle,
+  name: String,
+  copy_current: Option<bool>,
+) -> Result<ProfileIndex, String> {
+  validate_name(&name)?;
+  let mut index = read_index(&handle)?;
+  if index.profiles.contains(&name) {
+    return Err(format!("Profile {name} already exists"));
+  }
+
+  index.profiles.push(name.clone());
+  write_index(&handle, &index)?;
+
+  if copy_current.unwrap_or(false) {
+    if let Some(setup) = read_json::<SetupFile>(&setup_path(&handle, &active())?)? {
+      let copy = SetupFile { api_key: None, ..setup };
+      write_json(&setup_path(&handle, &name)?, &copy, JsonFormat::Pretty)?;
+    }
+  }
+  Ok(index)
+}
+
+/// Make `name` the active profile: reload preferences, drop the previous
+/// profile's action shortcuts, and re-register the main shortcut's variants.
+/// The UI reloads setup and rebinds action shortcuts on `profile-switched`.
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+pub fn switch_profile(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  name: String,
+) -> Result<(), String> {
+  let mut index = read_index(&handle)?;
+  if !index.profiles.contains(&name) {
+    return Err(format!("Unknown profile: {name}"));
+  }
+  if index.current_profile == name {
+    return Ok(());
+  }
+  // A pending edit belongs to the profile being left.
+  autosave::flush(&handle)?;
+
+  index.current_profile = name.clone();
+  write_index(&handle, &index)?;
+  set_active(&name);
+
+  *state
+    .preferences
+    .lock()
+    .map_err(|_| "Failed to lock preferences state".to_string())? =
+    load_preferences_from_disk(&handle);
+
+  let mut shortcut_manager = handle.global_shortcut_manager();
+  for (_, accelerator) in state
+    .action_shortcuts
+    .lock()
+    .map_err(|_| "Failed to lock action shortcut state".to_string())?
+    .drain()
+  {
+    let _ = shortcut_manager.unregister(&accelerator);
+  }
+
+  let main_shortcut = state
+    .active_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?
+    .clone();
+  if let Some(main_shortcut) = main_shortcut {
+    register_picker_override(&handle, &state, &main_shortcut)?;
+  }
+
+  tray::refresh(&handle);
+  info!(profile = %name, "switched profile");
+  let _ = handle.emit_all("profile-switched", ProfileSwitched { profile: name });
+  Ok(())
+}
+
+/// Remove a profile with its setup file and keyring entries. The active
+/// profile can't be deleted.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub fn delete_profile(handle: AppHandle, name: String) -> Result<ProfileIndex, String> {
+  let mut index = read_index(&handle)?;
+  if index.current_profile == name {
+    return Err("The active profile can't be deleted".to_string());
+  }
+  if !index.profiles.contains(&name) {
+    return Err(format!("Unknown profile: {name}"));
+  }
+
+  remove(&handle, &name)?;
+  index.profiles.retain(|profile| profile != &name);
+  write_index(&handle, &index)?;
+  Ok(index)
+}
+
+/// Delete the setup file and keyring entries of `name`.
+fn remove(handle: &AppHandle, name: &str) -> Result<(), String> {
+  let path = setup_path(handle, name)?;
+  if let Some(setup) = read_json::<SetupFile>(&path)? {
+    delete_keyring_entry(keyring_entry("api_key", name)?)?;
+    let providers = std::iter::once(&setup.provider)
+      .chain(setup.fallback_providers.iter().map(|config| &config.provider));
+    for provider in providers {
+      delete_keyring_entry(keyring_entry(&format!("api_key:{provider}"), name)?)?;
+    }
+  }
+  if path.exists() {
+    fs::remove_file(&path).map_err(|error| format!("Failed to delete profile {name}: {error}"))?;
+  }
+  Ok(())
+}
+
+/// Delete every profile, with its setup file and keyring entries, and the
+/// profile index, leaving only a blank default profile active.
+pub fn remove_all(handle: &AppHandle) -> Result<(), String> {
+  let index = read_index(handle)?;
+  let mut names = index.profiles;
+  if !names.iter().any(|name| name == DEFAULT_PROFILE) {
+    names.push(DEFAULT_PROFILE.to_string());
+  }
+  for name in &names {
+    remove(handle, name)?;
+  }
+  // The primary key may be stored without a setup file to name it.
+  delete_keyring_entry(keyring_entry("api_key", DEFAULT_PROFILE)?)?;
+
+  let index_path = index_path(handle)?;
+  if index_path.exists() {
+    fs::remove_file(&index_path)
+      .map_err(|error| format!("Failed to delete the profile index: {error}"))?;
+  }
+  set_active(DEFAULT_PROFILE);
+  Ok(())
+}
@@ -0,0 +1,1643 @@
+//! Native AI provider calls. Mirrors `callAI` in `platform.ts` so actions can
+//! run without the window (clipboard runs, silent shortcut runs).
+
+use crate::raw_response::{self, RawResponse};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+  OpenAI,
+  Anthropic,
+  OpenRouter,
+  Perplexity,
+  Groq,
+  /// A local Ollama server. It takes no key and always streams.
+  Ollama,
+}
+
+impl Provider {
+  const ALL: [Provider; 6] = [
+    Provider::OpenAI,
+    Provider::Anthropic,
+    Provider::OpenRouter,
+    Provider::Perplexity,
+    Provider::Groq,
+    Provider::Ollama,
+  ];
+
+  pub fn parse(name: &str) -> Result<Self, String> {
+    match name {
+      "OpenAI" => Ok(Provider::OpenAI),
+      "Anthropic" => Ok(Provider::Anthropic),
+      "OpenRouter" => Ok(Provider::OpenRouter),
+      "Perplexity" => Ok(Provider::Perplexity),
+      "Groq" => Ok(Provider::Groq),
+      "Ollama" => Ok(Provider::Ollama),
+      other => Err(format!("Unknown provider: {other}")),
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Provider::OpenAI => "OpenAI",
+      Provider::Anthropic => "Anthropic",
+      Provider::OpenRouter => "OpenRouter",
+      Provider::Perplexity => "Perplexity",
+      Provider::Groq => "Groq",
+      Provider::Ollama => "Ollama",
+    }
+  }
+
+  pub fn default_model(self) -> &'static str {
+    match self {
+      Provider::OpenAI => "gpt-4o-mini",
+      Provider::Anthropic => "claude-haiku-4-5-20251001",
+      Provider::OpenRouter => "openai/gpt-4o-mini",
+      Provider::Perplexity => "llama-3.1-sonar-small-128k-online",
+      Provider::Groq => "llama-3.1-8b-instant",
+      Provider::Ollama => "llama3.2",
+    }
+  }
+
+  /// The route a completion goes to. OpenAI's Responses API is the only
+  /// alternative, and only OpenAI offers it.
+  fn completion_route(self, openai_endpoint: OpenAIEndpoint) -> Route {
+    match (self, openai_endpoint) {
+      (Provider::OpenAI, OpenAIEndpoint::Responses) => Route::Responses,
+      _ => Route::Completion,
+    }
+  }
+
+  /// Where `route` lives under `base_url`. A `{model}` segment puts the model
+  /// in the path, and the request body then goes without a `model` field.
+  fn path(self, route: Route) -> &'static str {
+    match (self, route) {
+      (Provider::Anthropic, Route::Completion) => "/messages",
+      (Provider::Ollama, Route::Completion) => "/generate",
+      (Provider::Ollama, Route::Models) => "/tags",
+      (_, Route::Completion) => "/chat/completions",
+      (_, Route::Responses) => "/responses",
+      (_, Route::Models) => "/models",
+      (_, Route::Key) => "/key",
+    }
+  }
+
+  /// The full URL of `route` for `model`. Every URL a request goes to is
+  /// built here, so providers that want the model in the path fit in
+  /// without touching the others.
+  fn url(self, route: Route, model: &str) -> String {
+    format!("{}{}", self.base_url(), fill_model(self.path(route), model))
+  }
+
+  /// Whether `route` takes the model in its path rather than the body.
+  fn model_in_path(self, route: Route) -> bool {
+    self.path(route).contains(MODEL_SEGMENT)
+  }
+
+  fn base_url(self) -> &'static str {
+    match self {
+      Provider::OpenAI => "https://api.openai.com/v1",
+      Provider::Anthropic => "https://api.anthropic.com/v1",
+      Provider::OpenRouter => "https://openrouter.ai/api/v1",
+      Provider::Perplexity => "https://api.perplexity.ai",
+      Provider::Groq => "https://api.groq.com/openai/v1",
+      // Ollama's default address.
+      Provider::Ollama => "http://localhost:11434/api",
+    }
+  }
+
+  /// Whether requests need an API key. Ollama runs locally without one.
+  pub fn needs_api_key(self) -> bool {
+    self != Provider::Ollama
+  }
+
+  /// How every key this provider issues starts; `None` if it issues none.
+  fn key_prefix(self) -> Option<&'static str> {
+    match self {
+      Provider::OpenAI => Some("sk-"),
+      Provider::Anthropic => Some("sk-ant-"),
+      Provider::OpenRouter => Some("sk-or-"),
+      Provider::Perplexity => Some("pplx-"),
+      Provider::Groq => Some("gsk_"),
+      Provider::Ollama => None,
+    }
+  }
+
+  /// Why `api_key` obviously isn't a key for this provider, if it isn't:
+  /// empty, containing whitespace, or with another provider's prefix or
+  /// none. Surrounding whitespace is trimmed by every save and ignored here.
+  /// Providers that take no key accept anything.
+  pub fn key_format_warning(self, api_key: &str) -> Option<String> {
+    let prefix = self.key_prefix()?;
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+      return Some("The API key is empty".to_string());
+    }
+    if api_key.chars().any(char::is_whitespace) {
+      return Some("The API key contains spaces or line breaks".to_string());
+    }
+    // Prefixes overlap ("sk-" and "sk-ant-"), so the longest match decides.
+    let issuer = Self::ALL
+      .into_iter()
+      .filter_map(|provider| provider.key_prefix().map(|prefix| (provider, prefix)))
+      .filter(|(_, prefix)| api_key.starts_with(prefix))
+      .max_by_key(|(_, prefix)| prefix.len());
+    match issuer {
+      Some((issuer, _)) if issuer == self => None,
+      Some((issuer, _)) => {
+        Some(format!("This looks like a {} key, not a {} key", issuer.name(), self.name()))
+      }
+      None => Some(format!("{} keys start with {prefix}", self.name())),
+    }
+  }
+
+  /// Whether `model` takes a reasoning effort on this provider. OpenRouter
+  /// normalizes the parameter and drops it for models without reasoning.
+  fn supports_reasoning(self, model: &str) -> bool {
+    let prefixes: &[&str] = match self {
+      Provider::OpenAI => &["o1", "o3", "o4", "gpt-5"],
+      Provider::Anthropic => &["claude-3-7", "claude-sonnet-4", "claude-opus-4", "claude-haiku-4-5"],
+      Provider::Groq => &["openai/gpt-oss"],
+      Provider::OpenRouter => return true,
+      Provider::Perplexity | Provider::Ollama => return false,
+    };
+    prefixes.iter().any(|prefix| model.starts_with(prefix))
+  }
+
+  fn supports_seed(self) -> bool {
+    matches!(
+      self,
+      Provider::OpenAI | Provider::OpenRouter | Provider::Groq | Provider::Ollama
+    )
+  }
+
+  /// What the UI may offer for `model` on this provider. Model families are
+  /// matched by prefix; OpenRouter routes to many backends, so it claims the
+  /// optional features and lets the backend ignore what it can't do.
+  pub fn capabilities(self, model: &str) -> Capabilities {
+    let reasoning = self.supports_reasoning(model);
+    let matches = |prefixes: &[&str]| prefixes.iter().any(|prefix| model.starts_with(prefix));
+    let vision = match self {
+      Provider::OpenAI => matches(&["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"]),
+      Provider::Anthropic => model.starts_with("claude-") && !model.starts_with("claude-2"),
+      Provider::OpenRouter => true,
+      Provider::Groq => {
+        matches(&["meta-llama/llama-4", "llama-3.2-11b-vision", "llama-3.2-90b-vision"])
+      }
+      Provider::Perplexity | Provider::Ollama => false,
+    };
+    // OpenAI reasoning models only run at their default sampling settings.
+    let openai_reasoning = self == Provider::OpenAI && reasoning;
+    Capabilities {
+      streaming: true,
+      vision,
+      json_mode: matches!(self, Provider::OpenAI | Provider::OpenRouter | Provider::Groq),
+      stop_sequences: self.max_stop_sequences().filter(|_| !openai_reasoning),
+      seed: self.supports_seed(),
+      reasoning,
+      temperature: !openai_reasoning,
+      metadata: self == Provider::OpenAI,
+      user_id: !matches!(self, Provider::Perplexity | Provider::Ollama),
+      tools: !matches!(self, Provider::Perplexity | Provider::Ollama),
+    }
+  }
+
+  /// How many stop sequences a request may carry, or `None` if the provider
+  /// doesn't take them. Anthropic and Ollama publish no limit; 8 keeps
+  /// requests sane.
+  pub fn max_stop_sequences(self) -> Option<usize> {
+    match self {
+      Provider::OpenAI | Provider::OpenRouter | Provider::Groq => Some(4),
+      Provider::Anthropic | Provider::Ollama => Some(8),
+      Provider::Perplexity => None,
+    }
+  }
+}
+
+/// Result of `Provider::capabilities`, for `provider_capabilities`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+  /// The provider's API can stream. Native runs always stream from Ollama
+  /// and from Anthropic with `ProviderSettings::stream`; for other providers
+  /// this is for the window's own provider calls.
+  pub streaming: bool,
+  /// Accepts image input.
+  pub vision: bool,
+  /// Can be asked for a JSON object response.
+  pub json_mode: bool,
+  /// Most stop sequences a request may carry; `None` if they are unsupported.
+  pub stop_sequences: Option<usize>,
+  pub seed: bool,
+  /// Takes `Action::reasoning_effort`.
+  pub reasoning: bool,
+  /// Takes temperature and top-p from a parameter preset.
+  pub temperature: bool,
+  /// Takes per-action metadata tags.
+  pub metadata: bool,
+  /// Takes the anonymous user id.
+  pub user_id: bool,
+  /// Can call the action's local tools. Anthropic models can't while thinking.
+  pub tools: bool,
+}
+
+/// Which OpenAI API requests go to. Chat Completions stays the default for
+/// compatibility with gateways that only proxy it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpenAIEndpoint {
+  /// `/v1/chat/completions`.
+  #[default]
+  ChatCompletions,
+  /// `/v1/responses`. It takes no seed or stop sequences; both are dropped.
+  Responses,
+}
+
+/// The requests ShortcutAI sends to a provider; see `Provider::url`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Route {
+  /// A completion: Chat Completions, or Messages for Anthropic.
+  Completion,
+  /// OpenAI's Responses API.
+  Responses,
+  /// The model list, which doubles as a free key check.
+  Models,
+  /// OpenRouter's key info.
+  Key,
+}
+
+/// Placeholder for the model in a route's path.
+const MODEL_SEGMENT: &str = "{model}";
+
+/// `path` with the model percent-encoded into its `{model}` segment, so ids
+/// like `openai/gpt-4o-mini` stay a single segment.
+fn fill_model(path: &str, model: &str) -> String {
+  if !path.contains(MODEL_SEGMENT) {
+    return path.to_string();
+  }
+  let mut encoded = String::with_capacity(model.len());
+  for byte in model.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+        encoded.push(byte as char)
+      }
+      _ => encoded.push_str(&format!("%{byte:02X}")),
+    }
+  }
+  path.replace(MODEL_SEGMENT, &encoded)
+}
+
+/// How hard a reasoning model should think before answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReasoningEffort {
+  Low,
+  Medium,
+  High,
+}
+
+impl ReasoningEffort {
+  fn as_str(self) -> &'static str {
+    match self {
+      ReasoningEffort::Low => "low",
+      ReasoningEffort::Medium => "medium",
+      ReasoningEffort::High => "high",
+    }
+  }
+
+  /// Anthropic takes a thinking budget in tokens rather than an effort level.
+  fn thinking_budget(self) -> u32 {
+    match self {
+      ReasoningEffort::Low => 1024,
+      ReasoningEffort::Medium => 4096,
+      ReasoningEffort::High => 16_384,
+    }
+  }
+}
+
+/// Output length for requests that don't set `max_tokens`.
+const DEFAULT_MAX_TOKENS: u32 = 2048;
+
+/// Phrases providers use when the input doesn't fit the model's context.
+const CONTEXT_LENGTH_MARKERS: &[&str] = &[
+  "context_length_exceeded",
+  "maximum context length",
+  "context window",
+  "prompt is too long",
+  "reduce the length of the messages",
+];
+
+/// Sampling parameters, defined once as a named preset in setup and
+/// referenced from actions. Unset fields keep the provider's defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModelParameters {
+  /// 0-2. Anthropic tops out at 1, so higher values are capped there.
+  pub temperature: Option<f64>,
+  /// 0-1.
+  pub top_p: Option<f64>,
+  pub max_tokens: Option<u32>,
+}
+
+impl ModelParameters {
+  pub fn validate(&self) -> Result<(), String> {
+    if let Some(temperature) = self.temperature.filter(|value| !(0.0..=2.0).contains(value)) {
+      return Err(format!("Temperature must be between 0 and 2, got {temperature}"));
+    }
+    if let Some(top_p) = self.top_p.filter(|value| !(0.0..=1.0).contains(value)) {
+      return Err(format!("Top-p must be between 0 and 1, got {top_p}"));
+    }
+    if self.max_tokens == Some(0) {
+      return Err("Max tokens must be at least 1".to_string());
+    }
+    Ok(())
+  }
+
+  fn max_tokens(&self) -> u32 {
+    self.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+  }
+}
+
+/// One completion request: the action prompt as system prompt, the captured
+/// text as the user message.
+pub struct CompletionRequest<'a> {
+  pub provider: Provider,
+  pub api_key: &'a str,
+  pub model: &'a str,
+  pub system_prompt: &'a str,
+  pub input: &'a str,
+  /// Extra headers configured for this provider, already validated.
+  pub custom_headers: &'a HashMap<String, String>,
+  /// Ignored for models that don't reason.
+  pub reasoning_effort: Option<ReasoningEffort>,
+  /// Anonymous id for the provider's abuse monitoring; Perplexity and Ollama
+  /// get none.
+  pub user_id: Option<&'a str>,
+  /// Per-action tags. Only OpenAI takes arbitrary metadata.
+  pub metadata: &'a HashMap<String, String>,
+  /// Sampling seed for reproducible outputs; dropped where unsupported.
+  pub seed: Option<i64>,
+  /// Stop sequences, already validated against `max_stop_sequences`.
+  pub stop: &'a [String],
+  /// Resolved from the action's parameter preset. Sampling parameters are
+  /// dropped when reasoning is on and for models that reject them.
+  pub parameters: ModelParameters,
+  /// Requests with a larger JSON body are refused before sending. Zero
+  /// disables the limit.
+  pub max_body_bytes: usize,
+  /// Covers the whole exchange, from connecting to reading the response.
+  pub timeout: Duration,
+  /// Cancelling it drops the request, closing its connection.
+  pub cancel: &'a CancellationToken,
+  /// Local tools the model may call; dropped where unsupported.
+  pub tools: &'a [ToolDefinition],
+  /// Tool calls already answered in this run, oldest first.
+  pub tool_rounds: &'a [ToolRound],
+  /// Ignored by providers other than OpenAI.
+  pub openai_endpoint: OpenAIEndpoint,
+  /// Where to keep the raw response, when debugging.
+  pub raw_response: Option<&'a raw_response::Slot>,
+  /// Read the answer as an event stream. Only Anthropic streams on request,
+  /// and only when no tools are offered, since only text deltas are kept.
+  /// Ollama always streams.
+  pub stream: bool,
+  /// Called with each piece of a streamed answer as it arrives.
+  pub on_text: Option<&'a (dyn Fn(&str) + Sync)>,
+}
+
+/// A local tool as described to the provider.
+pub struct ToolDefinition {
+  pub name: &'static str,
+  pub description: &'static str,
+  /// JSON schema of the arguments.
+  pub parameters: Value,
+}
+
+/// The model asking for a tool to be run.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+  /// Provider-assigned id the output is sent back under.
+  pub id: String,
+  pub name: String,
+  pub arguments: Value,
+}
+
+/// Tool calls from one response with their outputs, in the same order.
+pub struct ToolRound {
+  pub calls: Vec<ToolCall>,
+  pub outputs: Vec<String>,
+}
+
+/// Generated text plus usage details worth logging.
+pub struct Completion {
+  pub text: String,
+  /// Tokens spent reasoning, when the provider reports them separately.
+  pub reasoning_tokens: Option<u32>,
+  /// Backend configuration that served a seeded request; outputs are only
+  /// reproducible while it stays the same.
+  pub system_fingerprint: Option<String>,
+  /// Tools the model wants run before it answers; `text` may be empty then.
+  pub tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug)]
+pub enum ProviderError {
+  /// No connection could be made (DNS, refused, TLS), so nothing was sent.
+  Connect(String),
+  /// The connection failed after the request may have been sent.
+  Network(String),
+  /// The provider answered with a non-success status.
+  Status { status: u16, body: String },
+  /// The response body couldn't be understood.
+  InvalidResponse(String),
+  /// The request body exceeded `max_body_bytes`; nothing was sent.
+  RequestTooLarge { size: usize, limit: usize },
+  /// No complete response arrived within the request timeout.
+  Timeout(Duration),
+  /// The run was cancelled while waiting for the provider.
+  Cancelled,
+}
+
+impl ProviderError {
+  fn from_transport(error: reqwest::Error, timeout: Duration) -> Self {
+    if error.is_connect() {
+      ProviderError::Connect(error.to_string())
+    } else if error.is_timeout() {
+      ProviderError::Timeout(timeout)
+    } else {
+      ProviderError::Network(error.to_string())
+    }
+  }
+
+  /// Whether the model refused the request for exceeding its context window.
+  pub fn is_context_length(&self) -> bool {
+    let ProviderError::Status { status: 400 | 413, body } = self else {
+      return false;
+    };
+    let body = body.to_ascii_lowercase();
+    CONTEXT_LENGTH_MARKERS.iter().any(|marker| body.contains(marker))
+  }
+
+  /// Share of the input likely to fit after a context-length error. Messages
+  /// that state both the limit and the request's size in tokens ("maximum
+  /// context length is 128000 tokens ... resulted in 130452 tokens") give
+  /// the ratio, less a margin; otherwise half is kept.
+  pub fn context_fit_ratio(&self) -> f64 {
+    let ProviderError::Status { body, .. } = self else {
+      return 0.5;
+    };
+    // Token counts are the only numbers this large in these messages.
+    let counts: Vec<f64> = body
+      .split(|c: char| !c.is_ascii_digit())
+      .filter_map(|digits| digits.parse::<u64>().ok())
+      .filter(|&count| count >= 1000)
+      .map(|count| count as f64)
+      .collect();
+    let limit = counts.iter().copied().fold(f64::INFINITY, f64::min);
+    let requested = counts.iter().copied().fold(0.0, f64::max);
+    if counts.len() < 2 || limit >= requested {
+      return 0.5;
+    }
+    (limit / requested * 0.9).clamp(0.1, 0.9)
+  }
+
+  /// Coarse, content-free kind of failure, for telemetry.
+  pub fn category(&self) -> &'static str {
+    match self {
+      ProviderError::Connect(_) | ProviderError::Network(_) => "network",
+      ProviderError::Status { status: 401 | 403, .. } => "auth",
+      ProviderError::Status { status: 429, .. } => "rateLimit",
+      ProviderError::Status { status, .. } if *status >= 500 => "server",
+      ProviderError::Status { .. } if self.is_context_length() => "contextLength",
+      ProviderError::Status { .. } => "request",
+      ProviderError::InvalidResponse(_) => "invalidResponse",
+      ProviderError::RequestTooLarge { .. } => "requestTooLarge",
+      ProviderError::Timeout(_) => "timeout",
+      ProviderError::Cancelled => "cancelled",
+    }
+  }
+
+  /// Whether sending the request again is safe, ambiguous or pointless.
+  /// Only failures where the provider certainly didn't run the request are
+  /// safe: no connection, or a refusal to take it on (429, 503, and 529,
+  /// Anthropic's `overloaded_error`). After a
+  /// timeout or a dropped connection the model may already have run, and
+  /// billed, the request. Client errors such as bad keys or invalid requests
+  /// would fail the same way again.
+  pub fn retry_safety(&self) -> RetrySafety {
+    match self {
+      ProviderError::Connect(_) | ProviderError::Status { status: 429 | 503 | 529, .. } => {
+        RetrySafety::Safe
+      }
+      ProviderError::Network(_) | ProviderError::Timeout(_) => RetrySafety::Ambiguous,
+      ProviderError::Status { status, .. } if *status >= 500 => RetrySafety::Ambiguous,
+      ProviderError::Status { .. }
+      | ProviderError::InvalidResponse(_)
+      | ProviderError::RequestTooLarge { .. }
+      | ProviderError::Cancelled => RetrySafety::Pointless,
+    }
+  }
+
+  /// Whether `policy` allows sending the request again to the same provider.
+  pub fn should_retry(&self, policy: RetryPolicy) -> bool {
+    matches!(
+      (self.retry_safety(), policy),
+      (RetrySafety::Safe, RetryPolicy::SafeOnly | RetryPolicy::AnyOutage)
+        | (RetrySafety::Ambiguous, RetryPolicy::AnyOutage)
+    )
+  }
+
+  /// Whether another provider might succeed where this one failed: the
+  /// provider was unreachable or had a server-side error. Client errors such
+  /// as bad keys or invalid requests would fail the same way elsewhere.
+  /// Independent of `RetryPolicy`, which only governs resending to the same
+  /// provider.
+  pub fn should_fail_over(&self) -> bool {
+    match self {
+      ProviderError::Connect(_) | ProviderError::Network(_) | ProviderError::Timeout(_) => true,
+      ProviderError::Status { status, .. } => *status >= 500,
+      ProviderError::InvalidResponse(_)
+      | ProviderError::RequestTooLarge { .. }
+      | ProviderError::Cancelled => false,
+    }
+  }
+}
+
+/// See `ProviderError::retry_safety`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetrySafety {
+  Safe,
+  Ambiguous,
+  Pointless,
+}
+
+/// Which failed requests are resent to the same provider. Failing over to
+/// fallback providers follows `ProviderError::should_fail_over` instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RetryPolicy {
+  /// Only failures the provider certainly didn't process.
+  #[default]
+  SafeOnly,
+  /// Also timeouts, dropped connections and other server errors, which may
+  /// run and bill a request twice.
+  AnyOutage,
+  /// Never resend; a failure ends the run.
+  Never,
+}
+
+impl fmt::Display for ProviderError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ProviderError::Connect(message) => write!(f, "Couldn't connect to the provider: {message}"),
+      ProviderError::Network(message) => write!(f, "Network error: {message}"),
+      ProviderError::Status { status, body } => write!(f, "{status}: {body}"),
+      ProviderError::InvalidResponse(message) => write!(f, "Invalid provider response: {message}"),
+      ProviderError::RequestTooLarge { size, limit } => write!(
+        f,
+        "The request is {} KB, over the {} KB limit; select less text or raise the limit",
+        size.div_ceil(1024),
+        limit / 1024
+      ),
+      ProviderError::Timeout(after) => {
+        write!(f, "The provider didn't answer within {:.1}s", after.as_secs_f64())
+      }
+      ProviderError::Cancelled => write!(f, "The run was cancelled"),
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct OpenAICompatibleResponse {
+  choices: Vec<OpenAIChoice>,
+  #[serde(default)]
+  usage: Option<OpenAIUsage>,
+  #[serde(default)]
+  system_fingerprint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIUsage {
+  #[serde(default)]
+  completion_tokens_details: Option<OpenAICompletionTokensDetails>,
+}
+
+#[derive(Deserialize)]
+struct OpenAICompletionTokensDetails {
+  #[serde(default)]
+  reasoning_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIChoice {
+  message: OpenAIMessage,
+}
+
+#[derive(Deserialize)]
+struct OpenAIMessage {
+  #[serde(default)]
+  content: Option<String>,
+  #[serde(default)]
+  tool_calls: Vec<OpenAIToolCall>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIToolCall {
+  id: String,
+  function: OpenAIFunctionCall,
+}
+
+#[derive(Deserialize)]
+struct OpenAIFunctionCall {
+  name: String,
+  /// JSON-encoded, and not always valid JSON.
+  arguments: String,
+}
+
+#[derive(Deserialize)]
+struct ResponsesResponse {
+  #[serde(default)]
+  output: Vec<ResponsesOutputItem>,
+  #[serde(default)]
+  usage: Option<ResponsesUsage>,
+}
+
+#[derive(Deserialize)]
+struct ResponsesUsage {
+  #[serde(default)]
+  output_tokens_details: Option<OpenAICompletionTokensDetails>,
+}
+
+#[derive(Deserialize)]
+struct ResponsesOutputItem {
+  #[serde(rename = "type")]
+  kind: String,
+  /// Set on `message` items.
+  #[serde(default)]
+  content: Vec<ResponsesContent>,
+  /// The rest are set on `function_call` items.
+  #[serde(default)]
+  call_id: Option<String>,
+  #[serde(default)]
+  name: Option<String>,
+  /// JSON-encoded, like in Chat Completions.
+  #[serde(default)]
+  arguments: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ResponsesContent {
+  #[serde(rename = "type")]
+  kind: String,
+  #[serde(default)]
+  text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+  content: Vec<AnthropicContent>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicContent {
+  #[serde(rename = "type")]
+  kind: String,
+  #[serde(default)]
+  text: Option<String>,
+  /// Set on `tool_use` blocks.
+  #[serde(default)]
+  id: Option<String>,
+  #[serde(default)]
+  name: Option<String>,
+  #[serde(default)]
+  input: Option<Value>,
+}
+
+/// One server-sent event of an Anthropic stream. Its `type` repeats the
+/// `event:` line, so only the data is read.
+#[derive(Deserialize)]
+struct AnthropicEvent {
+  #[serde(rename = "type")]
+  kind: String,
+  /// Set on `content_block_delta` and, in another shape, `message_delta`.
+  #[serde(default)]
+  delta: Option<AnthropicDelta>,
+  /// Set on `error`.
+  #[serde(default)]
+  error: Option<AnthropicError>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicDelta {
+  /// `text_delta`, `thinking_delta`, `input_json_delta`...; `message_delta`
+  /// carries none.
+  #[serde(rename = "type", default)]
+  kind: String,
+  #[serde(default)]
+  text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicError {
+  #[serde(rename = "type")]
+  kind: String,
+}
+
+/// Text collected from a streamed answer as its bytes arrive.
+trait StreamEvents {
+  /// Take in the next bytes of the stream. Returns whether the answer is
+  /// complete, after which the rest of the stream is ignored.
+  fn feed(&mut self, bytes: &[u8]) -> Result<bool, ProviderError>;
+
+  /// The text so far. It only ever grows.
+  fn text(&self) -> &str;
+}
+
+/// Text collected from an Anthropic event stream as its bytes arrive.
+#[derive(Default)]
+struct AnthropicEvents {
+  /// Received bytes not yet making up a whole event.
+  pending: Vec<u8>,
+  text: String,
+}
+
+impl StreamEvents for AnthropicEvents {
+  /// Complete once `message_stop` arrives.
+  fn feed(&mut self, bytes: &[u8]) -> Result<bool, ProviderError> {
+    // Events end with a blank line; dropping CRs makes that `\n\n` either way.
+    self.pending.extend(bytes.iter().filter(|&&byte| byte != b'\r'));
+    while let Some(end) = self.pending.windows(2).position(|pair| pair == b"\n\n") {
+      let event: Vec<u8> = self.pending.drain(..end + 2).collect();
+      if self.event(&String::from_utf8_lossy(&event))? {
+        return Ok(true);
+      }
+    }
+    Ok(false)
+  }
+
+  fn text(&self) -> &str {
+    &self.text
+  }
+}
+
+impl AnthropicEvents {
+  /// Only text deltas are kept; thinking, tool input, pings and message
+  /// metadata are skipped. An `error` ends the stream like the same error
+  /// sent as a status, so it is retried and reported the same way.
+  fn event(&mut self, event: &str) -> Result<bool, ProviderError> {
+    let data: Vec<&str> = event
+      .lines()
+      .filter_map(|line| line.strip_prefix("data:"))
+      .map(str::trim_start)
+      .collect();
+    if data.is_empty() {
+      return Ok(false);
+    }
+    let data = data.join("\n");
+    let parsed: AnthropicEvent = serde_json::from_str(&data)
+      .map_err(|error| ProviderError::InvalidResponse(error.to_string()))?;
+    match parsed.kind.as_str() {
+      "content_block_delta" => {
+        if let Some(AnthropicDelta { kind, text: Some(text) }) = parsed.delta {
+          if kind == "text_delta" {
+            self.text.push_str(&text);
+          }
+        }
+        Ok(false)
+      }
+      "message_stop" => Ok(true),
+      "error" => {
+        let kind = parsed.error.map(|error| error.kind).unwrap_or_default();
+        Err(ProviderError::Status { status: anthropic_error_status(&kind), body: data })
+      }
+      _ => Ok(false),
+    }
+  }
+}
+
+/// The HTTP status Anthropic answers an error type with before streaming.
+fn anthropic_error_status(kind: &str) -> u16 {
+  match kind {
+    "invalid_request_error" => 400,
+    "authentication_error" => 401,
+    "permission_error" => 403,
+    "not_found_error" => 404,
+    "request_too_large" => 413,
+    "rate_limit_error" => 429,
+    "overloaded_error" => 529,
+    _ => 500,
+  }
+}
+
+/// One line of an Ollama `/api/generate` stream.
+#[derive(Deserialize)]
+struct OllamaChunk {
+  /// The next token or few.
+  #[serde(default)]
+  response: String,
+  /// Set on the last line, which carries timings rather than text.
+  #[serde(default)]
+  done: bool,
+  /// Sent instead when generation fails partway.
+  #[serde(default)]
+  error: Option<String>,
+}
+
+/// Text collected from an Ollama stream of JSON lines as its bytes arrive.
+#[derive(Default)]
+struct OllamaEvents {
+  /// Received bytes not yet making up a whole line.
+  pending: Vec<u8>,
+  text: String,
+}
+
+impl StreamEvents for OllamaEvents {
+  /// Complete once a line has `done: true`. An `error` line ends the stream
+  /// like a server error, so it is retried and reported the same way.
+  fn feed(&mut self, bytes: &[u8]) -> Result<bool, ProviderError> {
+    self.pending.extend_from_slice(bytes);
+    while let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+      let line: Vec<u8> = self.pending.drain(..=end).collect();
+      let line = String::from_utf8_lossy(&line);
+      if line.trim().is_empty() {
+        continue;
+      }
+      let chunk: OllamaChunk = serde_json::from_str(&line)
+        .map_err(|error| ProviderError::InvalidResponse(error.to_string()))?;
+      if let Some(error) = chunk.error {
+        return Err(ProviderError::Status { status: 500, body: error });
+      }
+      self.text.push_str(&chunk.response);
+      if chunk.done {
+        return Ok(true);
+      }
+    }
+    Ok(false)
+  }
+
+  fn text(&self) -> &str {
+    &self.text
+  }
+}
+
+/// Read a stream until `events` has the whole answer, returning the text.
+/// Each new piece goes to `on_text` as it arrives. Everything received is
+/// appended to `received`, for `raw_response`. A stream that ends before the
+/// answer is complete failed rather than finished.
+async fn read_stream(
+  response: &mut reqwest::Response,
+  timeout: Duration,
+  on_text: Option<&(dyn Fn(&str) + Sync)>,
+  mut events: impl StreamEvents,
+  received: &mut Vec<u8>,
+) -> Result<String, ProviderError> {
+  loop {
+    let chunk = response
+      .chunk()
+      .await
+      .map_err(|error| ProviderError::from_transport(error, timeout))?
+      .ok_or_else(|| {
+        ProviderError::Network("The response stream ended before the answer was complete".into())
+      })?;
+    received.extend_from_slice(&chunk);
+    let seen = events.text().len();
+    let complete = events.feed(&chunk)?;
+    let new = &events.text()[seen..];
+    if let Some(on_text) = on_text.filter(|_| !new.is_empty()) {
+      on_text(new);
+    }
+    if complete {
+      return Ok(events.text().to_string());
+    }
+  }
+}
+
+/// The client behind every provider call. Idle connections stay pooled for a
+/// while and are kept alive, so repeated runs skip DNS and the TLS handshake.
+pub fn http_client() -> reqwest::Client {
+  reqwest::Client::builder()
+    .pool_idle_timeout(Duration::from_secs(90))
+    .pool_max_idle_per_host(4)
+    .tcp_keepalive(Duration::from_secs(60))
+    .build()
+    .unwrap_or_default()
+}
+
+/// Open a pooled connection to the provider so the first real request skips
+/// DNS resolution and the TLS handshake. Any HTTP response will do.
+pub async fn warm_up(client: &reqwest::Client, provider: Provider) -> Result<(), ProviderError> {
+  client
+    .head(provider.base_url())
+    .timeout(Duration::from_secs(5))
+    .send()
+    .await
+    .map(|_| ())
+    .map_err(|error| ProviderError::Network(error.to_string()))
+}
+
+/// Check `api_key` against an authenticated endpoint that costs nothing.
+/// `Ok(false)` means the provider has no such endpoint (Perplexity), so the
+/// key could only be checked with a billed completion. Ollama takes no key,
+/// so only that its server answers is checked.
+pub async fn verify_api_key(
+  client: &reqwest::Client,
+  provider: Provider,
+  api_key: &str,
+  custom_headers: &HashMap<String, String>,
+) -> Result<bool, ProviderError> {
+  let model = provider.default_model();
+  let builder = match provider {
+    Provider::Anthropic => client
+      .get(provider.url(Route::Models, model))
+      .header("x-api-key", api_key)
+      .header("anthropic-version", "2023-06-01"),
+    // OpenRouter's model list is public; the key endpoint isn't.
+    Provider::OpenRouter => client.get(provider.url(Route::Key, model)).bearer_auth(api_key),
+    Provider::OpenAI | Provider::Groq => {
+      client.get(provider.url(Route::Models, model)).bearer_auth(api_key)
+    }
+    Provider::Ollama => client.get(provider.url(Route::Models, model)),
+    Provider::Perplexity => return Ok(false),
+  };
+
+  let timeout = Duration::from_secs(10);
+  let response = custom_headers
+    .iter()
+    .fold(builder.timeout(timeout), |builder, (name, value)| builder.header(name, value))
+    .send()
+    .await
+    .map_err(|error| ProviderError::from_transport(error, timeout))?;
+  let status = response.status();
+  if !status.is_success() {
+    let body = response.text().await.unwrap_or_default();
+    return Err(ProviderError::Status { status: status.as_u16(), body });
+  }
+  Ok(true)
+}
+
+/// Send a completion request and return the generated text.
+pub async fn complete(
+  client: &reqwest::Client,
+  request: &CompletionRequest<'_>,
+) -> Result<Completion, ProviderError> {
+  let reasoning_effort = request
+    .reasoning_effort
+    .filter(|_| request.provider.supports_reasoning(request.model));
+
+  let parameters = request.parameters;
+  let capabilities = request.provider.capabilities(request.model);
+  let sampling = reasoning_effort.is_none() && capabilities.temperature;
+  // Anthropic requires thinking blocks to be passed back with tool results,
+  // which runs don't keep, so actions that reason go without tools there.
+  let tools = match request.provider {
+    Provider::Anthropic if reasoning_effort.is_some() => &[],
+    _ if capabilities.tools => request.tools,
+    _ => &[],
+  };
+
+  let stream = match request.provider {
+    Provider::Anthropic => request.stream && tools.is_empty(),
+    // Ollama streams by default, and its tokens are worth showing as they come.
+    Provider::Ollama => true,
+    _ => false,
+  };
+
+  let route = request.provider.completion_route(request.openai_endpoint);
+  let url = request.provider.url(route, request.model);
+  let responses = route == Route::Responses;
+  let (builder, mut body) = match request.provider {
+    Provider::OpenAI if responses => {
+      let mut body = json!({
+        "model": request.model,
+        "instructions": request.system_prompt,
+        "input": responses_input(request),
+        "max_output_tokens": parameters.max_tokens(),
+        // Runs carry their own history; nothing needs keeping server-side.
+        "store": false,
+      });
+      if !tools.is_empty() {
+        let tools: Vec<Value> = tools
+          .iter()
+          .map(|tool| {
+            json!({
+              "type": "function",
+              "name": tool.name,
+              "description": tool.description,
+              "parameters": tool.parameters,
+            })
+          })
+          .collect();
+        body["tools"] = json!(tools);
+      }
+      if let Some(effort) = reasoning_effort {
+        body["reasoning"] = json!({ "effort": effort.as_str() });
+      }
+      if let Some(temperature) = parameters.temperature.filter(|_| sampling) {
+        body["temperature"] = json!(temperature);
+      }
+      if let Some(top_p) = parameters.top_p.filter(|_| sampling) {
+        body["top_p"] = json!(top_p);
+      }
+      if let Some(user_id) = request.user_id {
+        body["user"] = json!(user_id);
+      }
+      if !request.metadata.is_empty() {
+        body["metadata"] = json!(request.metadata);
+      }
+      let builder = client
+        .post(&url)
+        .bearer_auth(request.api_key);
+      (builder, body)
+    }
+    Provider::Anthropic => {
+      let mut body = json!({
+        "model": request.model,
+        "max_tokens": parameters.max_tokens(),
+        "system": request.system_prompt,
+        "messages": anthropic_messages(request),
+      });
+      if !tools.is_empty() {
+        let tools: Vec<Value> = tools
+          .iter()
+          .map(|tool| {
+            json!({
+              "name": tool.name,
+              "description": tool.description,
+              "input_schema": tool.parameters,
+            })
+          })
+          .collect();
+        body["tools"] = json!(tools);
+      }
+      if let Some(effort) = reasoning_effort {
+        // The budget comes out of `max_tokens`, so leave room for the answer.
+        let budget = effort.thinking_budget();
+        body["max_tokens"] = json!(budget + parameters.max_tokens());
+        body["thinking"] = json!({ "type": "enabled", "budget_tokens": budget });
+      }
+      if let Some(temperature) = parameters.temperature.filter(|_| sampling) {
+        body["temperature"] = json!(temperature.min(1.0));
+      }
+      if let Some(top_p) = parameters.top_p.filter(|_| sampling) {
+        body["top_p"] = json!(top_p);
+      }
+      if let Some(user_id) = request.user_id {
+        body["metadata"] = json!({ "user_id": user_id });
+      }
+      if !request.stop.is_empty() {
+        body["stop_sequences"] = json!(request.stop);
+      }
+      if stream {
+        body["stream"] = json!(true);
+      }
+      let builder = client
+        .post(&url)
+        .header("x-api-key", request.api_key)
+        .header("anthropic-version", "2023-06-01");
+      (builder, body)
+    }
+    Provider::Ollama => {
+      let mut options = json!({ "num_predict": parameters.max_tokens() });
+      if let Some(temperature) = parameters.temperature.filter(|_| sampling) {
+        options["temperature"] = json!(temperature);
+      }
+      if let Some(top_p) = parameters.top_p.filter(|_| sampling) {
+        options["top_p"] = json!(top_p);
+      }
+      if let Some(seed) = request.seed {
+        options["seed"] = json!(seed);
+      }
+      if !request.stop.is_empty() {
+        options["stop"] = json!(request.stop);
+      }
+      let body = json!({
+        "model": request.model,
+        "system": request.system_prompt,
+        "prompt": request.input,
+        "stream": stream,
+        "options": options,
+      });
+      (client.post(&url), body)
+    }
+    provider => {
+      let mut body = json!({
+        "model": request.model,
+        "max_tokens": parameters.max_tokens(),
+        "messages": openai_messages(request),
+      });
+      if !tools.is_empty() {
+        let tools: Vec<Value> = tools
+          .iter()
+          .map(|tool| {
+            json!({
+              "type": "function",
+              "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+              },
+            })
+          })
+          .collect();
+        body["tools"] = json!(tools);
+      }
+      match (provider, reasoning_effort) {
+        (Provider::OpenRouter, Some(effort)) => {
+          body["reasoning"] = json!({ "effort": effort.as_str() });
+        }
+        (Provider::OpenAI, Some(effort)) => {
+          // OpenAI reasoning models reject `max_tokens`.
+          if let Some(object) = body.as_object_mut() {
+            object.remove("max_tokens");
+          }
+          body["max_completion_tokens"] = json!(parameters.max_tokens());
+          body["reasoning_effort"] = json!(effort.as_str());
+        }
+        (_, Some(effort)) => body["reasoning_effort"] = json!(effort.as_str()),
+        (_, None) => {}
+      }
+      if let Some(temperature) = parameters.temperature.filter(|_| sampling) {
+        body["temperature"] = json!(temperature);
+      }
+      if let Some(top_p) = parameters.top_p.filter(|_| sampling) {
+        body["top_p"] = json!(top_p);
+      }
+      if let Some(user_id) = request.user_id.filter(|_| provider != Provider::Perplexity) {
+        body["user"] = json!(user_id);
+      }
+      if provider == Provider::OpenAI && !request.metadata.is_empty() {
+        body["metadata"] = json!(request.metadata);
+      }
+      if let Some(seed) = request.seed.filter(|_| provider.supports_seed()) {
+        body["seed"] = json!(seed);
+      }
+      if !request.stop.is_empty() && provider.max_stop_sequences().is_some() {
+        body["stop"] = json!(request.stop);
+      }
+      let builder = client
+        .post(&url)
+        .bearer_auth(request.api_key);
+      (builder, body)
+    }
+  };
+
+  if request.provider.model_in_path(route) {
+    if let Some(object) = body.as_object_mut() {
+      object.remove("model");
+    }
+  }
+  let body = body.to_string().into_bytes();
+  if request.max_body_bytes > 0 && body.len() > request.max_body_bytes {
+    return Err(ProviderError::RequestTooLarge { size: body.len(), limit: request.max_body_bytes });
+  }
+  let builder = builder
+    .header("content-type", "application/json")
+    .body(body)
+    .timeout(request.timeout);
+
+  let builder = request
+    .custom_headers
+    .iter()
+    .fold(builder, |builder, (name, value)| builder.header(name, value));
+
+  let keep_raw = |status: reqwest::StatusCode, headers: &Option<HeaderMap>, body: &str| {
+    if let (Some(slot), Some(headers)) = (request.raw_response, headers) {
+      let raw = RawResponse::new(
+        request.provider.name(),
+        request.model,
+        status.as_u16(),
+        headers,
+        body,
+        request.api_key,
+      );
+      if let Ok(mut last) = slot.lock() {
+        *last = Some(raw);
+      }
+    }
+  };
+
+  let exchange = async {
+    let mut response = builder
+      .send()
+      .await
+      .map_err(|error| ProviderError::from_transport(error, request.timeout))?;
+
+    let status = response.status();
+    let headers = request.raw_response.map(|_| response.headers().clone());
+    if stream && status.is_success() {
+      let mut received = Vec::new();
+      let text = match request.provider {
+        Provider::Ollama => {
+          let events = OllamaEvents::default();
+          read_stream(&mut response, request.timeout, request.on_text, events, &mut received).await
+        }
+        _ => {
+          let events = AnthropicEvents::default();
+          read_stream(&mut response, request.timeout, request.on_text, events, &mut received).await
+        }
+      };
+      keep_raw(status, &headers, &String::from_utf8_lossy(&received));
+      return text.map(|text| Completion {
+        text,
+        reasoning_tokens: None,
+        system_fingerprint: None,
+        tool_calls: Vec::new(),
+      });
+    }
+
+    let text = response.text().await;
+    keep_raw(status, &headers, text.as_deref().unwrap_or_default());
+    if !status.is_success() {
+      let body = text.unwrap_or_default();
+      return Err(ProviderError::Status { status: status.as_u16(), body });
+    }
+
+    let text = text.map_err(|error| {
+      if error.is_timeout() {
+        ProviderError::Timeout(request.timeout)
+      } else {
+        ProviderError::InvalidResponse(error.to_string())
+      }
+    })?;
+    let body = serde_json::from_str::<Value>(&text)
+      .map_err(|error| ProviderError::InvalidResponse(error.to_string()))?;
+    if responses {
+      extract_response_output(body)
+    } else {
+      extract_completion(request.provider, body)
+    }
+  };
+  let mut completion = request
+    .cancel
+    .run_until_cancelled(exchange)
+    .await
+    .ok_or(ProviderError::Cancelled)??;
+  strip_stop_sequence(&mut completion.text, request.stop);
+  Ok(completion)
+}
+
+/// The conversation so far in the Chat Completions shape: the prompt, the
+/// input, then each tool round as an assistant turn and one `tool` message
+/// per output.
+fn openai_messages(request: &CompletionRequest<'_>) -> Vec<Value> {
+  let mut messages = vec![
+    json!({ "role": "system", "content": request.system_prompt }),
+    json!({ "role": "user", "content": request.input }),
+  ];
+  for round in request.tool_rounds {
+    let calls: Vec<Value> = round
+      .calls
+      .iter()
+      .map(|call| {
+        json!({
+          "id": call.id,
+          "type": "function",
+          "function": { "name": call.name, "arguments": call.arguments.to_string() },
+        })
+      })
+      .collect();
+    messages.push(json!({ "role": "assistant", "content": null, "tool_calls": calls }));
+    for (call, output) in round.calls.iter().zip(&round.outputs) {
+      messages.push(json!({ "role": "tool", "tool_call_id": call.id, "content": output }));
+    }
+  }
+  messages
+}
+
+/// The conversation so far in the Messages shape: the input, then each tool
+/// round as `tool_use` blocks answered by a user turn of `tool_result` blocks.
+fn anthropic_messages(request: &CompletionRequest<'_>) -> Vec<Value> {
+  let mut messages = vec![json!({ "role": "user", "content": request.input })];
+  for round in request.tool_rounds {
+    let calls: Vec<Value> = round
+      .calls
+      .iter()
+      .map(|call| {
+        json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments })
+      })
+      .collect();
+    let outputs: Vec<Value> = round
+      .calls
+      .iter()
+      .zip(&round.outputs)
+      .map(|(call, output)| {
+        json!({ "type": "tool_result", "tool_use_id": call.id, "content": output })
+      })
+      .collect();
+    messages.push(json!({ "role": "assistant", "content": calls }));
+    messages.push(json!({ "role": "user", "content": outputs }));
+  }
+  messages
+}
+
+/// The conversation so far as Responses input items: the input, then each
+/// tool round as `function_call` items and their `function_call_output`s. The
+/// prompt goes in `instructions` instead.
+fn responses_input(request: &CompletionRequest<'_>) -> Vec<Value> {
+  let mut input = vec![json!({ "role": "user", "content": request.input })];
+  for round in request.tool_rounds {
+    for call in &round.calls {
+      input.push(json!({
+        "type": "function_call",
+        "call_id": call.id,
+        "name": call.name,
+        "arguments": call.arguments.to_string(),
+      }));
+    }
+    for (call, output) in round.calls.iter().zip(&round.outputs) {
+      input.push(json!({ "type": "function_call_output", "call_id": call.id, "output": output }));
+    }
+  }
+  input
+}
+
+/// Providers normally leave the matched stop sequence out, but some models
+/// behind OpenRouter echo it; it must not end up in the pasted text.
+fn strip_stop_sequence(text: &mut String, stop: &[String]) {
+  if let Some(sequence) = stop.iter().find(|sequence| text.ends_with(sequence.as_str())) {
+    text.truncate(text.len() - sequence.len());
+  }
+}
+
+fn extract_completion(provider: Provider, body: Value) -> Result<Completion, ProviderError> {
+  let invalid = |error: serde_json::Error| ProviderError::InvalidResponse(error.to_string());
+
+  match provider {
+    Provider::Anthropic => {
+      // Skips `thinking` blocks; Anthropic doesn't report thinking tokens
+      // separately from output tokens.
+      let parsed: AnthropicResponse = serde_json::from_value(body).map_err(invalid)?;
+      let mut text = None;
+      let mut tool_calls = Vec::new();
+      for block in parsed.content {
+        match block.kind.as_str() {
+          "text" if text.is_none() => text = block.text,
+          "tool_use" => {
+            let (Some(id), Some(name)) = (block.id, block.name) else {
+              return Err(ProviderError::InvalidResponse("Tool call without an id".to_string()));
+            };
+            tool_calls.push(ToolCall { id, name, arguments: block.input.unwrap_or_default() });
+          }
+          _ => {}
+        }
+      }
+      Ok(Completion {
+        text: text.unwrap_or_default(),
+        reasoning_tokens: None,
+        system_fingerprint: None,
+        tool_calls,
+      })
+    }
+    _ => {
+      let parsed: OpenAICompatibleResponse = serde_json::from_value(body).map_err(invalid)?;
+      let reasoning_tokens = parsed
+        .usage
+        .and_then(|usage| usage.completion_tokens_details)
+        .and_then(|details| details.reasoning_tokens);
+      let message = parsed.choices.into_iter().next().map(|choice| choice.message);
+      let (text, tool_calls) = match message {
+        Some(message) => (message.content.unwrap_or_default(), message.tool_calls),
+        None => (String::new(), Vec::new()),
+      };
+      let tool_calls = tool_calls
+        .into_iter()
+        .map(|call| ToolCall {
+          id: call.id,
+          name: call.function.name,
+          // Malformed arguments reach the tool as null, which it reports.
+          arguments: serde_json::from_str(&call.function.arguments).unwrap_or_default(),
+        })
+        .collect();
+      Ok(Completion {
+        text,
+        reasoning_tokens,
+        system_fingerprint: parsed.system_fingerprint,
+        tool_calls,
+      })
+    }
+  }
+}
+
+/// Text of the `message` items in a Responses body, joined, plus its
+/// function calls. `reasoning` items are skipped.
+fn extract_response_output(body: Value) -> Result<Completion, ProviderError> {
+  let parsed: ResponsesResponse = serde_json::from_value(body)
+    .map_err(|error| ProviderError::InvalidResponse(error.to_string()))?;
+  let reasoning_tokens = parsed
+    .usage
+    .and_then(|usage| usage.output_tokens_details)
+    .and_then(|details| details.reasoning_tokens);
+
+  let mut text = String::new();
+  let mut tool_calls = Vec::new();
+  for item in parsed.output {
+    match item.kind.as_str() {
+      "message" => {
+        let parts = item.content.into_iter().filter(|part| part.kind == "output_text");
+        text.extend(parts.filter_map(|part| part.text));
+      }
+      "function_call" => {
+        let (Some(id), Some(name)) = (item.call_id, item.name) else {
+          return Err(ProviderError::InvalidResponse("Tool call without an id".to_string()));
+        };
+        let arguments = item.arguments.unwrap_or_default();
+        // Malformed arguments reach the tool as null, which it reports.
+        let arguments = serde_json::from_str(&arguments).unwrap_or_default();
+        tool_calls.push(ToolCall { id, name, arguments });
+      }
+      _ => {}
+    }
+  }
+  Ok(Completion { text, reasoning_tokens, system_fingerprint: None, tool_calls })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn anthropic_stream_keeps_text_deltas_until_message_stop() {
+    let stream = concat!(
+      "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n",
+      "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+      "event: content_block_delta\r\n",
+      "data: {\"type\":\"content_block_delta\",\"index\":0,",
+      "\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"hm\"}}\r\n\r\n",
+      "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,",
+      "\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello, \"}}\n\n",
+      "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,",
+      "\"delta\":{\"type\":\"text_delta\",\"text\":\"wörld\"}}\n\n",
+      "event: message_delta\ndata: {\"type\":\"message_delta\",",
+      "\"delta\":{\"stop_reason\":\"end_turn\"}}\n\n",
+      "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+      "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+    );
+    // Chunks split events, and characters, anywhere.
+    let mut events = AnthropicEvents::default();
+    assert!(stream.as_bytes().chunks(7).any(|chunk| events.feed(chunk).unwrap()));
+    assert_eq!(events.text, "Hello, wörld");
+  }
+
+  #[test]
+  fn anthropic_stream_error_is_reported_as_its_status() {
+    let mut events = AnthropicEvents::default();
+    let delta = concat!(
+      "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",",
+      "\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n",
+    );
+    assert!(!events.feed(delta.as_bytes()).unwrap());
+    let error = concat!(
+      "event: error\ndata: {\"type\":\"error\",",
+      "\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+    );
+    match events.feed(error.as_bytes()) {
+      Err(ProviderError::Status { status, body }) => {
+        assert_eq!(status, 529);
+        assert!(body.contains("Overloaded"));
+      }
+      other => panic!("expected a status error, got {:?}", other.map(|_| ())),
+    }
+  }
+
+  #[test]
+  fn ollama_stream_joins_responses_until_done() {
+    let stream = concat!(
+      "{\"model\":\"llama3.2\",\"response\":\"Hello, \",\"done\":false}\n",
+      "{\"model\":\"llama3.2\",\"response\":\"wörld\",\"done\":false}\n",
+      "\n",
+      "{\"model\":\"llama3.2\",\"response\":\"\",\"done\":true,\"eval_count\":3}\n",
+      "{\"response\":\"ignored\",\"done\":false}\n",
+    );
+    // Chunks split lines, and characters, anywhere.
+    let mut events = OllamaEvents::default();
+    assert!(stream.as_bytes().chunks(5).any(|chunk| events.feed(chunk).unwrap()));
+    assert_eq!(events.text, "Hello, wörld");
+  }
+
+  #[test]
+  fn ollama_stream_error_is_a_server_error() {
+    let mut events = OllamaEvents::default();
+    assert!(!events.feed(b"{\"response\":\"Hel\",\"done\":false}\n{\"resp").unwrap());
+    assert_eq!(events.text, "Hel");
+    match events.feed(b"onse\":\"lo\"}\n{\"error\":\"model runner crashed\"}\n") {
+      Err(ProviderError::Status { status, body }) => {
+        assert_eq!(status, 500);
+        assert_eq!(body, "model runner crashed");
+      }
+      other => panic!("expected a status error, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(events.text, "Hello");
+  }
+
+  #[test]
+  fn ollama_takes_any_key() {
+    assert!(!Provider::Ollama.needs_api_key());
+    assert_eq!(Provider::Ollama.key_format_warning(""), None);
+    // The longest prefix still decides among providers that issue keys.
+    assert_eq!(Provider::Anthropic.key_format_warning("sk-ant-abc"), None);
+    assert!(Provider::OpenAI.key_format_warning("sk-ant-abc").is_some());
+    assert_eq!(
+      Provider::Groq.key_format_warning("abc"),
+      Some("Groq keys start with gsk_".to_string())
+    );
+  }
+
+  #[test]
+  fn completion_urls_for_each_provider() {
+    let cases = [
+      (Provider::OpenAI, "gpt-4o-mini", "https://api.openai.com/v1/chat/completions"),
+      (Provider::Anthropic, "claude-haiku-4-5-20251001", "https://api.anthropic.com/v1/messages"),
+      (
+        Provider::OpenRouter,
+        "anthropic/claude-3.5-sonnet:beta",
+        "https://openrouter.ai/api/v1/chat/completions",
+      ),
+      (
+        Provider::Perplexity,
+        "llama-3.1-sonar-small-128k-online",
+        "https://api.perplexity.ai/chat/completions",
+      ),
+      (Provider::Groq, "llama-3.1-8b-instant", "https://api.groq.com/openai/v1/chat/completions"),
+      (Provider::Ollama, "llama3.2", "http://localhost:11434/api/generate"),
+    ];
+    for (provider, model, expected) in cases {
+      let route = provider.completion_route(OpenAIEndpoint::ChatCompletions);
+      assert_eq!(provider.url(route, model), expected, "{}", provider.name());
+      assert!(!provider.model_in_path(route), "{}", provider.name());
+    }
+  }
+
+  #[test]
+  fn responses_endpoint_is_openai_only() {
+    let route = Provider::OpenAI.completion_route(OpenAIEndpoint::Responses);
+    assert_eq!(route, Route::Responses);
+    assert_eq!(Provider::OpenAI.url(route, "o4-mini"), "https://api.openai.com/v1/responses");
+    for provider in Provider::ALL.into_iter().filter(|&provider| provider != Provider::OpenAI) {
+      assert_eq!(provider.completion_route(OpenAIEndpoint::Responses), Route::Completion);
+    }
+  }
+
+  #[test]
+  fn key_check_urls() {
+    assert_eq!(Provider::OpenAI.url(Route::Models, "gpt-4o"), "https://api.openai.com/v1/models");
+    assert_eq!(
+      Provider::Anthropic.url(Route::Models, "claude-sonnet-4-5"),
+      "https://api.anthropic.com/v1/models"
+    );
+    assert_eq!(
+      Provider::OpenRouter.url(Route::Key, "openai/gpt-4o-mini"),
+      "https://openrouter.ai/api/v1/key"
+    );
+    assert_eq!(Provider::Ollama.url(Route::Models, "llama3.2"), "http://localhost:11434/api/tags");
+  }
+
+  #[test]
+  fn only_unprocessed_failures_are_safe_to_retry() {
+    let status = |status: u16| ProviderError::Status { status, body: String::new() };
+    assert_eq!(ProviderError::Connect(String::new()).retry_safety(), RetrySafety::Safe);
+    assert_eq!(status(429).retry_safety(), RetrySafety::Safe);
+    assert_eq!(status(503).retry_safety(), RetrySafety::Safe);
+    assert_eq!(status(529).retry_safety(), RetrySafety::Safe);
+    assert_eq!(status(500).retry_safety(), RetrySafety::Ambiguous);
+    assert_eq!(ProviderError::Network(String::new()).retry_safety(), RetrySafety::Ambiguous);
+    let timeout = ProviderError::Timeout(Duration::from_secs(30));
+    assert_eq!(timeout.retry_safety(), RetrySafety::Ambiguous);
+    assert_eq!(status(401).retry_safety(), RetrySafety::Pointless);
+
+    assert!(status(503).should_retry(RetryPolicy::SafeOnly));
+    assert!(!timeout.should_retry(RetryPolicy::SafeOnly));
+    assert!(timeout.should_retry(RetryPolicy::AnyOutage));
+    assert!(!status(503).should_retry(RetryPolicy::Never));
+  }
+
+  #[test]
+  fn outages_fail_over_whatever_the_retry_policy() {
+    let status = |status: u16| ProviderError::Status { status, body: String::new() };
+    assert!(ProviderError::Timeout(Duration::from_secs(30)).should_fail_over());
+    assert!(ProviderError::Network(String::new()).should_fail_over());
+    assert!(ProviderError::Connect(String::new()).should_fail_over());
+    assert!(status(502).should_fail_over());
+    assert!(!status(429).should_fail_over());
+    assert!(!status(401).should_fail_over());
+    assert!(!ProviderError::Cancelled.should_fail_over());
+  }
+
+  #[test]
+  fn model_is_encoded_into_path_segment() {
+    assert_eq!(
+      fill_model("/models/{model}:generateContent", "gemini-1.5-flash"),
+      "/models/gemini-1.5-flash:generateContent"
+    );
+    assert_eq!(
+      fill_model("/deployments/{model}/chat/completions", "openai/gpt-4o mini"),
+      "/deployments/openai%2Fgpt-4o%20mini/chat/completions"
+    );
+    assert_eq!(fill_model("/chat/completions", "gpt-4o"), "/chat/completions");
+  }
+}
@@ -0,0 +1,103 @@
+//! The last response a provider sent, exactly as received, for debugging
+//! outputs that look wrong. Only kept while debug logging is on, only in
+//! memory, and cleared on quit.
+
+use crate::AppState;
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::State;
+use tracing::instrument;
+
+/// Longer bodies are cut; a debugging view doesn't need megabytes of JSON.
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+/// Headers whose values are never kept.
+const HIDDEN_HEADERS: &[&str] = &["set-cookie", "cookie", "authorization", "x-api-key"];
+
+const REDACTED: &str = "[REDACTED]";
+
+pub type Slot = Mutex<Option<RawResponse>>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawResponse {
+  provider: &'static str,
+  model: String,
+  received_at: String,
+  status: u16,
+  /// Response headers in the order received, minus cookies and credentials.
+  headers: Vec<(String, String)>,
+  body: String,
+  /// The body was longer than `MAX_BODY_BYTES` and was cut.
+  truncated: bool,
+}
+
+impl RawResponse {
+  /// Keep a response, with every occurrence of `api_key` redacted in case a
+  /// provider echoes it back.
+  pub fn new(
+    provider: &'static str,
+    model: &str,
+    status: u16,
+    headers: &HeaderMap,
+    body: &str,
+    api_key: &str,
+  ) -> Self {
+    let redact = |text: &str| {
+      if api_key.is_empty() {
+        text.to_string()
+      } else {
+        text.replace(api_key, REDACTED)
+      }
+    };
+    let headers = headers
+      .iter()
+      .filter(|(name, _)| !HIDDEN_HEADERS.contains(&name.as_str()))
+      .map(|(name, value)| {
+        (name.to_string(), redact(&String::from_utf8_lossy(value.as_bytes())))
+      })
+      .collect();
+
+    let mut cut = body.len().min(MAX_BODY_BYTES);
+    while !body.is_char_boundary(cut) {
+      cut -= 1;
+    }
+    Self {
+      provider,
+      model: model.to_string(),
+      received_at: chrono::Utc::now().to_rfc3339(),
+      status,
+      headers,
+      body: redact(&body[..cut]),
+      truncated: cut < body.len(),
+    }
+  }
+}
+
+/// Responses are only kept while debug logging is on, by `log_level` or
+/// `RUST_LOG`, so normal use never holds provider output beyond the run.
+pub fn enabled() -> bool {
+  tracing::enabled!(tracing::Level::DEBUG)
+}
+
+/// The last provider response, or `None` if debug logging was off or
+/// nothing ran since launch.
+#[tauri::command]
+#[instrument(skip_all, err)]
+pub fn get_last_raw_response(state: State<'_, AppState>) -> Result<Option<RawResponse>, String> {
+  Ok(
+    state
+      .last_raw_response
+      .lock()
+      .map_err(|_| "Failed to lock raw response state".to_string())?
+      .clone(),
+  )
+}
+
+/// Forget the kept response, on quit.
+pub fn clear(state: &AppState) {
+  if let Ok(mut last) = state.last_raw_response.lock() {
+    last.take();
+  }
+}
@@ -0,0 +1,36 @@
+//! Masks sensitive text, such as emails or API keys, before it reaches the
+//! execution log.
+
+use regex::Regex;
+use tracing::warn;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Checks run by `save_setup`.
+pub fn validate(patterns: &[String]) -> Result<(), String> {
+  for pattern in patterns {
+    if pattern.is_empty() {
+      return Err("Redaction patterns can't be empty".to_string());
+    }
+    Regex::new(pattern)
+      .map_err(|error| format!("Invalid redaction pattern {pattern:?}: {error}"))?;
+  }
+  Ok(())
+}
+
+/// `text` with every match of `patterns` replaced by `[REDACTED]`. A pattern
+/// that doesn't compile (setup edited by hand) redacts the whole text rather
+/// than letting it through.
+pub fn redact(text: &str, patterns: &[String]) -> String {
+  let mut redacted = text.to_string();
+  for pattern in patterns {
+    match Regex::new(pattern) {
+      Ok(regex) => redacted = regex.replace_all(&redacted, REDACTED).into_owned(),
+      Err(error) => {
+        warn!(%pattern, %error, "invalid redaction pattern, redacting everything");
+        return REDACTED.to_string();
+      }
+    }
+  }
+  redacted
+}
@@ -0,0 +1,91 @@
+//! Factory reset: forget everything the app stored, for handing a machine
+//! over or testing from a clean slate.
+
+use crate::{
+  archive, autosave, cache, corrupt_logs_path, logs_file_path, profile, telemetry, tray,
+  unregister_all_shortcuts, usage, AppState, Preferences,
+};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, instrument};
+
+/// Delete setup (every profile's), the execution log and its archives, and
+/// the stored API keys, unregister all shortcuts, and return the in-memory
+/// state to what a first launch has. Does nothing unless `confirm` is true.
+/// Refused while a run is in flight, since it would log itself again on
+/// finishing. The UI returns to onboarding on `factory-reset`.
+#[tauri::command]
+#[instrument(skip(handle, state), err)]
+pub fn factory_reset(
+  handle: AppHandle,
+  state: State<'_, AppState>,
+  confirm: bool,
+) -> Result<(), String> {
+  if !confirm {
+    return Err("Factory reset needs confirm set to true".to_string());
+  }
+  if state.runs_in_flight.load(Ordering::SeqCst) > 0 {
+    return Err("Wait for running actions to finish before resetting".to_string());
+  }
+
+  unregister_all_shortcuts(handle.clone(), state.clone())?;
+  autosave::discard(&state);
+  usage::discard(&state);
+  telemetry::discard(&state);
+
+  {
+    let mut logs = state.logs.lock().map_err(|_| "Failed to lock log state".to_string())?;
+    let path = logs_file_path(&handle)?;
+    remove_file(&path, "the execution log")?;
+    remove_file(&corrupt_logs_path(&path), "the execution log backup")?;
+    let archive = archive::archive_dir(&handle)?;
+    if archive.exists() {
+      fs::remove_dir_all(&archive)
+        .map_err(|error| format!("Failed to delete the log archive: {error}"))?;
+    }
+    logs.clear();
+  }
+  profile::remove_all(&handle)?;
+
+  *state
+    .preferences
+    .lock()
+    .map_err(|_| "Failed to lock preferences state".to_string())? = Preferences::default();
+  state
+    .pending_pastes
+    .lock()
+    .map_err(|_| "Failed to lock pending paste state".to_string())?
+    .clear();
+  *state
+    .last_output
+    .lock()
+    .map_err(|_| "Failed to lock output state".to_string())? = None;
+  *state
+    .response_cache
+    .lock()
+    .map_err(|_| "Failed to lock response cache state".to_string())? =
+    cache::ResponseCache::default();
+  *state
+    .active_file
+    .lock()
+    .map_err(|_| "Failed to lock active file state".to_string())? = None;
+  *state
+    .cursor_context
+    .lock()
+    .map_err(|_| "Failed to lock cursor context state".to_string())? = None;
+
+  tray::refresh(&handle);
+  info!("factory reset");
+  let _ = handle.emit_all("factory-reset", ());
+  Ok(())
+}
+
+fn remove_file(path: &Path, what: &str) -> Result<(), String> {
+  match fs::remove_file(path) {
+    Ok(()) => Ok(()),
+    Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+    Err(error) => Err(format!("Failed to delete {what}: {error}")),
+  }
+}
@@ -0,0 +1,121 @@
+//! What the app holds in memory, for troubleshooting reports of high memory
+//! use: whether the capped log and the buffers around it account for it.
+
+use crate::AppState;
+use serde::Serialize;
+use tauri::State;
+use tracing::instrument;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceStats {
+  log_entries: usize,
+  /// Size of the in-memory log as JSON, close to what it occupies.
+  log_bytes: usize,
+  /// The main, picker-override and log shortcuts plus per-action ones.
+  registered_shortcuts: usize,
+  response_cache_entries: usize,
+  /// Outputs held by the response cache.
+  response_cache_bytes: usize,
+  /// What the last capture left behind: the file name guessed from the
+  /// window title, the text around the selection and the last output.
+  capture_bytes: usize,
+  pending_pastes: usize,
+  runs_in_flight: usize,
+  /// Physical memory in use by the process; `None` where it can't be read.
+  working_set_bytes: Option<u64>,
+  peak_working_set_bytes: Option<u64>,
+}
+
+/// Sizes of the app's in-memory state. Byte counts are approximate.
+#[tauri::command]
+#[instrument(skip_all, err)]
+pub fn get_resource_stats(state: State<'_, AppState>) -> Result<ResourceStats, String> {
+  let (log_entries, log_bytes) = {
+    let logs = state.logs.lock().map_err(|_| "Failed to lock log state".to_string())?;
+    (logs.len(), serde_json::to_vec(&*logs).map_or(0, |json| json.len()))
+  };
+
+  let shortcut_lock = |_| "Failed to lock shortcut state".to_string();
+  let mut registered_shortcuts = state
+    .action_shortcuts
+    .lock()
+    .map_err(|_| "Failed to lock action shortcut state".to_string())?
+    .len();
+  for shortcut in [&state.active_shortcut, &state.picker_override_shortcut, &state.logs_shortcut] {
+    registered_shortcuts += usize::from(shortcut.lock().map_err(shortcut_lock)?.is_some());
+  }
+
+  let (response_cache_entries, response_cache_bytes) = {
+    let cache = state
+      .response_cache
+      .lock()
+      .map_err(|_| "Failed to lock response cache state".to_string())?;
+    (cache.len(), cache.output_bytes())
+  };
+
+  let active_file = state
+    .active_file
+    .lock()
+    .map_err(|_| "Failed to lock active file state".to_string())?
+    .as_ref()
+    .map_or(0, String::len);
+  let cursor_context = state
+    .cursor_context
+    .lock()
+    .map_err(|_| "Failed to lock cursor context state".to_string())?
+    .as_ref()
+    .map_or(0, |context| context.before.len() + context.after.len());
+  let last_output = state
+    .last_output
+    .lock()
+    .map_err(|_| "Failed to lock last output state".to_string())?
+    .as_ref()
+    .map_or(0, |output| output.text.len());
+
+  let pending_pastes = state
+    .pending_pastes
+    .lock()
+    .map_err(|_| "Failed to lock pending paste state".to_string())?
+    .len();
+  let memory = process_memory();
+
+  Ok(ResourceStats {
+    log_entries,
+    log_bytes,
+    registered_shortcuts,
+    response_cache_entries,
+    response_cache_bytes,
+    capture_bytes: active_file + cursor_context + last_output,
+    pending_pastes,
+    runs_in_flight: state.runs_in_flight.load(std::sync::atomic::Ordering::SeqCst),
+    working_set_bytes: memory.map(|(current, _)| current),
+    peak_working_set_bytes: memory.map(|(_, peak)| peak),
+  })
+}
+
+/// Current and peak working set of this process.
+#[cfg(windows)]
+fn process_memory() -> Option<(u64, u64)> {
+  use windows_sys::Win32::System::ProcessStatus::{
+    K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+  };
+  use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+  let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+  // SAFETY: the counters are a local of the size passed along with them,
+  // and the current process pseudo handle needs no closing.
+  unsafe {
+    let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+    counters.cb = size;
+    if K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) == 0 {
+      return None;
+    }
+    Some((counters.WorkingSetSize as u64, counters.PeakWorkingSetSize as u64))
+  }
+}
+
+#[cfg(not(windows))]
+fn process_memory() -> Option<(u64, u64)> {
+  None
+}
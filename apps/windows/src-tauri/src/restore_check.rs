@@ -0,0 +1,90 @@
+//! Self-test of the clipboard restore around a capture, for proving on a
+//! user's machine that ShortcutAI puts their clipboard back as it was.
+
+use crate::{
+  capture_selected_text, clipboard, clipboard_watch, current_preferences, Capture, InputOptions,
+};
+use arboard::Clipboard;
+use serde::Serialize;
+use tauri::AppHandle;
+use tracing::{info, instrument, warn};
+
+/// Clipboard contents the capture has to put back, chosen for the ways a
+/// restore has gone wrong: truncation, surrogate pairs and line endings.
+fn cases() -> Vec<(&'static str, String)> {
+  vec![
+    ("short", "ShortcutAI restore check".to_string()),
+    ("long", "The quick brown fox jumps over the lazy dog. ".repeat(2_000)),
+    ("emoji", "Thumbs up 👍🏽, family 👨‍👩‍👧, flag 🇯🇵, kanji 漢字".to_string()),
+    ("multiline", "First line\r\nSecond line\r\n\tIndented third line".to_string()),
+  ]
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreCase {
+  name: &'static str,
+  passed: bool,
+  /// What was on the clipboard before the capture.
+  expected: String,
+  /// What was on it afterwards, or `None` if it held no readable text.
+  observed: Option<String>,
+  /// Why the capture itself failed, if it did. The restore is still checked.
+  error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreCheck {
+  /// Whether every case passed.
+  passed: bool,
+  cases: Vec<RestoreCase>,
+}
+
+/// Put each test text on the clipboard, run a full capture as a shortcut
+/// press would, and check the text was restored exactly. The copy goes to
+/// whichever window has focus, normally ShortcutAI's own. The user's text
+/// clipboard is put back afterwards; other formats, like images, are not.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub async fn check_clipboard_restore(handle: AppHandle) -> Result<RestoreCheck, String> {
+  let preferences = current_preferences(&handle);
+
+  // Keystroke simulation blocks; keep it off the async runtime's workers.
+  let check = tauri::async_runtime::spawn_blocking(move || {
+    let _own_write = clipboard_watch::own_write();
+    let mut board =
+      Clipboard::new().map_err(|error| format!("Clipboard init failed: {error}"))?;
+    let original = board.get_text().ok();
+
+    let mut results = Vec::new();
+    for (name, expected) in cases() {
+      board
+        .set_text(&expected)
+        .map_err(|error| format!("Clipboard write failed: {error}"))?;
+      let error = match capture_selected_text(&preferences, &InputOptions::default()) {
+        Capture::Text(_) => None,
+        Capture::TimedOut => Some("The copy was not answered in time".to_string()),
+        Capture::Failed(error) => Some(error),
+      };
+      let observed = clipboard::read_text(&mut board).ok();
+      let passed = observed.as_ref() == Some(&expected);
+      if !passed {
+        warn!(case = name, "clipboard was not restored exactly");
+      }
+      results.push(RestoreCase { name, passed, expected, observed, error });
+    }
+
+    let _ = match original {
+      Some(original) => board.set_text(original),
+      None => board.clear(),
+    };
+    let passed = results.iter().all(|case| case.passed);
+    Ok::<_, String>(RestoreCheck { passed, cases: results })
+  })
+  .await
+  .map_err(|error| format!("Clipboard restore check failed: {error}"))??;
+
+  info!(passed = check.passed, "checked clipboard restore");
+  Ok(check)
+}
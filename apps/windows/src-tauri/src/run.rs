@@ -0,0 +1,956 @@
+//! Native action execution: resolve the action, call the provider, log the run.
+
+use crate::provider::{self, CompletionRequest, Provider};
+use crate::tools::{self, MAX_TOOL_ROUNDS};
+use crate::{
+  cache, classify, copy_to_clipboard, current_preferences, diff, file_run,
+  load_provider_api_key_secure, notify, raw_response, read_json, record_execution, redact,
+  setup_file_path, show_main_window, telemetry, usage, Action, AppState, ExecutionLogEntry,
+  OutputDestination, Preferences, ProviderSettings, SetupFile,
+};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, instrument, warn};
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionRunResult {
+  pub execution_id: String,
+  pub action_id: String,
+  pub output: String,
+  /// `None` for local transforms.
+  pub provider: Option<String>,
+  pub model_id: Option<String>,
+  pub duration_ms: f64,
+  /// The action wants its result copied rather than pasted.
+  pub copy_only: bool,
+  /// Where the action's results go. `run_action` has already delivered
+  /// clipboard and file results; pasting and displaying, and every result of
+  /// replays, are up to the caller.
+  pub output_destination: OutputDestination,
+  /// Served from the response cache.
+  pub cached: bool,
+}
+
+/// How long a run waits for `confirm_run` before it is called off.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Wait before resending to the same provider, times the retry number.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Payload of `confirm-required`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ConfirmRequired<'a> {
+  execution_id: &'a str,
+  action_id: &'a str,
+  action_name: &'a str,
+  message: &'a str,
+}
+
+/// Outputs longer than this are not inlined into `action-done`; they are
+/// streamed as `action-output-chunk` events and kept for `get_last_output`.
+const INLINE_OUTPUT_MAX_CHARS: usize = 8192;
+
+/// Payload of the `action-done` event, emitted for every native run.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ActionDone {
+  execution_id: String,
+  action_id: String,
+  success: bool,
+  /// The full output, or `None` if it failed or was delivered in chunks.
+  output: Option<String>,
+  output_length: usize,
+  output_chunked: bool,
+  error_message: Option<String>,
+}
+
+/// Payload of `action-chunk`: a piece of a streamed answer as it arrives.
+/// `request` numbers the run's provider requests from 1. A retried or
+/// failed-over request streams again under the next number, so whatever
+/// arrived for earlier ones is no longer part of the answer.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ActionChunk<'a> {
+  execution_id: &'a str,
+  request: usize,
+  text: &'a str,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ActionOutputChunk {
+  execution_id: String,
+  index: usize,
+  total: usize,
+  text: String,
+}
+
+/// The most recent successful output, kept so the UI can fetch it on demand.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LastOutput {
+  pub execution_id: String,
+  pub action_id: String,
+  pub text: String,
+}
+
+/// Split `text` into pieces of at most `max_chars` characters.
+fn chunk_chars(text: &str, max_chars: usize) -> Vec<String> {
+  let chars: Vec<char> = text.chars().collect();
+  chars.chunks(max_chars).map(|chunk| chunk.iter().collect()).collect()
+}
+
+/// Emit `action-done`, sending long outputs ahead of it in chunks.
+fn emit_action_done(
+  handle: &AppHandle,
+  execution_id: &str,
+  action_id: &str,
+  result: &Result<String, String>,
+) {
+  let output_length = result.as_ref().map_or(0, |output| output.chars().count());
+  let output_chunked = output_length > INLINE_OUTPUT_MAX_CHARS;
+
+  if let (true, Ok(output)) = (output_chunked, result) {
+    let chunks = chunk_chars(output, INLINE_OUTPUT_MAX_CHARS);
+    let total = chunks.len();
+    for (index, text) in chunks.into_iter().enumerate() {
+      let _ = handle.emit_all(
+        "action-output-chunk",
+        ActionOutputChunk {
+          execution_id: execution_id.to_string(),
+          index,
+          total,
+          text,
+        },
+      );
+    }
+  }
+
+  let _ = handle.emit_all(
+    "action-done",
+    ActionDone {
+      execution_id: execution_id.to_string(),
+      action_id: action_id.to_string(),
+      success: result.is_ok(),
+      output: result.as_ref().ok().filter(|_| !output_chunked).cloned(),
+      output_length,
+      output_chunked,
+      error_message: result.as_ref().err().cloned(),
+    },
+  );
+}
+
+/// One provider to try, with everything needed to call it.
+struct Candidate {
+  provider: Provider,
+  model: String,
+  api_key: String,
+  settings: ProviderSettings,
+}
+
+fn settings_for(setup: &SetupFile, provider: Provider) -> ProviderSettings {
+  setup.provider_settings.get(provider.name()).cloned().unwrap_or_default()
+}
+
+/// The key stored for `provider`, or an empty one if it takes none.
+fn api_key_for(setup: &SetupFile, provider: Provider) -> Result<Option<String>, String> {
+  let stored = load_provider_api_key_secure(setup, provider.name())?.filter(|key| !key.is_empty());
+  Ok(stored.or_else(|| (!provider.needs_api_key()).then(String::new)))
+}
+
+/// The primary provider followed, if the action opts in, by the fallback
+/// chain. Fallback providers without a stored key are skipped.
+fn resolve_candidates(setup: &SetupFile, action: &Action) -> Result<Vec<Candidate>, String> {
+  let provider = Provider::parse(&setup.provider)?;
+  let api_key =
+    api_key_for(setup, provider)?.ok_or_else(|| "API key is not configured".to_string())?;
+
+  let mut candidates = vec![Candidate {
+    provider,
+    model: provider.default_model().to_string(),
+    api_key,
+    settings: settings_for(setup, provider),
+  }];
+
+  if !action.use_fallback {
+    return Ok(candidates);
+  }
+
+  for config in &setup.fallback_providers {
+    let provider = match Provider::parse(&config.provider) {
+      Ok(provider) => provider,
+      Err(error) => {
+        warn!(%error, "skipping fallback provider");
+        continue;
+      }
+    };
+    match api_key_for(setup, provider)? {
+      Some(api_key) => candidates.push(Candidate {
+        provider,
+        model: config
+          .model
+          .clone()
+          .unwrap_or_else(|| provider.default_model().to_string()),
+        api_key,
+        settings: settings_for(setup, provider),
+      }),
+      None => warn!(provider = provider.name(), "skipping fallback provider without API key"),
+    }
+  }
+
+  Ok(candidates)
+}
+
+/// Warm up the connection to the configured provider in the background.
+/// Being offline only costs the failed attempt, which is logged and ignored.
+pub fn spawn_warm_up(handle: &AppHandle) {
+  let setup = setup_file_path(handle).and_then(|path| read_json::<SetupFile>(&path));
+  let Some(provider) = setup
+    .ok()
+    .flatten()
+    .and_then(|setup| Provider::parse(&setup.provider).ok())
+  else {
+    return;
+  };
+
+  let client = handle.state::<AppState>().http_client.clone();
+  tauri::async_runtime::spawn(async move {
+    match provider::warm_up(&client, provider).await {
+      Ok(()) => info!(provider = provider.name(), "provider connection warmed up"),
+      Err(error) => info!(provider = provider.name(), %error, "skipped provider warm-up"),
+    }
+  });
+}
+
+/// The configured default action, falling back to the first action.
+pub fn default_action_id(handle: &AppHandle) -> Result<String, String> {
+  let setup = read_json::<SetupFile>(&setup_file_path(handle)?)?
+    .ok_or_else(|| "Setup has not been completed".to_string())?;
+
+  setup
+    .default_action_id
+    .filter(|id| setup.actions.iter().any(|action| &action.id == id))
+    .or_else(|| setup.actions.first().map(|action| action.id.clone()))
+    .ok_or_else(|| "No actions are configured".to_string())
+}
+
+/// Run a saved action against `input` with the configured provider, then
+/// deliver the result to the action's clipboard or file destination. A
+/// failed delivery is returned as an error; the run itself is still logged
+/// and its output kept for `get_last_output`.
+#[instrument(skip(handle, input), fields(chars = input.len()), err)]
+pub async fn run_action(
+  handle: &AppHandle,
+  action_id: &str,
+  input: String,
+) -> Result<ActionRunResult, String> {
+  let (result, output_path) = run_saved(handle, action_id, input).await?;
+  deliver(&result, output_path.as_deref())?;
+  Ok(result)
+}
+
+/// `run_action` without the delivery, for callers that put the output
+/// somewhere themselves.
+pub async fn run_action_undelivered(
+  handle: &AppHandle,
+  action_id: &str,
+  input: String,
+) -> Result<ActionRunResult, String> {
+  run_saved(handle, action_id, input).await.map(|(result, _)| result)
+}
+
+/// Run a saved action, returning its result and `output_path`.
+async fn run_saved(
+  handle: &AppHandle,
+  action_id: &str,
+  input: String,
+) -> Result<(ActionRunResult, Option<String>), String> {
+  let setup = read_json::<SetupFile>(&setup_file_path(handle)?)?
+    .ok_or_else(|| "Setup has not been completed".to_string())?;
+  let action = setup
+    .actions
+    .iter()
+    .find(|action| action.id == action_id)
+    .cloned()
+    .ok_or_else(|| format!("Unknown action: {action_id}"))?;
+  let output_path = action.output_path.clone();
+  let result = run(handle, &setup, action, input).await?;
+  Ok((result, output_path))
+}
+
+/// Put a result where its destination needs no window or foreground app.
+fn deliver(result: &ActionRunResult, output_path: Option<&str>) -> Result<(), String> {
+  match result.output_destination {
+    OutputDestination::Clipboard => copy_to_clipboard(result.output.clone()),
+    OutputDestination::File => {
+      let path = output_path.map(str::trim).unwrap_or_default();
+      file_run::replace_file(Path::new(path), result.output.as_bytes())?;
+      info!(path, "wrote output to file");
+      Ok(())
+    }
+    OutputDestination::Paste | OutputDestination::Display => Ok(()),
+  }
+}
+
+/// Run a logged execution again on its logged input. If the action has been
+/// deleted since, the prompt it had at the time is used as is.
+#[instrument(skip(handle), err)]
+pub async fn replay(handle: &AppHandle, log_id: &str) -> Result<ActionRunResult, String> {
+  let entry = handle
+    .state::<AppState>()
+    .logs
+    .lock()
+    .map_err(|_| "Failed to lock log state".to_string())?
+    .iter()
+    .find(|entry| entry.id == log_id)
+    .cloned()
+    .ok_or_else(|| format!("Unknown log entry: {log_id}"))?;
+  let input = entry
+    .input
+    .filter(|input| !input.is_empty())
+    .ok_or_else(|| "The input of this run wasn't logged, so it can't be replayed".to_string())?;
+
+  let setup = read_json::<SetupFile>(&setup_file_path(handle)?)?
+    .ok_or_else(|| "Setup has not been completed".to_string())?;
+  let action = match setup.actions.iter().find(|action| action.id == entry.action_id) {
+    Some(action) => action.clone(),
+    None => Action {
+      id: entry.action_id,
+      name: entry.action_name,
+      prompt: entry.prompt,
+      ..Action::default()
+    },
+  };
+  run(handle, &setup, action, input).await
+}
+
+/// Id and name ad-hoc runs are logged under.
+pub const ADHOC_ACTION_ID: &str = "adhoc";
+const ADHOC_ACTION_NAME: &str = "(ad-hoc)";
+
+/// Run a one-off `prompt` against `input`, as an action that is never
+/// stored. It is logged as "(ad-hoc)" and doesn't count towards usage.
+#[instrument(skip_all, fields(chars = input.len()), err)]
+pub async fn run_adhoc(
+  handle: &AppHandle,
+  prompt: String,
+  input: String,
+) -> Result<ActionRunResult, String> {
+  if prompt.trim().is_empty() {
+    return Err("The prompt can't be empty".to_string());
+  }
+  if input.trim().is_empty() {
+    return Err("There is no text to run the prompt on".to_string());
+  }
+
+  let setup = read_json::<SetupFile>(&setup_file_path(handle)?)?
+    .ok_or_else(|| "Setup has not been completed".to_string())?;
+  let action = Action {
+    id: ADHOC_ACTION_ID.to_string(),
+    name: ADHOC_ACTION_NAME.to_string(),
+    prompt,
+    ..Action::default()
+  };
+  run(handle, &setup, action, input).await
+}
+
+/// The most providers `compare_providers` calls at once.
+const MAX_COMPARED: usize = 8;
+
+/// One provider's answer in `compare_providers`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderComparison {
+  /// As listed in the request.
+  provider: String,
+  model_id: Option<String>,
+  output: Option<String>,
+  error: Option<String>,
+  duration_ms: f64,
+}
+
+/// Run `action_id` on `input` against each of `providers` at once, to
+/// compare their outputs before choosing one. Every provider needs a stored
+/// key; one that has none, or fails, gets an error of its own while the rest
+/// still answer. Comparisons aren't logged, cached or counted as usage, and
+/// tools aren't offered.
+#[instrument(skip(handle, input), fields(chars = input.len()), err)]
+pub async fn compare_providers(
+  handle: &AppHandle,
+  action_id: &str,
+  input: String,
+  providers: Vec<String>,
+) -> Result<Vec<ProviderComparison>, String> {
+  if providers.is_empty() {
+    return Err("Choose at least one provider to compare".to_string());
+  }
+  if providers.len() > MAX_COMPARED {
+    return Err(format!("At most {MAX_COMPARED} providers can be compared at once"));
+  }
+  if input.trim().is_empty() {
+    return Err("There is no text to run the action on".to_string());
+  }
+
+  let setup = read_json::<SetupFile>(&setup_file_path(handle)?)?
+    .ok_or_else(|| "Setup has not been completed".to_string())?;
+  let action = setup
+    .actions
+    .iter()
+    .find(|action| action.id == action_id)
+    .cloned()
+    .ok_or_else(|| format!("Unknown action: {action_id}"))?;
+  let input = match &action.local_transform {
+    None => input,
+    Some(transform) if action.send_transformed => transform.apply(&input)?,
+    Some(_) => return Err("This action runs locally, so no provider is involved".to_string()),
+  };
+
+  let state = handle.state::<AppState>();
+  let _in_flight = InFlight::enter(&state.runs_in_flight);
+  let execution_id = uuid::Uuid::new_v4().to_string();
+  let cancellable = Cancellable::register(&state, &execution_id, &action.id)?;
+  let preferences = current_preferences(handle);
+  let parameters = setup.parameters_for(&action)?;
+  let system_prompt = action.system_prompt(None, None);
+  let timeout = Duration::from_millis(action.timeout_ms.unwrap_or(preferences.request_timeout_ms));
+  let user_id = setup.request_user_id().map(str::to_string);
+
+  let tasks: Vec<_> = providers
+    .into_iter()
+    .map(|name| {
+      let candidate = candidate_for(&setup, &name);
+      let client = state.http_client.clone();
+      let action = action.clone();
+      let (system_prompt, input, user_id) = (system_prompt.clone(), input.clone(), user_id.clone());
+      let token = cancellable.token.clone();
+      let max_body_bytes = preferences.max_request_bytes;
+      tauri::async_runtime::spawn(async move {
+        let candidate = match candidate {
+          Ok(candidate) => candidate,
+          Err(error) => {
+            return ProviderComparison {
+              provider: name,
+              model_id: None,
+              output: None,
+              error: Some(error),
+              duration_ms: 0.0,
+            };
+          }
+        };
+        let started = Instant::now();
+        let headers = candidate.settings.request_headers();
+        let response = provider::complete(
+          &client,
+          &CompletionRequest {
+            provider: candidate.provider,
+            api_key: &candidate.api_key,
+            model: &candidate.model,
+            system_prompt: &system_prompt,
+            input: &input,
+            custom_headers: &headers,
+            reasoning_effort: action.reasoning_effort,
+            user_id: user_id.as_deref(),
+            metadata: &action.metadata,
+            seed: action.seed,
+            stop: &action.stop,
+            parameters,
+            max_body_bytes,
+            timeout,
+            cancel: &token,
+            tools: &[],
+            tool_rounds: &[],
+            openai_endpoint: candidate.settings.openai_endpoint,
+            raw_response: None,
+            stream: candidate.settings.stream,
+            on_text: None,
+          },
+        )
+        .await;
+        let (output, error) = match response {
+          Ok(completion) => (Some(completion.text), None),
+          Err(error) => {
+            warn!(provider = candidate.provider.name(), %error, "compared provider failed");
+            (None, Some(error.to_string()))
+          }
+        };
+        ProviderComparison {
+          provider: name,
+          model_id: Some(candidate.model),
+          output,
+          error,
+          duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        }
+      })
+    })
+    .collect();
+
+  let mut comparisons = Vec::with_capacity(tasks.len());
+  for task in tasks {
+    comparisons.push(task.await.map_err(|error| format!("Comparison failed: {error}"))?);
+  }
+  info!(%execution_id, providers = comparisons.len(), "compared providers");
+  Ok(comparisons)
+}
+
+/// The provider `name` as `compare_providers` calls it: with the model its
+/// fallback entry names, if it has one, and its stored key, which is required
+/// where the provider takes one.
+fn candidate_for(setup: &SetupFile, name: &str) -> Result<Candidate, String> {
+  let provider = Provider::parse(name)?;
+  let api_key = api_key_for(setup, provider)?
+    .ok_or_else(|| format!("No API key is stored for {}", provider.name()))?;
+  let model = setup
+    .fallback_providers
+    .iter()
+    .find(|config| config.provider == provider.name())
+    .and_then(|config| config.model.clone())
+    .unwrap_or_else(|| provider.default_model().to_string());
+  Ok(Candidate { provider, model, api_key, settings: settings_for(setup, provider) })
+}
+
+/// How a run ended, with everything that gets logged about it.
+struct Finished {
+  execution_id: String,
+  /// `None` for local transforms.
+  provider: Option<Provider>,
+  model: Option<String>,
+  duration_ms: f64,
+  result: Result<String, String>,
+  reasoning_tokens: Option<u32>,
+  system_fingerprint: Option<String>,
+  cached: bool,
+  timed_out: bool,
+  truncated_retry: bool,
+  cancelled: bool,
+  /// Coarse kind of failure, counted by telemetry.
+  error_category: Option<&'static str>,
+}
+
+/// Counts a run in `AppState::runs_in_flight` until dropped, however it ends.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+  fn enter(counter: &'a AtomicUsize) -> Self {
+    counter.fetch_add(1, Ordering::SeqCst);
+    Self(counter)
+  }
+}
+
+impl Drop for InFlight<'_> {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+/// A run's entry in `AppState::cancellations`, removed when dropped.
+struct Cancellable<'a> {
+  state: &'a AppState,
+  execution_id: String,
+  token: CancellationToken,
+}
+
+impl<'a> Cancellable<'a> {
+  fn register(state: &'a AppState, execution_id: &str, action_id: &str) -> Result<Self, String> {
+    let token = CancellationToken::new();
+    state
+      .cancellations
+      .lock()
+      .map_err(|_| "Failed to lock cancellation state".to_string())?
+      .insert(execution_id.to_string(), (action_id.to_string(), token.clone()));
+    Ok(Self { state, execution_id: execution_id.to_string(), token })
+  }
+}
+
+impl Drop for Cancellable<'_> {
+  fn drop(&mut self) {
+    if let Ok(mut cancellations) = self.state.cancellations.lock() {
+      cancellations.remove(&self.execution_id);
+    }
+  }
+}
+
+/// Abort the provider requests of every running run of `action_id`,
+/// returning how many there were. Each still finishes, failed and logged as
+/// cancelled.
+pub fn cancel(state: &AppState, action_id: &str) -> Result<usize, String> {
+  let cancellations = state
+    .cancellations
+    .lock()
+    .map_err(|_| "Failed to lock cancellation state".to_string())?;
+  let mut cancelled = 0;
+  for (running_action_id, token) in cancellations.values() {
+    if running_action_id == action_id {
+      token.cancel();
+      cancelled += 1;
+    }
+  }
+  Ok(cancelled)
+}
+
+/// Run `action`, which doesn't have to be one of `setup`'s, against `input`.
+async fn run(
+  handle: &AppHandle,
+  setup: &SetupFile,
+  action: Action,
+  input: String,
+) -> Result<ActionRunResult, String> {
+  let state = handle.state::<AppState>();
+  let _in_flight = InFlight::enter(&state.runs_in_flight);
+  let execution_id = uuid::Uuid::new_v4().to_string();
+  let cancellable = Cancellable::register(&state, &execution_id, &action.id)?;
+  let confirm_message =
+    action.confirm_message.as_deref().filter(|message| !message.trim().is_empty());
+  if let Some(message) = confirm_message {
+    wait_for_confirmation(handle, &execution_id, &action, message, &cancellable.token).await?;
+  }
+  let started = Instant::now();
+  let preferences = current_preferences(handle);
+
+  // A local transform either is the whole action or prepares the input.
+  let mut request_input = match &action.local_transform {
+    None => input.clone(),
+    Some(transform) => match transform.apply(&input) {
+      Ok(transformed) if action.send_transformed => transformed,
+      result => {
+        info!(%execution_id, transform = transform.name(), "ran local transform");
+        let error_category = result.is_err().then_some("transform");
+        let finished = Finished {
+          execution_id,
+          provider: None,
+          model: None,
+          duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+          result,
+          reasoning_tokens: None,
+          system_fingerprint: None,
+          cached: false,
+          timed_out: false,
+          truncated_retry: false,
+          cancelled: false,
+          error_category,
+        };
+        return finish(handle, &preferences, action, &input, finished);
+      }
+    },
+  };
+
+  let candidates = resolve_candidates(setup, &action)?;
+  let parameters = setup.parameters_for(&action)?;
+  let active_file = handle
+    .state::<AppState>()
+    .active_file
+    .lock()
+    .map_err(|_| "Failed to lock active file state".to_string())?
+    .clone();
+  let cursor_context = handle
+    .state::<AppState>()
+    .cursor_context
+    .lock()
+    .map_err(|_| "Failed to lock cursor context state".to_string())?
+    .clone();
+  let system_prompt = action.system_prompt(active_file.as_deref(), cursor_context.as_ref());
+  let tool_definitions = tools::definitions(&action.tools);
+  let client = handle.state::<AppState>().http_client.clone();
+
+  let cache_key = action
+    .cache_responses
+    .then(|| cache::Key::new(&action.id, &candidates[0].model, &system_prompt, &request_input));
+  let cached_output = cache_key.as_ref().and_then(|key| {
+    let ttl = Duration::from_secs(preferences.response_cache_ttl_secs);
+    handle.state::<AppState>().response_cache.lock().ok()?.get(key, ttl)
+  });
+  let cached = cached_output.is_some();
+
+  // Try each candidate in turn; only outages move on to the next one.
+  let mut served_by = &candidates[0];
+  let mut result = Err(String::new());
+  let mut reasoning_tokens = None;
+  let mut system_fingerprint = None;
+  let mut timed_out = false;
+  let mut truncated_retry = false;
+  let mut cancelled = false;
+  let mut error_category = None;
+  let mut used_tools = false;
+  let mut requests = 0;
+  let timeout = Duration::from_millis(action.timeout_ms.unwrap_or(preferences.request_timeout_ms));
+  if let Some(output) = cached_output {
+    result = Ok(output);
+  } else {
+    for (attempt, candidate) in candidates.iter().enumerate() {
+      served_by = candidate;
+      let headers = candidate.settings.request_headers();
+      // Tool call ids are the provider's own, so a fallback starts over.
+      let mut tool_rounds = Vec::new();
+      let mut retries = 0;
+      let response = loop {
+        requests += 1;
+        let request = requests;
+        let on_text = |text: &str| {
+          let chunk = ActionChunk { execution_id: &execution_id, request, text };
+          let _ = handle.emit_all("action-chunk", chunk);
+        };
+        let response = provider::complete(
+          &client,
+          &CompletionRequest {
+            provider: candidate.provider,
+            api_key: &candidate.api_key,
+            model: &candidate.model,
+            system_prompt: &system_prompt,
+            input: &request_input,
+            custom_headers: &headers,
+            reasoning_effort: action.reasoning_effort,
+            user_id: setup.request_user_id(),
+            metadata: &action.metadata,
+            seed: action.seed,
+            stop: &action.stop,
+            parameters,
+            max_body_bytes: preferences.max_request_bytes,
+            timeout,
+            cancel: &cancellable.token,
+            tools: &tool_definitions,
+            tool_rounds: &tool_rounds,
+            openai_endpoint: candidate.settings.openai_endpoint,
+            raw_response: raw_response::enabled().then_some(&state.last_raw_response),
+            stream: candidate.settings.stream,
+            on_text: Some(&on_text),
+          },
+        )
+        .await;
+        match response {
+          // Only one truncated retry per run, whichever provider hits it.
+          Err(error)
+            if action.truncate_on_context_overflow
+              && !truncated_retry
+              && error.is_context_length() =>
+          {
+            let kept = truncate(&request_input, error.context_fit_ratio());
+            warn!(
+              provider = candidate.provider.name(),
+              chars = request_input.chars().count(),
+              kept = kept.chars().count(),
+              "input exceeds the model context, retrying truncated"
+            );
+            request_input = kept;
+            truncated_retry = true;
+          }
+          Err(error)
+            if retries < preferences.request_retries
+              && error.should_retry(preferences.retry_policy) =>
+          {
+            retries += 1;
+            let provider = candidate.provider.name();
+            warn!(provider, %error, retries, "retrying provider request");
+            let backoff = RETRY_BACKOFF * retries;
+            if cancellable.token.run_until_cancelled(tokio::time::sleep(backoff)).await.is_none() {
+              break Err(provider::ProviderError::Cancelled);
+            }
+          }
+          Ok(completion)
+            if !completion.tool_calls.is_empty() && tool_rounds.len() < MAX_TOOL_ROUNDS =>
+          {
+            tool_rounds.push(tools::answer(&action.tools, completion.tool_calls));
+            used_tools = true;
+          }
+          response => break response,
+        }
+      };
+      match response {
+        Ok(completion) if !completion.tool_calls.is_empty() => {
+          warn!(provider = candidate.provider.name(), "model kept calling tools");
+          error_category = Some("tools");
+          result = Err(format!(
+            "The model asked for more than {MAX_TOOL_ROUNDS} rounds of tool calls without answering"
+          ));
+          break;
+        }
+        Ok(completion) => {
+          reasoning_tokens = completion.reasoning_tokens;
+          system_fingerprint = completion.system_fingerprint;
+          timed_out = false;
+          error_category = None;
+          result = Ok(completion.text);
+          break;
+        }
+        Err(error) => {
+          timed_out = matches!(error, provider::ProviderError::Timeout(_));
+          cancelled = matches!(error, provider::ProviderError::Cancelled);
+          error_category = Some(error.category());
+          let retry = error.should_fail_over() && attempt + 1 < candidates.len();
+          warn!(provider = candidate.provider.name(), %error, retry, "provider request failed");
+          result = Err(error.to_string());
+          if !retry {
+            break;
+          }
+        }
+      }
+    }
+  }
+
+  // Lookups use the primary provider's model, so fallback answers aren't
+  // stored, and the whole input, so answers on a truncated copy aren't either.
+  // Tool outputs such as the time change between runs.
+  let from_primary = std::ptr::eq(served_by, &candidates[0]);
+  let storable = !cached && from_primary && !truncated_retry && !used_tools;
+  if let (Some(key), Ok(output), true) = (cache_key, &result, storable) {
+    if let Ok(mut cache) = handle.state::<AppState>().response_cache.lock() {
+      cache.insert(key, output.clone(), preferences.response_cache_size);
+    }
+  }
+
+  let finished = Finished {
+    execution_id,
+    provider: Some(served_by.provider),
+    model: Some(served_by.model.clone()),
+    duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+    result,
+    reasoning_tokens,
+    system_fingerprint,
+    cached,
+    timed_out,
+    truncated_retry,
+    cancelled,
+    error_category,
+  };
+  finish(handle, &preferences, action, &input, finished)
+}
+
+/// Show the window, emit `confirm-required` and wait for `confirm_run`.
+/// Declining, cancelling or not answering within `CONFIRM_TIMEOUT` ends the
+/// run before anything is sent or logged.
+async fn wait_for_confirmation(
+  handle: &AppHandle,
+  execution_id: &str,
+  action: &Action,
+  message: &str,
+  cancel: &CancellationToken,
+) -> Result<(), String> {
+  let state = handle.state::<AppState>();
+  let (sender, receiver) = tokio::sync::oneshot::channel();
+  state
+    .pending_confirmations
+    .lock()
+    .map_err(|_| "Failed to lock confirmation state".to_string())?
+    .insert(execution_id.to_string(), sender);
+
+  show_main_window(handle);
+  let _ = handle.emit_all(
+    "confirm-required",
+    ConfirmRequired { execution_id, action_id: &action.id, action_name: &action.name, message },
+  );
+  let answer = cancel.run_until_cancelled(tokio::time::timeout(CONFIRM_TIMEOUT, receiver)).await;
+  if let Ok(mut pending) = state.pending_confirmations.lock() {
+    pending.remove(execution_id);
+  }
+
+  match answer {
+    Some(Ok(Ok(true))) => Ok(()),
+    Some(Ok(_)) => {
+      info!(%execution_id, "run declined");
+      Err("The run was declined".to_string())
+    }
+    Some(Err(_)) => {
+      warn!(%execution_id, "run was not confirmed in time");
+      let _ = handle.emit_all("confirm-cancelled", execution_id);
+      Err("The run was not confirmed in time".to_string())
+    }
+    None => Err("The run was cancelled".to_string()),
+  }
+}
+
+/// The first `ratio` of `text`, cut at a character boundary.
+fn truncate(text: &str, ratio: f64) -> String {
+  let keep = (text.chars().count() as f64 * ratio) as usize;
+  text.chars().take(keep).collect()
+}
+
+/// Log a finished run, keep its output for `get_last_output`, and announce it.
+fn finish(
+  handle: &AppHandle,
+  preferences: &Preferences,
+  action: Action,
+  input: &str,
+  finished: Finished,
+) -> Result<ActionRunResult, String> {
+  let Finished { execution_id, provider, model, duration_ms, result, cached, .. } = finished;
+  let provider = provider.map(|provider| provider.name().to_string());
+
+  let entry = ExecutionLogEntry {
+    id: execution_id.clone(),
+    timestamp: chrono::Utc::now().to_rfc3339(),
+    action_id: action.id.clone(),
+    action_name: action.name.clone(),
+    prompt: redact::redact(&action.prompt, &preferences.log_redaction_patterns),
+    provider: provider.clone(),
+    model_id: model.clone(),
+    duration_ms,
+    input_length: input.chars().count() as u32,
+    output_length: result.as_ref().map_or(0, |output| output.chars().count() as u32),
+    success: result.is_ok(),
+    error_message: result.as_ref().err().cloned(),
+    reasoning_tokens: finished.reasoning_tokens,
+    system_fingerprint: finished.system_fingerprint,
+    cached,
+    input: preferences
+      .log_inputs
+      .then(|| redact::redact(input, &preferences.log_redaction_patterns)),
+    timed_out: finished.timed_out,
+    truncated_retry: finished.truncated_retry,
+    cancelled: finished.cancelled,
+    content: classify::classify(input),
+    diff: result
+      .as_ref()
+      .ok()
+      .filter(|_| action.log_diff)
+      .map(|output| diff::summary(input, output)),
+    paste_method: None,
+  };
+  if let Err(error) = record_execution(handle, entry) {
+    warn!(%error, "failed to record execution log");
+  }
+  if action.id != ADHOC_ACTION_ID {
+    usage::record(handle, &action.id, result.is_ok());
+  }
+  telemetry::record(
+    handle,
+    preferences,
+    provider.as_deref(),
+    if result.is_ok() { "success" } else { finished.error_category.unwrap_or("other") },
+  );
+
+  if let Ok(output) = &result {
+    if let Ok(mut last) = handle.state::<AppState>().last_output.lock() {
+      *last = Some(LastOutput {
+        execution_id: execution_id.clone(),
+        action_id: action.id.clone(),
+        text: output.clone(),
+      });
+    }
+  }
+  emit_action_done(handle, &execution_id, &action.id, &result);
+  notify::action_completed(
+    handle,
+    &action.name,
+    &result.as_ref().map(|_| duration_ms).map_err(Clone::clone),
+  );
+
+  let output = result?;
+  info!(%execution_id, provider = ?provider, duration_ms, cached, "action completed");
+
+  let output_destination = action.destination();
+  Ok(ActionRunResult {
+    execution_id,
+    action_id: action.id,
+    output,
+    provider,
+    model_id: model,
+    duration_ms,
+    copy_only: output_destination == OutputDestination::Clipboard,
+    output_destination,
+    cached,
+  })
+}
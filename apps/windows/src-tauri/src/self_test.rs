@@ -0,0 +1,320 @@
+//! End-to-end check of the capture and paste pipeline, for onboarding. It
+//! copies from and pastes into a scratch edit control of its own, placed off
+//! screen, so it needs no provider and doesn't touch the user's apps.
+
+use crate::{clipboard, clipboard_watch, current_preferences, input, Preferences, CAPTURE_SENTINEL};
+use arboard::Clipboard;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tracing::{info, instrument};
+
+/// Give up on a copy or paste after this long.
+const STEP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Placed in the scratch buffer and copied out of it.
+const CAPTURE_VALUE: &str = "ShortcutAI self-test: capture ✓ é 漢字 🧪";
+/// Put on the clipboard and pasted into the scratch buffer.
+const PASTE_VALUE: &str = "ShortcutAI self-test: paste ✓ ü かな 🚀";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Check {
+  passed: bool,
+  /// How long the step took, when it ran to the end.
+  duration_ms: Option<f64>,
+  error: Option<String>,
+}
+
+impl Check {
+  fn timed(started: Instant, result: Result<(), String>) -> Self {
+    let duration_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+    match result {
+      Ok(()) => Self { passed: true, duration_ms, error: None },
+      Err(error) => Self { passed: false, duration_ms, error: Some(error) },
+    }
+  }
+
+  fn failed(error: String) -> Self {
+    Self { passed: false, duration_ms: None, error: Some(error) }
+  }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+  /// Whether every check passed.
+  passed: bool,
+  clipboard_write: Check,
+  clipboard_read: Check,
+  /// Keyboard simulation can start and reach the input desktop.
+  key_simulation: Check,
+  /// The copy shortcut until the clipboard held the scratch buffer's text.
+  capture: Check,
+  /// The paste shortcut until the scratch buffer held the clipboard's text.
+  paste: Check,
+}
+
+/// Check clipboard access, keyboard simulation, and a timed capture and
+/// paste round trip against a scratch buffer, with the configured copy/paste
+/// modifier and key mode. The user's text clipboard is restored afterwards,
+/// and focus goes back to ShortcutAI's window.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub async fn run_self_test(handle: AppHandle) -> Result<SelfTestReport, String> {
+  let preferences = current_preferences(&handle);
+
+  // Keystroke simulation blocks; keep it off the async runtime's workers.
+  let report = tauri::async_runtime::spawn_blocking(move || run(&preferences))
+    .await
+    .map_err(|error| format!("Self-test failed: {error}"))??;
+
+  info!(
+    passed = report.passed,
+    capture_ms = ?report.capture.duration_ms,
+    paste_ms = ?report.paste.duration_ms,
+    "self-test finished"
+  );
+  Ok(report)
+}
+
+fn run(preferences: &Preferences) -> Result<SelfTestReport, String> {
+  let _own_write = clipboard_watch::own_write();
+  let mut board =
+    Clipboard::new().map_err(|error| format!("Clipboard access failed: {error}"))?;
+  let previous = board.get_text().ok();
+
+  let started = Instant::now();
+  let clipboard_write = Check::timed(
+    started,
+    board.set_text(CAPTURE_SENTINEL).map_err(|error| format!("Clipboard write failed: {error}")),
+  );
+  let started = Instant::now();
+  let clipboard_read = Check::timed(
+    started,
+    match clipboard::read_text(&mut board) {
+      Ok(text) if text == CAPTURE_SENTINEL => Ok(()),
+      Ok(_) => Err("The clipboard returned different text than was written".to_string()),
+      Err(error) => Err(format!("Clipboard read failed: {error}")),
+    },
+  );
+
+  let started = Instant::now();
+  let key_simulation = Check::timed(started, input::availability());
+
+  let (capture, paste) = if key_simulation.passed && clipboard_write.passed {
+    match scratch::Scratch::open() {
+      Ok(scratch) => {
+        let capture = check_capture(preferences, &mut board, &scratch);
+        let paste = check_paste(preferences, &mut board, &scratch);
+        (capture, paste)
+      }
+      Err(error) => (Check::failed(error.clone()), Check::failed(error)),
+    }
+  } else {
+    let skipped = "Skipped: clipboard writes or keyboard simulation failed".to_string();
+    (Check::failed(skipped.clone()), Check::failed(skipped))
+  };
+
+  let _ = match previous {
+    Some(previous) => board.set_text(previous),
+    None => board.clear(),
+  };
+
+  let passed = [&clipboard_write, &clipboard_read, &key_simulation, &capture, &paste]
+    .iter()
+    .all(|check| check.passed);
+  Ok(SelfTestReport { passed, clipboard_write, clipboard_read, key_simulation, capture, paste })
+}
+
+fn check_capture(
+  preferences: &Preferences,
+  board: &mut Clipboard,
+  scratch: &scratch::Scratch,
+) -> Check {
+  scratch.set_text(CAPTURE_VALUE);
+  scratch.select_all();
+  if let Err(error) = board.set_text(CAPTURE_SENTINEL) {
+    return Check::failed(format!("Clipboard write failed: {error}"));
+  }
+
+  let started = Instant::now();
+  if let Err(error) = input::send_modified_key(preferences, 'c') {
+    return Check::failed(error);
+  }
+  let deadline = started + STEP_TIMEOUT;
+  let result = loop {
+    scratch.pump();
+    match clipboard::read_text(board) {
+      Ok(text) if text == CAPTURE_VALUE => break Ok(()),
+      Ok(text) if text != CAPTURE_SENTINEL => {
+        break Err(format!("The copy returned {} characters of other text", text.chars().count()))
+      }
+      _ if Instant::now() >= deadline => {
+        break Err("The copy never reached the clipboard".to_string())
+      }
+      _ => std::thread::sleep(Duration::from_millis(5)),
+    }
+  };
+  Check::timed(started, result)
+}
+
+fn check_paste(
+  preferences: &Preferences,
+  board: &mut Clipboard,
+  scratch: &scratch::Scratch,
+) -> Check {
+  scratch.set_text("");
+  if let Err(error) = board.set_text(PASTE_VALUE) {
+    return Check::failed(format!("Clipboard write failed: {error}"));
+  }
+
+  let started = Instant::now();
+  if let Err(error) = input::send_modified_key(preferences, 'v') {
+    return Check::failed(error);
+  }
+  let deadline = started + STEP_TIMEOUT;
+  let result = loop {
+    scratch.pump();
+    let text = scratch.text();
+    if text == PASTE_VALUE {
+      break Ok(());
+    }
+    if Instant::now() >= deadline {
+      break Err(if text.is_empty() {
+        "The paste never arrived".to_string()
+      } else {
+        format!("The paste arrived as {} characters of other text", text.chars().count())
+      });
+    }
+    std::thread::sleep(Duration::from_millis(5));
+  };
+  Check::timed(started, result)
+}
+
+#[cfg(windows)]
+mod scratch {
+  use std::ptr::null_mut;
+  use windows_sys::Win32::Foundation::HWND;
+  use windows_sys::Win32::UI::Input::KeyboardAndMouse::SetFocus;
+  use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DestroyWindow, DispatchMessageW, GetForegroundWindow, GetWindowTextW,
+    PeekMessageW, SendMessageW, SetForegroundWindow, SetWindowTextW, TranslateMessage,
+    ES_AUTOHSCROLL, MSG, PM_REMOVE, WS_EX_TOOLWINDOW, WS_POPUP, WS_VISIBLE,
+  };
+
+  const EM_SETSEL: u32 = 0x00B1;
+  const WM_GETTEXTLENGTH: u32 = 0x000E;
+
+  fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+  }
+
+  /// A focused, off-screen edit control owned by the calling thread, which
+  /// has to `pump` its messages for keystrokes to reach it.
+  pub struct Scratch {
+    edit: HWND,
+    previous_foreground: HWND,
+  }
+
+  impl Scratch {
+    pub fn open() -> Result<Self, String> {
+      let class = wide("EDIT");
+      // SAFETY: creates a window of a system class on this thread; it is
+      // destroyed on drop, on the same thread.
+      unsafe {
+        let previous_foreground = GetForegroundWindow();
+        let edit = CreateWindowExW(
+          WS_EX_TOOLWINDOW,
+          class.as_ptr(),
+          std::ptr::null(),
+          WS_POPUP | WS_VISIBLE | ES_AUTOHSCROLL as u32,
+          -32_000,
+          -32_000,
+          320,
+          24,
+          null_mut(),
+          null_mut(),
+          null_mut(),
+          std::ptr::null(),
+        );
+        if edit.is_null() {
+          return Err("Failed to create the self-test scratch buffer".to_string());
+        }
+        let scratch = Self { edit, previous_foreground };
+        if SetForegroundWindow(edit) == 0 {
+          return Err("Windows did not let the scratch buffer take focus".to_string());
+        }
+        SetFocus(edit);
+        scratch.pump();
+        Ok(scratch)
+      }
+    }
+
+    pub fn set_text(&self, text: &str) {
+      let text = wide(text);
+      // SAFETY: `edit` is alive and the string is NUL-terminated.
+      unsafe { SetWindowTextW(self.edit, text.as_ptr()) };
+    }
+
+    pub fn select_all(&self) {
+      // SAFETY: a plain message to a window of this thread.
+      unsafe { SendMessageW(self.edit, EM_SETSEL, 0, -1) };
+    }
+
+    pub fn text(&self) -> String {
+      // SAFETY: the buffer holds the reported length plus the terminator.
+      unsafe {
+        let length = SendMessageW(self.edit, WM_GETTEXTLENGTH, 0, 0).max(0) as usize;
+        let mut buffer = vec![0u16; length + 1];
+        let copied = GetWindowTextW(self.edit, buffer.as_mut_ptr(), buffer.len() as i32);
+        String::from_utf16_lossy(&buffer[..copied.max(0) as usize])
+      }
+    }
+
+    /// Deliver queued input, including the simulated keystrokes.
+    pub fn pump(&self) {
+      // SAFETY: standard message loop over this thread's queue.
+      unsafe {
+        let mut message: MSG = std::mem::zeroed();
+        while PeekMessageW(&mut message, null_mut(), 0, 0, PM_REMOVE) != 0 {
+          TranslateMessage(&message);
+          DispatchMessageW(&message);
+        }
+      }
+    }
+  }
+
+  impl Drop for Scratch {
+    fn drop(&mut self) {
+      // SAFETY: `edit` was created on this thread and is destroyed once.
+      unsafe {
+        DestroyWindow(self.edit);
+        if !self.previous_foreground.is_null() {
+          SetForegroundWindow(self.previous_foreground);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(not(windows))]
+mod scratch {
+  pub struct Scratch;
+
+  impl Scratch {
+    pub fn open() -> Result<Self, String> {
+      Err("The capture and paste checks need Windows".to_string())
+    }
+
+    pub fn set_text(&self, _text: &str) {}
+
+    pub fn select_all(&self) {}
+
+    pub fn text(&self) -> String {
+      String::new()
+    }
+
+    pub fn pump(&self) {}
+  }
+}
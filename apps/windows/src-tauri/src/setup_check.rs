@@ -0,0 +1,186 @@
+//! One-call readiness check for the last onboarding step: everything a first
+//! run needs, each item with its own result so the UI can say what to fix.
+
+use crate::provider::{self, Provider, ProviderError};
+use crate::{
+  action_bound_to, is_logs_shortcut, load_provider_api_key_secure, read_json, setup_file_path,
+  AppState, SetupFile,
+};
+use serde::Serialize;
+use tauri::{AppHandle, GlobalShortcutManager, Manager};
+use tracing::{info, instrument};
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupCheck {
+  passed: bool,
+  message: String,
+}
+
+impl SetupCheck {
+  fn pass(message: impl Into<String>) -> Self {
+    Self { passed: true, message: message.into() }
+  }
+
+  fn fail(message: impl Into<String>) -> Self {
+    Self { passed: false, message: message.into() }
+  }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupReport {
+  /// Every check passed.
+  ready: bool,
+  /// Setup is saved and passes the checks `save_setup` runs.
+  setup: SetupCheck,
+  /// The provider's key is stored and, where it can be checked for free,
+  /// accepted by the provider.
+  api_key: SetupCheck,
+  actions: SetupCheck,
+  default_action: SetupCheck,
+  shortcut: SetupCheck,
+}
+
+/// Check that the saved setup is usable. `shortcut` is the one the UI is
+/// about to register; without it the registered main shortcut is checked.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub async fn validate_setup(
+  handle: AppHandle,
+  shortcut: Option<String>,
+) -> Result<SetupReport, String> {
+  let shortcut_check = check_shortcut(&handle, shortcut)?;
+  let Some(setup) = read_json::<SetupFile>(&setup_file_path(&handle)?)? else {
+    let missing = || SetupCheck::fail("Setup hasn't been saved yet");
+    return Ok(SetupReport {
+      ready: false,
+      setup: missing(),
+      api_key: missing(),
+      actions: missing(),
+      default_action: missing(),
+      shortcut: shortcut_check,
+    });
+  };
+
+  let setup_check = match setup.validate() {
+    Ok(()) => SetupCheck::pass("Setup is valid"),
+    Err(error) => SetupCheck::fail(error),
+  };
+  let api_key = check_api_key(&handle, &setup).await?;
+  let actions = match setup.actions.len() {
+    0 => SetupCheck::fail("Create at least one action"),
+    1 => SetupCheck::pass("1 action"),
+    count => SetupCheck::pass(format!("{count} actions")),
+  };
+  let default_action = match (&setup.default_action_id, setup.actions.first()) {
+    (Some(id), _) => match setup.actions.iter().find(|action| &action.id == id) {
+      Some(action) => SetupCheck::pass(format!("{} is the default action", action.name)),
+      None => SetupCheck::fail("The default action no longer exists; pick another one"),
+    },
+    (None, Some(first)) => {
+      SetupCheck::pass(format!("No default is set, so the first action, {}, is used", first.name))
+    }
+    (None, None) => SetupCheck::fail("There is no action to use as the default"),
+  };
+
+  let checks = [&setup_check, &api_key, &actions, &default_action, &shortcut_check];
+  let ready = checks.iter().all(|check| check.passed);
+  info!(ready, "setup validated");
+  Ok(SetupReport {
+    ready,
+    setup: setup_check,
+    api_key,
+    actions,
+    default_action,
+    shortcut: shortcut_check,
+  })
+}
+
+async fn check_api_key(handle: &AppHandle, setup: &SetupFile) -> Result<SetupCheck, String> {
+  let provider = match Provider::parse(&setup.provider) {
+    Ok(provider) => provider,
+    Err(_) if setup.provider.is_empty() => return Ok(SetupCheck::fail("Choose a provider")),
+    Err(error) => return Ok(SetupCheck::fail(error)),
+  };
+  // Ollama takes no key, so its check is only that the server answers.
+  let api_key = match load_provider_api_key_secure(setup, &setup.provider)?
+    .filter(|key| !key.is_empty())
+  {
+    Some(api_key) => api_key,
+    None if !provider.needs_api_key() => String::new(),
+    None => {
+      return Ok(SetupCheck::fail(format!("No API key is stored for {}", provider.name())));
+    }
+  };
+  if let Some(warning) = provider.key_format_warning(&api_key) {
+    return Ok(SetupCheck::fail(warning));
+  }
+
+  let custom_headers = setup
+    .provider_settings
+    .get(provider.name())
+    .map(|settings| settings.request_headers())
+    .unwrap_or_default();
+  let client = handle.state::<AppState>().http_client.clone();
+  Ok(match provider::verify_api_key(&client, provider, &api_key, &custom_headers).await {
+    Ok(true) if !provider.needs_api_key() => {
+      SetupCheck::pass(format!("{} is running", provider.name()))
+    }
+    Ok(true) => SetupCheck::pass(format!("{} accepted the API key", provider.name())),
+    Ok(false) => SetupCheck::pass(format!(
+      "The API key is stored; {} can't check it without a billed request",
+      provider.name()
+    )),
+    Err(error) if !provider.needs_api_key() => {
+      SetupCheck::fail(format!("Couldn't reach {}: {error}", provider.name()))
+    }
+    Err(ProviderError::Status { status: 401 | 403, .. }) => {
+      SetupCheck::fail(format!("{} rejected the API key", provider.name()))
+    }
+    Err(error) => SetupCheck::fail(format!("Couldn't verify the API key: {error}")),
+  })
+}
+
+/// `shortcut` passes if it is the registered main shortcut, or if it is not
+/// taken (by this app or another one) and registers cleanly.
+fn check_shortcut(handle: &AppHandle, shortcut: Option<String>) -> Result<SetupCheck, String> {
+  let state = handle.state::<AppState>();
+  let active = state
+    .active_shortcut
+    .lock()
+    .map_err(|_| "Failed to lock shortcut state".to_string())?
+    .clone();
+  let Some(shortcut) = shortcut.map(|shortcut| shortcut.trim().to_string()).or(active.clone())
+  else {
+    return Ok(SetupCheck::fail("No shortcut is set"));
+  };
+  if shortcut.is_empty() {
+    return Ok(SetupCheck::fail("No shortcut is set"));
+  }
+
+  let mut shortcut_manager = handle.global_shortcut_manager();
+  if active.as_deref() == Some(shortcut.as_str()) {
+    return Ok(match shortcut_manager.is_registered(&shortcut) {
+      Ok(true) => SetupCheck::pass(format!("{shortcut} is registered")),
+      Ok(false) => SetupCheck::fail(format!("{shortcut} was set but is no longer registered")),
+      Err(error) => SetupCheck::fail(format!("Failed to query shortcut {shortcut}: {error}")),
+    });
+  }
+  if let Some(action_id) = action_bound_to(&state, &shortcut)? {
+    return Ok(SetupCheck::fail(format!("{shortcut} is already bound to action {action_id}")));
+  }
+  if is_logs_shortcut(&state, &shortcut)? {
+    return Ok(SetupCheck::fail(format!("{shortcut} is already used to show the logs")));
+  }
+
+  Ok(match shortcut_manager.register(&shortcut, || {}) {
+    Ok(()) => {
+      let _ = shortcut_manager.unregister(&shortcut);
+      SetupCheck::pass(format!("{shortcut} can be registered"))
+    }
+    Err(error) => SetupCheck::fail(format!(
+      "{shortcut} can't be registered; another application may own it ({error})"
+    )),
+  })
+}
@@ -0,0 +1,70 @@
+//! Single actions as self-contained JSON, for sharing between users. Only
+//! the action travels; provider settings and API keys never do.
+
+use crate::{read_json, setup_file_path, write_json, Action, JsonFormat, SetupFile};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tracing::{info, instrument};
+
+/// Bumped on incompatible changes. New action fields don't need a bump:
+/// they have serde defaults, so older exports still import.
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionExport {
+  shortcutai_action: u32,
+  action: Action,
+}
+
+fn load_setup(handle: &AppHandle) -> Result<SetupFile, String> {
+  read_json::<SetupFile>(&setup_file_path(handle)?)?
+    .ok_or_else(|| "Setup has not been completed".to_string())
+}
+
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub fn export_action(handle: AppHandle, action_id: String) -> Result<String, String> {
+  let action = load_setup(&handle)?
+    .actions
+    .into_iter()
+    .find(|action| action.id == action_id)
+    .ok_or_else(|| format!("Unknown action: {action_id}"))?;
+
+  let export = ActionExport {
+    shortcutai_action: EXPORT_VERSION,
+    // Usage details are the sender's, not part of the action.
+    action: Action { last_used_at: None, run_count: 0, pinned: false, ..action },
+  };
+  serde_json::to_string_pretty(&export).map_err(|error| format!("Failed to export action: {error}"))
+}
+
+/// Add an action from `export_action` output under a fresh id.
+#[tauri::command]
+#[instrument(skip_all, err)]
+pub fn import_action(handle: AppHandle, json: String) -> Result<Action, String> {
+  let export: ActionExport =
+    serde_json::from_str(&json).map_err(|error| format!("Not a ShortcutAI action: {error}"))?;
+  if export.shortcutai_action > EXPORT_VERSION {
+    return Err("This action was exported by a newer version of ShortcutAI".to_string());
+  }
+
+  let action = Action {
+    id: uuid::Uuid::new_v4().to_string(),
+    created_at: chrono::Utc::now().to_rfc3339(),
+    last_used_at: None,
+    run_count: 0,
+    pinned: false,
+    ..export.action
+  };
+  action.validate()?;
+
+  let path = setup_file_path(&handle)?;
+  let mut setup = load_setup(&handle)?;
+  setup.validate_stop_count(&action)?;
+  setup.actions.push(action.clone());
+  write_json(&path, &setup, JsonFormat::Pretty)?;
+
+  info!(action_id = %action.id, "imported action");
+  Ok(action)
+}
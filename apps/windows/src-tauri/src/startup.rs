@@ -0,0 +1,170 @@
+//! Launch at sign-in, through the current user's `Run` registry key. The
+//! per-user key needs no elevation, though group policy can still lock it.
+
+#[cfg(windows)]
+use crate::config_dir_override;
+#[cfg(windows)]
+use tracing::info;
+use tracing::instrument;
+
+/// Name of the value under the `Run` key.
+#[cfg(windows)]
+const VALUE_NAME: &str = "ShortcutAI";
+
+/// What the `Run` entry starts: this executable, with the portable config
+/// directory if one is in use so the started app finds the same setup.
+#[cfg(windows)]
+fn command_line() -> Result<String, String> {
+  let exe = std::env::current_exe()
+    .map_err(|error| format!("Failed to locate the ShortcutAI executable: {error}"))?;
+  let mut command = format!("\"{}\"", exe.display());
+  if let Some(dir) = config_dir_override() {
+    command.push_str(&format!(" --config-dir \"{}\"", dir.display()));
+  }
+  Ok(command)
+}
+
+#[cfg(windows)]
+mod registry {
+  use windows_sys::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_FILE_NOT_FOUND, ERROR_SUCCESS, WIN32_ERROR,
+  };
+  use windows_sys::Win32::System::Registry::{
+    RegCloseKey, RegDeleteValueW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW, HKEY,
+    HKEY_CURRENT_USER, KEY_QUERY_VALUE, KEY_SET_VALUE, REG_SAM_FLAGS, REG_SZ,
+  };
+
+  const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+  fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+  }
+
+  fn describe(action: &str, code: WIN32_ERROR) -> String {
+    if code == ERROR_ACCESS_DENIED {
+      format!(
+        "Failed to {action}: Windows denied access to the startup list. It may be managed by \
+         your organization's policy."
+      )
+    } else {
+      format!("Failed to {action}: Windows error {code}")
+    }
+  }
+
+  /// The `Run` key, closed when dropped.
+  struct RunKey(HKEY);
+
+  impl RunKey {
+    fn open(access: REG_SAM_FLAGS, action: &str) -> Result<Self, String> {
+      let path = wide(RUN_KEY);
+      let mut key: HKEY = std::ptr::null_mut();
+      // SAFETY: `path` is NUL-terminated and `key` is a local out parameter.
+      let code = unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, path.as_ptr(), 0, access, &mut key) };
+      if code != ERROR_SUCCESS {
+        return Err(describe(action, code));
+      }
+      Ok(Self(key))
+    }
+  }
+
+  impl Drop for RunKey {
+    fn drop(&mut self) {
+      // SAFETY: the handle came from a successful RegOpenKeyExW.
+      unsafe { RegCloseKey(self.0) };
+    }
+  }
+
+  pub fn is_set(name: &str) -> Result<bool, String> {
+    let action = "read the startup setting";
+    let key = RunKey::open(KEY_QUERY_VALUE, action)?;
+    let name = wide(name);
+    // Only the value's existence matters, so no data buffer is passed.
+    // SAFETY: `name` is NUL-terminated; null out parameters are allowed.
+    let code = unsafe {
+      RegQueryValueExW(
+        key.0,
+        name.as_ptr(),
+        std::ptr::null(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+      )
+    };
+    match code {
+      ERROR_SUCCESS => Ok(true),
+      ERROR_FILE_NOT_FOUND => Ok(false),
+      code => Err(describe(action, code)),
+    }
+  }
+
+  pub fn set(name: &str, command: &str) -> Result<(), String> {
+    let action = "enable launch at startup";
+    let key = RunKey::open(KEY_SET_VALUE, action)?;
+    let name = wide(name);
+    let data = wide(command);
+    // SAFETY: `data` is a NUL-terminated UTF-16 string; its size in bytes,
+    // terminator included, is passed along with it.
+    let code = unsafe {
+      RegSetValueExW(
+        key.0,
+        name.as_ptr(),
+        0,
+        REG_SZ,
+        data.as_ptr().cast(),
+        (data.len() * std::mem::size_of::<u16>()) as u32,
+      )
+    };
+    if code != ERROR_SUCCESS {
+      return Err(describe(action, code));
+    }
+    Ok(())
+  }
+
+  pub fn delete(name: &str) -> Result<(), String> {
+    let action = "disable launch at startup";
+    let key = RunKey::open(KEY_SET_VALUE, action)?;
+    let name = wide(name);
+    // SAFETY: `name` is NUL-terminated.
+    match unsafe { RegDeleteValueW(key.0, name.as_ptr()) } {
+      ERROR_SUCCESS | ERROR_FILE_NOT_FOUND => Ok(()),
+      code => Err(describe(action, code)),
+    }
+  }
+}
+
+/// Whether ShortcutAI is registered to start at sign-in, read from the
+/// registry so the settings toggle matches what Windows will do.
+#[tauri::command]
+#[instrument(err)]
+pub fn get_launch_at_startup() -> Result<bool, String> {
+  #[cfg(windows)]
+  {
+    registry::is_set(VALUE_NAME)
+  }
+  #[cfg(not(windows))]
+  {
+    Ok(false)
+  }
+}
+
+/// Register or unregister ShortcutAI to start at sign-in. Enabling again
+/// rewrites the entry, which repairs it after the app was moved.
+#[tauri::command]
+#[instrument(err)]
+pub fn set_launch_at_startup(enabled: bool) -> Result<(), String> {
+  #[cfg(windows)]
+  {
+    if enabled {
+      registry::set(VALUE_NAME, &command_line()?)?;
+    } else {
+      registry::delete(VALUE_NAME)?;
+    }
+    info!(enabled, "updated launch at startup");
+    Ok(())
+  }
+  #[cfg(not(windows))]
+  {
+    let _ = enabled;
+    Err("Launch at startup is only supported on Windows".to_string())
+  }
+}
@@ -0,0 +1,221 @@
+//! Opt-in usage telemetry. Runs are counted per provider and outcome, never
+//! with their text, prompts or ids, and the counts are sent in batches to
+//! the endpoint the user configured. Nothing is collected while
+//! `telemetry_enabled` is off, so nothing from before consent can be sent.
+
+use crate::{current_preferences, AppState, Preferences};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, instrument, warn};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// A batch is sent early once it has counted this many runs.
+const BATCH_RUNS: u32 = 50;
+
+/// Bumped when the payload changes shape.
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Default)]
+pub struct Tracker {
+  batch: Mutex<Batch>,
+  delivery: Mutex<Delivery>,
+}
+
+#[derive(Default)]
+struct Batch {
+  started_at: Option<String>,
+  /// Run counts keyed by provider (none for local transforms) and outcome:
+  /// `success` or an error category.
+  runs: HashMap<(Option<String>, &'static str), u32>,
+}
+
+impl Batch {
+  fn total(&self) -> u32 {
+    self.runs.values().sum()
+  }
+}
+
+#[derive(Default)]
+struct Delivery {
+  last_sent_at: Option<String>,
+  last_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Payload {
+  schema: u32,
+  app_version: &'static str,
+  period_start: String,
+  period_end: String,
+  runs: Vec<RunCount>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunCount {
+  provider: Option<String>,
+  outcome: &'static str,
+  count: u32,
+}
+
+/// Result of `telemetry_status`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryStatus {
+  enabled: bool,
+  endpoint: Option<String>,
+  /// Runs counted but not sent yet.
+  pending_runs: u32,
+  last_sent_at: Option<String>,
+  last_error: Option<String>,
+}
+
+/// Checks run by `save_setup`: consent needs somewhere to send to, over HTTPS.
+pub fn validate(preferences: &Preferences) -> Result<(), String> {
+  let Some(endpoint) = &preferences.telemetry_endpoint else {
+    if preferences.telemetry_enabled {
+      return Err("Telemetry needs an endpoint to send to".to_string());
+    }
+    return Ok(());
+  };
+  let url = reqwest::Url::parse(endpoint)
+    .map_err(|error| format!("Invalid telemetry endpoint {endpoint}: {error}"))?;
+  if url.scheme() != "https" {
+    return Err(format!("Telemetry endpoint {endpoint} must use https"));
+  }
+  Ok(())
+}
+
+/// Count a finished run, if the user opted in. `outcome` is `success` or an
+/// error category.
+pub fn record(
+  handle: &AppHandle,
+  preferences: &Preferences,
+  provider: Option<&str>,
+  outcome: &'static str,
+) {
+  if !preferences.telemetry_enabled {
+    return;
+  }
+  let state = handle.state::<AppState>();
+  let Ok(mut batch) = state.telemetry.batch.lock() else {
+    return;
+  };
+  batch.started_at.get_or_insert_with(|| chrono::Utc::now().to_rfc3339());
+  *batch.runs.entry((provider.map(str::to_string), outcome)).or_insert(0) += 1;
+  if batch.total() >= BATCH_RUNS {
+    let handle = handle.clone();
+    tauri::async_runtime::spawn(async move { flush(&handle).await });
+  }
+}
+
+/// Drop everything counted so far, e.g. right after consent is withdrawn.
+pub fn discard(state: &AppState) {
+  if let Ok(mut batch) = state.telemetry.batch.lock() {
+    *batch = Batch::default();
+  }
+}
+
+/// Send the batch on a timer for the lifetime of the app.
+pub fn spawn(handle: AppHandle) {
+  let spawned = thread::Builder::new().name("telemetry".to_string()).spawn(move || loop {
+    thread::sleep(FLUSH_INTERVAL);
+    tauri::async_runtime::block_on(flush(&handle));
+  });
+  if let Err(error) = spawned {
+    warn!(%error, "failed to start telemetry");
+  }
+}
+
+/// Send and clear the batch. Consent is checked again here, so a batch
+/// counted before the user opted out is dropped rather than sent. A failed
+/// send drops the batch too; telemetry is not worth retry logic.
+async fn flush(handle: &AppHandle) {
+  let preferences = current_preferences(handle);
+  let state = handle.state::<AppState>();
+  let endpoint = preferences.telemetry_endpoint.filter(|_| preferences.telemetry_enabled);
+  let Some(endpoint) = endpoint else {
+    discard(&state);
+    return;
+  };
+  let Some(payload) = take_payload(&state) else {
+    return;
+  };
+
+  let result = state
+    .http_client
+    .post(&endpoint)
+    .json(&payload)
+    .timeout(Duration::from_secs(10))
+    .send()
+    .await
+    .and_then(|response| response.error_for_status())
+    .map(|_| ());
+
+  let Ok(mut delivery) = state.telemetry.delivery.lock() else {
+    return;
+  };
+  match result {
+    Ok(()) => {
+      info!(runs = payload.runs.len(), "sent telemetry batch");
+      delivery.last_sent_at = Some(payload.period_end);
+      delivery.last_error = None;
+    }
+    Err(error) => {
+      warn!(%error, "failed to send telemetry batch");
+      delivery.last_error = Some(error.to_string());
+    }
+  }
+}
+
+fn take_payload(state: &AppState) -> Option<Payload> {
+  let batch = std::mem::take(&mut *state.telemetry.batch.lock().ok()?);
+  let period_start = batch.started_at?;
+  Some(Payload {
+    schema: SCHEMA_VERSION,
+    app_version: env!("CARGO_PKG_VERSION"),
+    period_start,
+    period_end: chrono::Utc::now().to_rfc3339(),
+    runs: batch
+      .runs
+      .into_iter()
+      .map(|((provider, outcome), count)| RunCount { provider, outcome, count })
+      .collect(),
+  })
+}
+
+/// Whether telemetry is on, where it goes and how delivery is going, for the
+/// consent screen.
+#[tauri::command]
+#[instrument(skip_all, err)]
+pub fn telemetry_status(state: State<'_, AppState>) -> Result<TelemetryStatus, String> {
+  let preferences = state
+    .preferences
+    .lock()
+    .map_err(|_| "Failed to lock preferences state".to_string())?
+    .clone();
+  let pending_runs = state
+    .telemetry
+    .batch
+    .lock()
+    .map_err(|_| "Failed to lock telemetry state".to_string())?
+    .total();
+  let delivery = state
+    .telemetry
+    .delivery
+    .lock()
+    .map_err(|_| "Failed to lock telemetry state".to_string())?;
+  Ok(TelemetryStatus {
+    enabled: preferences.telemetry_enabled,
+    endpoint: preferences.telemetry_endpoint,
+    pending_runs,
+    last_sent_at: delivery.last_sent_at.clone(),
+    last_error: delivery.last_error.clone(),
+  })
+}
@@ -0,0 +1,109 @@
+//! Local counts for a piece of text, for a word-count view and for hints
+//! such as the size of a capture before an expensive run.
+
+use serde::Serialize;
+
+/// Typical silent reading speeds for adult readers.
+const WORDS_PER_MINUTE: f64 = 238.0;
+/// Chinese and Japanese are read by character rather than by word.
+const CJK_CHARS_PER_MINUTE: f64 = 500.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStats {
+  /// Unicode scalar values, like `String::chars`.
+  chars: usize,
+  /// Characters other than whitespace.
+  chars_without_spaces: usize,
+  /// Runs of letters or digits; each Chinese or Japanese character counts as
+  /// a word of its own, as their word processors count them.
+  words: usize,
+  /// Lines with or without text; zero for empty text.
+  lines: usize,
+  /// Rounded up to whole seconds; zero for text without words.
+  reading_time_secs: u64,
+}
+
+/// Count `text` locally; no provider is called.
+#[tauri::command]
+pub fn analyze_text(text: String) -> TextStats {
+  analyze(&text)
+}
+
+fn analyze(text: &str) -> TextStats {
+  let mut stats = TextStats::default();
+  let mut cjk = 0;
+  let mut in_word = false;
+  for c in text.chars() {
+    stats.chars += 1;
+    if !c.is_whitespace() {
+      stats.chars_without_spaces += 1;
+    }
+    if is_cjk(c) {
+      cjk += 1;
+      in_word = false;
+    } else if c.is_alphanumeric() || (in_word && matches!(c, '\'' | '’' | '-')) {
+      if !in_word {
+        stats.words += 1;
+        in_word = true;
+      }
+    } else {
+      in_word = false;
+    }
+  }
+  stats.words += cjk;
+  stats.lines = if text.is_empty() { 0 } else { text.lines().count().max(1) };
+
+  let minutes = (stats.words - cjk) as f64 / WORDS_PER_MINUTE + cjk as f64 / CJK_CHARS_PER_MINUTE;
+  stats.reading_time_secs = (minutes * 60.0).ceil() as u64;
+  stats
+}
+
+/// Han ideographs and kana, which are written without spaces between words.
+fn is_cjk(c: char) -> bool {
+  matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn empty_text_has_no_counts() {
+    assert_eq!(analyze(""), TextStats::default());
+    let blank = analyze(" \n");
+    assert_eq!((blank.chars, blank.chars_without_spaces, blank.words), (2, 0, 0));
+    assert_eq!((blank.lines, blank.reading_time_secs), (1, 0));
+  }
+
+  #[test]
+  fn words_keep_apostrophes_and_hyphens_inside_them() {
+    assert_eq!(analyze("Don't over-think it - twice, 2 times.").words, 6);
+    assert_eq!(analyze("it’s").words, 1);
+    assert_eq!(analyze("Привет, мир").words, 2);
+  }
+
+  #[test]
+  fn lines_count_crlf_and_blank_lines_once() {
+    let stats = analyze("one\r\ntwo\r\n\r\nthree\r\n");
+    assert_eq!(stats.lines, 4);
+    assert_eq!(stats.words, 3);
+    assert_eq!(stats.chars, 19);
+    assert_eq!(stats.chars_without_spaces, 11);
+    assert_eq!(analyze("no newline").lines, 1);
+  }
+
+  #[test]
+  fn each_cjk_character_is_a_word() {
+    let stats = analyze("今日はいい天気 ok");
+    assert_eq!(stats.words, 8);
+    assert_eq!(stats.chars_without_spaces, 9);
+  }
+
+  #[test]
+  fn reading_time_rounds_up_to_whole_seconds() {
+    assert_eq!(analyze(&"word ".repeat(238)).reading_time_secs, 60);
+    assert_eq!(analyze("word").reading_time_secs, 1);
+    assert_eq!(analyze(&"字".repeat(500)).reading_time_secs, 60);
+  }
+}
@@ -0,0 +1,266 @@
+//! Local tools an action can let the model call. Each tool is a variant of
+//! `Tool`: to add one, give it a name, a description, a JSON schema for its
+//! arguments and an implementation in `Tool::call`. Tools run in-process and
+//! must not touch files, the network or the clipboard.
+
+use crate::provider::{ToolCall, ToolDefinition, ToolRound};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+
+/// Tool calls answered per run before the model has to give a final answer.
+pub const MAX_TOOL_ROUNDS: usize = 4;
+
+/// Longest expression the calculator evaluates; also bounds its recursion.
+const MAX_EXPRESSION_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Tool {
+  /// Arithmetic on numbers with `+ - * / % ^` and parentheses.
+  Calculator,
+  /// The local date and time with its UTC offset.
+  CurrentTime,
+}
+
+impl Tool {
+  /// Name the model calls the tool by.
+  pub fn name(self) -> &'static str {
+    match self {
+      Tool::Calculator => "calculator",
+      Tool::CurrentTime => "current_time",
+    }
+  }
+
+  fn description(self) -> &'static str {
+    match self {
+      Tool::Calculator => {
+        "Evaluate an arithmetic expression exactly. Supports + - * / % ^ and parentheses."
+      }
+      Tool::CurrentTime => "Get the user's current local date and time, with the UTC offset.",
+    }
+  }
+
+  /// JSON schema of the tool's arguments.
+  fn parameters(self) -> Value {
+    match self {
+      Tool::Calculator => json!({
+        "type": "object",
+        "properties": {
+          "expression": { "type": "string", "description": "For example (12.5 * 4) / 3" },
+        },
+        "required": ["expression"],
+      }),
+      Tool::CurrentTime => json!({ "type": "object", "properties": {} }),
+    }
+  }
+
+  fn call(self, arguments: &Value) -> Result<String, String> {
+    match self {
+      Tool::Calculator => {
+        let expression = arguments
+          .get("expression")
+          .and_then(Value::as_str)
+          .ok_or("Missing the expression argument")?;
+        evaluate(expression).map(format_number)
+      }
+      Tool::CurrentTime => Ok(chrono::Local::now().to_rfc3339()),
+    }
+  }
+}
+
+/// What the provider is told about `tools`.
+pub fn definitions(tools: &[Tool]) -> Vec<ToolDefinition> {
+  tools
+    .iter()
+    .map(|tool| ToolDefinition {
+      name: tool.name(),
+      description: tool.description(),
+      parameters: tool.parameters(),
+    })
+    .collect()
+}
+
+/// Run the model's tool calls. A call to a tool the action doesn't allow, or
+/// one that fails, is answered with the error so the model can recover.
+pub fn answer(allowed: &[Tool], calls: Vec<ToolCall>) -> ToolRound {
+  let outputs = calls
+    .iter()
+    .map(|call| {
+      let result = match allowed.iter().find(|tool| tool.name() == call.name) {
+        Some(tool) => tool.call(&call.arguments),
+        None => Err(format!("Unknown tool: {}", call.name)),
+      };
+      info!(tool = %call.name, ok = result.is_ok(), "answered tool call");
+      result.unwrap_or_else(|error| format!("Error: {error}"))
+    })
+    .collect();
+  ToolRound { calls, outputs }
+}
+
+fn format_number(value: f64) -> String {
+  if value.fract() == 0.0 && value.abs() < 1e15 {
+    format!("{value:.0}")
+  } else {
+    value.to_string()
+  }
+}
+
+fn evaluate(expression: &str) -> Result<f64, String> {
+  if expression.chars().count() > MAX_EXPRESSION_CHARS {
+    return Err(format!("Expressions are limited to {MAX_EXPRESSION_CHARS} characters"));
+  }
+  let mut parser = Parser {
+    chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+    position: 0,
+  };
+  let value = parser.sum()?;
+  if let Some(c) = parser.peek() {
+    return Err(format!("Unexpected '{c}' in the expression"));
+  }
+  if !value.is_finite() {
+    return Err("The result is not a finite number".to_string());
+  }
+  Ok(value)
+}
+
+/// Recursive descent over `sum := product (('+' | '-') product)*`,
+/// `product := unary (('*' | '/' | '%') unary)*`, `unary := '-' unary | power`
+/// and `power := atom ('^' unary)?`, so `-2^2` is -4 and `^` is right-associative.
+struct Parser {
+  chars: Vec<char>,
+  position: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.position).copied()
+  }
+
+  fn sum(&mut self) -> Result<f64, String> {
+    let mut value = self.product()?;
+    while let Some(operator @ ('+' | '-')) = self.peek() {
+      self.position += 1;
+      let rhs = self.product()?;
+      value = if operator == '+' { value + rhs } else { value - rhs };
+    }
+    Ok(value)
+  }
+
+  fn product(&mut self) -> Result<f64, String> {
+    let mut value = self.unary()?;
+    while let Some(operator @ ('*' | '/' | '%')) = self.peek() {
+      self.position += 1;
+      let rhs = self.unary()?;
+      if operator != '*' && rhs == 0.0 {
+        return Err("Division by zero".to_string());
+      }
+      value = match operator {
+        '*' => value * rhs,
+        '/' => value / rhs,
+        _ => value % rhs,
+      };
+    }
+    Ok(value)
+  }
+
+  fn unary(&mut self) -> Result<f64, String> {
+    match self.peek() {
+      Some('-') => {
+        self.position += 1;
+        Ok(-self.unary()?)
+      }
+      Some('+') => {
+        self.position += 1;
+        self.unary()
+      }
+      _ => self.power(),
+    }
+  }
+
+  fn power(&mut self) -> Result<f64, String> {
+    let base = self.atom()?;
+    if self.peek() == Some('^') {
+      self.position += 1;
+      return Ok(base.powf(self.unary()?));
+    }
+    Ok(base)
+  }
+
+  fn atom(&mut self) -> Result<f64, String> {
+    match self.peek() {
+      Some('(') => {
+        self.position += 1;
+        let value = self.sum()?;
+        if self.peek() != Some(')') {
+          return Err("Missing ')' in the expression".to_string());
+        }
+        self.position += 1;
+        Ok(value)
+      }
+      Some(c) if c.is_ascii_digit() || c == '.' => {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+          self.position += 1;
+        }
+        let number: String = self.chars[start..self.position].iter().collect();
+        number.parse().map_err(|_| format!("Invalid number {number}"))
+      }
+      Some(c) => Err(format!("Unexpected '{c}' in the expression")),
+      None => Err("The expression ended early".to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn products_bind_tighter_than_sums() {
+    assert_eq!(evaluate("1 + 2 * 3"), Ok(7.0));
+    assert_eq!(evaluate("(1 + 2) * 3"), Ok(9.0));
+    assert_eq!(evaluate("10 - 4 - 3"), Ok(3.0));
+    assert_eq!(evaluate("7 % 4 * 2"), Ok(6.0));
+    assert_eq!(evaluate("12.5 * 4 / 5"), Ok(10.0));
+  }
+
+  #[test]
+  fn powers_are_right_associative_and_bind_tighter_than_negation() {
+    assert_eq!(evaluate("-2^2"), Ok(-4.0));
+    assert_eq!(evaluate("(-2)^2"), Ok(4.0));
+    assert_eq!(evaluate("2^3^2"), Ok(512.0));
+    assert_eq!(evaluate("2^-1"), Ok(0.5));
+  }
+
+  #[test]
+  fn division_by_zero_is_an_error() {
+    assert!(evaluate("1 / 0").is_err());
+    assert!(evaluate("1 % (2 - 2)").is_err());
+  }
+
+  #[test]
+  fn malformed_expressions_are_errors() {
+    assert!(evaluate("(1 + 2").is_err());
+    assert!(evaluate("1 + 2)").is_err());
+    assert!(evaluate("2 * 3 x").is_err());
+    assert!(evaluate("1.2.3").is_err());
+    assert!(evaluate("1 +").is_err());
+    assert!(evaluate("").is_err());
+  }
+
+  #[test]
+  fn long_expressions_are_refused() {
+    let at_limit = format!("1{}", "+1".repeat((MAX_EXPRESSION_CHARS - 1) / 2));
+    assert_eq!(evaluate(&at_limit), Ok(at_limit.len().div_ceil(2) as f64));
+    let depth = MAX_EXPRESSION_CHARS;
+    let nested = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(evaluate(&nested).is_err());
+  }
+
+  #[test]
+  fn whole_results_are_formatted_without_a_fraction() {
+    assert_eq!(format_number(42.0), "42");
+    assert_eq!(format_number(0.25), "0.25");
+  }
+}
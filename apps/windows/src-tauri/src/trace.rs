@@ -0,0 +1,72 @@
+//! Backend diagnostics: a daily-rolling trace file in the app data directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::EnvFilter;
+
+const TRACE_DIR: &str = "traces";
+const TRACE_FILE_PREFIX: &str = "shortcutai.log";
+
+pub fn trace_dir(app_data_dir: &Path) -> PathBuf {
+  app_data_dir.join(TRACE_DIR)
+}
+
+/// Install the global subscriber. `RUST_LOG` takes precedence over the
+/// configured `level` so field builds can be debugged without editing setup.
+/// The returned guard must be kept alive to flush buffered lines.
+pub fn init(app_data_dir: &Path, level: &str) -> Result<WorkerGuard, String> {
+  let dir = trace_dir(app_data_dir);
+  fs::create_dir_all(&dir)
+    .map_err(|error| format!("Failed to create trace directory: {error}"))?;
+
+  let filter = EnvFilter::try_from_default_env()
+    .or_else(|_| EnvFilter::try_new(level))
+    .unwrap_or_else(|_| EnvFilter::new("info"));
+
+  let appender = tracing_appender::rolling::daily(&dir, TRACE_FILE_PREFIX);
+  let (writer, guard) = tracing_appender::non_blocking(appender);
+
+  tracing_subscriber::fmt()
+    .with_env_filter(filter)
+    .with_writer(writer)
+    .with_ansi(false)
+    .try_init()
+    .map_err(|error| format!("Failed to install trace subscriber: {error}"))?;
+
+  Ok(guard)
+}
+
+/// Return the last `max_lines` lines of the newest trace file.
+pub fn recent_lines(app_data_dir: &Path, max_lines: usize) -> Result<Vec<String>, String> {
+  let dir = trace_dir(app_data_dir);
+  if !dir.exists() {
+    return Ok(Vec::new());
+  }
+
+  let entries = fs::read_dir(&dir)
+    .map_err(|error| format!("Failed to read trace directory: {error}"))?;
+
+  // Rolled files are suffixed with the date, so the lexically last name is newest.
+  let newest = entries
+    .filter_map(|entry| entry.ok())
+    .map(|entry| entry.path())
+    .filter(|path| {
+      path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(TRACE_FILE_PREFIX))
+    })
+    .max();
+
+  let Some(path) = newest else {
+    return Ok(Vec::new());
+  };
+
+  let raw = fs::read_to_string(&path)
+    .map_err(|error| format!("Failed to read trace file {}: {error}", path.display()))?;
+  let lines: Vec<&str> = raw.lines().collect();
+  let start = lines.len().saturating_sub(max_lines);
+
+  Ok(lines[start..].iter().map(|line| line.to_string()).collect())
+}
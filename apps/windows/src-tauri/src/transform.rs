@@ -0,0 +1,139 @@
+//! Local text transforms: actions that reformat text without a provider.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LocalTransform {
+  /// Prefix every line with `> `, as in an email or Markdown reply.
+  Quote,
+  Uppercase,
+  Lowercase,
+  /// Capitalize the first letter of every word and lowercase the rest.
+  Titlecase,
+  /// Remove leading and trailing whitespace.
+  Trim,
+  /// Re-indent JSON with two spaces; fails on invalid JSON.
+  JsonPretty,
+  /// Replace every match of `pattern`. `replacement` may refer to capture
+  /// groups as `$1` or `${name}`.
+  #[serde(rename_all = "camelCase")]
+  RegexReplace { pattern: String, replacement: String },
+}
+
+impl LocalTransform {
+  pub fn name(&self) -> &'static str {
+    match self {
+      LocalTransform::Quote => "quote",
+      LocalTransform::Uppercase => "uppercase",
+      LocalTransform::Lowercase => "lowercase",
+      LocalTransform::Titlecase => "titlecase",
+      LocalTransform::Trim => "trim",
+      LocalTransform::JsonPretty => "jsonPretty",
+      LocalTransform::RegexReplace { .. } => "regexReplace",
+    }
+  }
+
+  /// Checks run by `save_setup`, so a broken pattern fails there rather than
+  /// on the next run.
+  pub fn validate(&self) -> Result<(), String> {
+    if let LocalTransform::RegexReplace { pattern, .. } = self {
+      compile(pattern)?;
+    }
+    Ok(())
+  }
+
+  pub fn apply(&self, text: &str) -> Result<String, String> {
+    Ok(match self {
+      LocalTransform::Quote => text
+        .lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+        .collect::<Vec<_>>()
+        .join("\n"),
+      LocalTransform::Uppercase => text.to_uppercase(),
+      LocalTransform::Lowercase => text.to_lowercase(),
+      LocalTransform::Titlecase => titlecase(text),
+      LocalTransform::Trim => text.trim().to_string(),
+      LocalTransform::JsonPretty => {
+        let value: serde_json::Value = serde_json::from_str(text.trim())
+          .map_err(|error| format!("The text is not valid JSON: {error}"))?;
+        serde_json::to_string_pretty(&value)
+          .map_err(|error| format!("Failed to format JSON: {error}"))?
+      }
+      LocalTransform::RegexReplace { pattern, replacement } => {
+        compile(pattern)?.replace_all(text, replacement.as_str()).into_owned()
+      }
+    })
+  }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+  Regex::new(pattern).map_err(|error| format!("Invalid regex {pattern:?}: {error}"))
+}
+
+fn titlecase(text: &str) -> String {
+  let mut output = String::with_capacity(text.len());
+  let mut at_word_start = true;
+  for c in text.chars() {
+    if at_word_start {
+      output.extend(c.to_uppercase());
+    } else {
+      output.extend(c.to_lowercase());
+    }
+    at_word_start = c.is_whitespace() || c == '-';
+  }
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn apply(transform: LocalTransform, text: &str) -> String {
+    transform.apply(text).unwrap()
+  }
+
+  #[test]
+  fn quote_prefixes_every_line() {
+    assert_eq!(apply(LocalTransform::Quote, "one\r\n\r\ntwo\n"), "> one\n>\n> two");
+    assert_eq!(apply(LocalTransform::Quote, "single"), "> single");
+    assert_eq!(apply(LocalTransform::Quote, ""), "");
+  }
+
+  #[test]
+  fn case_transforms_handle_non_latin_letters() {
+    assert_eq!(apply(LocalTransform::Uppercase, "straße café"), "STRASSE CAFÉ");
+    assert_eq!(apply(LocalTransform::Lowercase, "ΣΟΦΙΑ Ünal"), "σοφια ünal");
+    assert_eq!(apply(LocalTransform::Titlecase, "hELLO wide-world"), "Hello Wide-World");
+    let cyrillic = apply(LocalTransform::Titlecase, "привет  мир\nnext");
+    assert_eq!(cyrillic, "Привет  Мир\nNext");
+    assert_eq!(apply(LocalTransform::Titlecase, "日本語 text"), "日本語 Text");
+  }
+
+  #[test]
+  fn trim_removes_surrounding_whitespace_only() {
+    assert_eq!(apply(LocalTransform::Trim, " \r\n a  b \t\n"), "a  b");
+    assert_eq!(apply(LocalTransform::Trim, "   "), "");
+  }
+
+  #[test]
+  fn json_is_reindented_or_refused() {
+    let pretty = apply(LocalTransform::JsonPretty, " {\"a\":[1]} \n");
+    assert_eq!(pretty, "{\n  \"a\": [\n    1\n  ]\n}");
+    assert!(LocalTransform::JsonPretty.apply("{\"a\":").is_err());
+  }
+
+  #[test]
+  fn regex_replace_expands_groups_and_rejects_bad_patterns() {
+    let swap = LocalTransform::RegexReplace {
+      pattern: r"(?P<first>\w+) (\w+)".to_string(),
+      replacement: "$2 ${first}".to_string(),
+    };
+    assert_eq!(apply(swap, "hello world"), "world hello");
+    let broken =
+      LocalTransform::RegexReplace { pattern: "(".to_string(), replacement: String::new() };
+    assert!(broken.validate().is_err());
+    assert!(broken.apply("text").is_err());
+  }
+}
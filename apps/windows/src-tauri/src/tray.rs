@@ -0,0 +1,65 @@
+//! Tray menu, with a submenu of the active profile's pinned actions.
+
+use crate::{
+  clipboard, read_json, setup_file_path, show_main_window, Action, ActionShortcutTriggered, SetupFile,
+};
+use arboard::Clipboard;
+use tauri::{
+  AppHandle, CustomMenuItem, Manager, SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu,
+};
+use tracing::warn;
+
+/// Menu item ids of pinned actions are this prefix plus the action id.
+const PINNED_PREFIX: &str = "pinned:";
+
+pub fn menu(pinned: &[Action]) -> SystemTrayMenu {
+  let mut menu = SystemTrayMenu::new().add_item(CustomMenuItem::new("show", "Show ShortcutAI"));
+
+  if !pinned.is_empty() {
+    let submenu = pinned.iter().fold(SystemTrayMenu::new(), |submenu, action| {
+      submenu.add_item(CustomMenuItem::new(
+        format!("{PINNED_PREFIX}{}", action.id),
+        action.name.clone(),
+      ))
+    });
+    menu = menu.add_submenu(SystemTraySubmenu::new("Pinned actions", submenu));
+  }
+
+  menu
+    .add_native_item(SystemTrayMenuItem::Separator)
+    .add_item(CustomMenuItem::new("quit", "Quit"))
+}
+
+/// Rebuild the menu from the saved setup, after it or the profile changed.
+pub fn refresh(handle: &AppHandle) {
+  let pinned = setup_file_path(handle)
+    .and_then(|path| read_json::<SetupFile>(&path))
+    .map(|setup| setup.map(|setup| setup.pinned_actions()).unwrap_or_default());
+  let result = pinned.and_then(|pinned| {
+    handle
+      .tray_handle()
+      .set_menu(menu(&pinned))
+      .map_err(|error| format!("Failed to update tray menu: {error}"))
+  });
+  if let Err(error) = result {
+    warn!(%error, "failed to refresh pinned actions in the tray");
+  }
+}
+
+/// The action id behind a tray menu item, if it's a pinned action.
+pub fn pinned_action_id(menu_id: &str) -> Option<&str> {
+  menu_id.strip_prefix(PINNED_PREFIX)
+}
+
+/// Run a pinned action picked from the tray. The tray has focus by then, so
+/// there is no selection to capture; the action gets the clipboard instead.
+pub fn run_pinned(handle: &AppHandle, action_id: &str) {
+  let text = Clipboard::new()
+    .and_then(|mut board| clipboard::read_text(&mut board))
+    .unwrap_or_default();
+  let _ = handle.emit_all(
+    "action-shortcut-triggered",
+    ActionShortcutTriggered::new(action_id.to_string(), text),
+  );
+  show_main_window(handle);
+}
@@ -0,0 +1,189 @@
+//! Stamps `last_used_at` on actions when they run and counts their
+//! successful runs in `run_count`. Setup is rewritten a moment after the last
+//! run rather than after every one, so a burst of runs costs a single write.
+
+use crate::{read_json, setup_file_path, write_json, Action, AppState, JsonFormat, SetupFile};
+use chrono::DateTime;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::{instrument, warn};
+
+const FLUSH_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct Tracker {
+  /// Usage not written to setup yet, keyed by action id.
+  pending: Mutex<HashMap<String, Usage>>,
+  /// Bumped by every run; a scheduled flush only goes ahead if it is still
+  /// the latest.
+  generation: AtomicU64,
+}
+
+#[derive(Debug, Clone)]
+struct Usage {
+  last_used_at: String,
+  /// Successful runs since the last write.
+  runs: u64,
+}
+
+/// Payload of `action-last-used`, so the UI doesn't save a stale time back.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ActionLastUsed {
+  action_id: String,
+  last_used_at: String,
+  /// Whether the run counted towards `run_count`.
+  succeeded: bool,
+}
+
+impl Tracker {
+  pub fn has_pending(&self) -> bool {
+    self.pending.lock().is_ok_and(|pending| !pending.is_empty())
+  }
+}
+
+/// Note that `action_id` just ran, counting the run if it `succeeded`, and
+/// schedule a write.
+pub fn record(handle: &AppHandle, action_id: &str, succeeded: bool) {
+  let tracker = &handle.state::<AppState>().usage;
+  let last_used_at = chrono::Utc::now().to_rfc3339();
+  match tracker.pending.lock() {
+    Ok(mut pending) => {
+      let usage = pending
+        .entry(action_id.to_string())
+        .or_insert_with(|| Usage { last_used_at: String::new(), runs: 0 });
+      usage.last_used_at = last_used_at.clone();
+      usage.runs += u64::from(succeeded);
+    }
+    Err(_) => {
+      warn!("failed to lock usage state");
+      return;
+    }
+  };
+  let _ = handle.emit_all(
+    "action-last-used",
+    ActionLastUsed { action_id: action_id.to_string(), last_used_at, succeeded },
+  );
+
+  let generation = tracker.generation.fetch_add(1, Ordering::SeqCst) + 1;
+  let handle = handle.clone();
+  thread::spawn(move || {
+    thread::sleep(FLUSH_DELAY);
+    if handle.state::<AppState>().usage.generation.load(Ordering::SeqCst) == generation {
+      if let Err(error) = flush(&handle) {
+        warn!(%error, "failed to save last-used times");
+      }
+    }
+  });
+}
+
+/// Forget usage not written yet, so it can't land in a setup saved later.
+pub fn discard(state: &AppState) {
+  if let Ok(mut pending) = state.usage.pending.lock() {
+    pending.clear();
+  }
+  state.usage.generation.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Write pending usage into setup. Actions deleted since they ran, or
+/// belonging to a profile switched away from, are not in setup and are skipped.
+pub fn flush(handle: &AppHandle) -> Result<(), String> {
+  let pending = std::mem::take(
+    &mut *handle
+      .state::<AppState>()
+      .usage
+      .pending
+      .lock()
+      .map_err(|_| "Failed to lock usage state".to_string())?,
+  );
+  if pending.is_empty() {
+    return Ok(());
+  }
+
+  let path = setup_file_path(handle)?;
+  let Some(mut setup) = read_json::<SetupFile>(&path)? else {
+    return Ok(());
+  };
+  let mut changed = false;
+  for action in &mut setup.actions {
+    if let Some(usage) = pending.get(&action.id) {
+      action.last_used_at = Some(usage.last_used_at.clone());
+      action.run_count += usage.runs;
+      changed = true;
+    }
+  }
+  if changed {
+    write_json(&path, &setup, JsonFormat::Pretty)?;
+  }
+  Ok(())
+}
+
+/// Keep the later of the saved and incoming `last_used_at` of each action, so
+/// a save from a UI that missed a run doesn't roll the time back, and the
+/// saved `run_count`, which only `flush` changes.
+pub fn keep_usage(setup: &mut SetupFile, stored: &SetupFile) {
+  let run_counts: HashMap<&str, u64> =
+    stored.actions.iter().map(|action| (action.id.as_str(), action.run_count)).collect();
+  let stored: HashMap<&str, &str> = stored
+    .actions
+    .iter()
+    .filter_map(|action| Some((action.id.as_str(), action.last_used_at.as_deref()?)))
+    .collect();
+  for action in &mut setup.actions {
+    if let Some(&run_count) = run_counts.get(action.id.as_str()) {
+      action.run_count = run_count;
+    }
+    let Some(&saved) = stored.get(action.id.as_str()) else {
+      continue;
+    };
+    let newer = match action.last_used_at.as_deref().map(DateTime::parse_from_rfc3339) {
+      Some(Ok(incoming)) => DateTime::parse_from_rfc3339(saved).is_ok_and(|saved| saved > incoming),
+      // Nothing or garbage came back; the saved time is the better one.
+      None | Some(Err(_)) => true,
+    };
+    if newer {
+      action.last_used_at = Some(saved.to_string());
+    }
+  }
+}
+
+/// Actions by `run_count`, most used first, counting runs not written yet.
+/// Ties go to the more recently used. At most `limit` are returned.
+#[tauri::command]
+#[instrument(skip(handle), err)]
+pub fn most_used_actions(handle: AppHandle, limit: Option<usize>) -> Result<Vec<Action>, String> {
+  let mut actions = current_actions(&handle)?;
+  // RFC 3339 times in UTC sort chronologically as strings.
+  actions.sort_by(|a, b| {
+    b.run_count.cmp(&a.run_count).then_with(|| b.last_used_at.cmp(&a.last_used_at))
+  });
+  actions.truncate(limit.unwrap_or(actions.len()));
+  Ok(actions)
+}
+
+/// Each action's `run_count`, counting runs not written yet, by action id.
+pub fn run_counts(handle: &AppHandle) -> Result<HashMap<String, u64>, String> {
+  Ok(current_actions(handle)?.into_iter().map(|action| (action.id, action.run_count)).collect())
+}
+
+/// The configured actions with the usage not written yet applied.
+fn current_actions(handle: &AppHandle) -> Result<Vec<Action>, String> {
+  let Some(setup) = read_json::<SetupFile>(&setup_file_path(handle)?)? else {
+    return Ok(Vec::new());
+  };
+  let mut actions = setup.actions;
+  let state = handle.state::<AppState>();
+  let pending = state.usage.pending.lock().map_err(|_| "Failed to lock usage state".to_string())?;
+  for action in &mut actions {
+    if let Some(usage) = pending.get(&action.id) {
+      action.last_used_at = Some(usage.last_used_at.clone());
+      action.run_count += usage.runs;
+    }
+  }
+  Ok(actions)
+}
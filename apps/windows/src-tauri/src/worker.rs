@@ -0,0 +1,70 @@
+//! Bounded worker pool for shortcut-triggered capture and run tasks.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::{error, warn};
+
+/// Captures are serialized through the clipboard anyway, so two workers are
+/// enough to let a slow provider call overlap with the next capture.
+const WORKERS: usize = 2;
+/// Presses beyond this many waiting tasks are rejected instead of queued.
+const QUEUE_CAPACITY: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+pub struct Pool {
+  sender: SyncSender<Job>,
+}
+
+impl Default for Pool {
+  fn default() -> Self {
+    Self::new(WORKERS, QUEUE_CAPACITY)
+  }
+}
+
+impl Pool {
+  pub fn new(workers: usize, queue_capacity: usize) -> Self {
+    let (sender, receiver) = mpsc::sync_channel::<Job>(queue_capacity);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for index in 0..workers {
+      let receiver = Arc::clone(&receiver);
+      let spawned = thread::Builder::new()
+        .name(format!("shortcut-worker-{index}"))
+        .spawn(move || work(&receiver));
+      if let Err(error) = spawned {
+        error!(%error, index, "failed to spawn shortcut worker");
+      }
+    }
+
+    Self { sender }
+  }
+
+  /// Queue `job`, or return an error if the pool is saturated.
+  pub fn submit(&self, job: impl FnOnce() + Send + 'static) -> Result<(), String> {
+    self.sender.try_send(Box::new(job)).map_err(|error| match error {
+      TrySendError::Full(_) => "Too many shortcut tasks are already waiting".to_string(),
+      TrySendError::Disconnected(_) => "Shortcut workers are not running".to_string(),
+    })
+  }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+  loop {
+    // The lock is only held while waiting, not while the job runs.
+    let job = match receiver.lock() {
+      Ok(receiver) => receiver.recv(),
+      Err(_) => return,
+    };
+    let Ok(job) = job else {
+      return;
+    };
+
+    // A panicking task must not take the worker down with it.
+    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+      warn!("shortcut task panicked");
+    }
+  }
+}
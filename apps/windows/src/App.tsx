@@ -20,6 +20,7 @@ import {
   writeClipboardText,
   type Action,
   type PermissionStatus,
+  type SetupPayload,
   type Provider,
 } from "./platform";
 import { t, type AppLanguage } from "./i18n";
@@ -42,7 +43,14 @@ type PopupPhase =
   | { phase: "idle" }
   | { phase: "captured"; text: string }
   | { phase: "running"; text: string; actionId: string }
-  | { phase: "result"; originalText: string; result: string; actionName: string }
+  | {
+      phase: "result";
+      originalText: string;
+      result: string;
+      actionId: string;
+      actionName: string;
+      executionId: string;
+    }
   | { phase: "error"; originalText: string; message: string };
 
 // ---------------------------------------------------------------------------
@@ -53,6 +61,7 @@ interface ActionPopupProps {
   state: PopupPhase;
   actions: Action[];
   onRun: (actionId: string, text: string) => void;
+  onEditText: (text: string) => void;
   onApply: (result: string) => void;
   onCopy: (result: string) => void;
   onClose: () => void;
@@ -64,6 +73,7 @@ function ActionPopup({
   state,
   actions,
   onRun,
+  onEditText,
   onApply,
   onCopy,
   onClose,
@@ -97,9 +107,19 @@ function ActionPopup({
 
         <div className="popup-section">
           <label>{tr("popupInputLabel")}</label>
-          <div className="popup-text-box">
-            {capturedText || <span className="muted">{tr("popupEmptyText")}</span>}
-          </div>
+          {state.phase === "captured" ? (
+            // Editable before running, so small tweaks don't need a re-capture.
+            <textarea
+              className="popup-text-box"
+              value={capturedText}
+              placeholder={tr("popupEmptyText")}
+              onChange={(event) => onEditText(event.target.value)}
+            />
+          ) : (
+            <div className="popup-text-box">
+              {capturedText || <span className="muted">{tr("popupEmptyText")}</span>}
+            </div>
+          )}
         </div>
 
         {!result && !errorMessage && (
@@ -184,6 +204,8 @@ export function App() {
     },
   ]);
   const [defaultActionId, setDefaultActionId] = useState<string | undefined>(undefined);
+  // Setup fields this screen doesn't edit are kept so saving doesn't drop them.
+  const [savedSetup, setSavedSetup] = useState<SetupPayload | null>(null);
   const [setupDone, setSetupDone] = useState(false);
   const [logs, setLogs] = useState<ExecutionLogEntry[]>([]);
 
@@ -231,6 +253,7 @@ export function App() {
         setApiKey(savedSetup.apiKey);
         setActions(savedSetup.actions);
         setDefaultActionId(savedSetup.defaultActionId);
+        setSavedSetup(savedSetup);
         setSetupDone(true);
       }
     }
@@ -262,6 +285,20 @@ export function App() {
     return () => { unlisten?.(); };
   }, []);
 
+  // The logs shortcut brings the window forward; scroll to the insights card.
+  const insightsRef = useRef<HTMLDivElement>(null);
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+
+    listen("show-logs", () => {
+      insightsRef.current?.scrollIntoView({ behavior: "smooth" });
+    })
+      .then((fn) => { unlisten = fn; })
+      .catch(() => {});
+
+    return () => { unlisten?.(); };
+  }, []);
+
   const firstActionId = actions[0]?.id || "";
   const stats = useMemo(() => computeActionStats(logs, firstActionId), [logs, firstActionId]);
   const firstAction = actions[0];
@@ -272,7 +309,7 @@ export function App() {
 
   const canFinish =
     permissionGranted &&
-    apiKey.trim().length > 0 &&
+    (provider === "Ollama" || apiKey.trim().length > 0) &&
     actions.length > 0 &&
     actions.every((a) => a.name.trim().length > 0 && a.prompt.trim().length > 0);
 
@@ -285,6 +322,7 @@ export function App() {
   const finishSetup = async () => {
     if (!canFinish) return;
     await saveSetup({
+      ...(savedSetup ?? {}),
       provider,
       apiKey,
       actions,
@@ -365,8 +403,8 @@ export function App() {
       success,
       errorMessage,
     };
-    const nextLogs = await appendExecutionLog(entry);
-    setLogs(nextLogs);
+    const appended = await appendExecutionLog(entry);
+    setLogs((current) => [...current, appended.entry].slice(-500));
   };
 
   // -------------------------------------------------------------------------
@@ -438,11 +476,19 @@ export function App() {
         text,
       );
       const durationMs = Date.now() - start;
+      const executionId = crypto.randomUUID();
 
-      setPopup({ phase: "result", originalText: text, result, actionName: action.name });
+      setPopup({
+        phase: "result",
+        originalText: text,
+        result,
+        actionId: action.id,
+        actionName: action.name,
+        executionId,
+      });
 
       const entry: ExecutionLogEntry = {
-        id: crypto.randomUUID(),
+        id: executionId,
         timestamp: new Date().toISOString(),
         actionId: action.id,
         actionName: action.name,
@@ -455,13 +501,15 @@ export function App() {
         success: true,
         errorMessage: null,
       };
-      const nextLogs = await appendExecutionLog(entry);
-      setLogs(nextLogs);
+      const appended = await appendExecutionLog(entry);
+      setLogs((current) => [...current, appended.entry].slice(-500));
 
-      // Update last used timestamp
+      // Update last used timestamp and run count
       setActions(
         actionsRef.current.map((a) =>
-          a.id === actionId ? { ...a, lastUsedAt: new Date().toISOString() } : a,
+          a.id === actionId
+            ? { ...a, lastUsedAt: new Date().toISOString(), runCount: (a.runCount ?? 0) + 1 }
+            : a,
         ),
       );
     } catch (error) {
@@ -484,15 +532,17 @@ export function App() {
         success: false,
         errorMessage: message,
       };
-      const nextLogs = await appendExecutionLog(entry);
-      setLogs(nextLogs);
+      const appended = await appendExecutionLog(entry);
+      setLogs((current) => [...current, appended.entry].slice(-500));
     }
   };
 
   const handleApply = async (result: string) => {
+    const actionId = popup.phase === "result" ? popup.actionId : undefined;
+    const executionId = popup.phase === "result" ? popup.executionId : undefined;
     await hideWindow();
     await new Promise((resolve) => setTimeout(resolve, 200));
-    await pasteText(result);
+    await pasteText(result, actionId, undefined, executionId);
     setPopup({ phase: "idle" });
   };
 
@@ -521,6 +571,7 @@ export function App() {
         state={popup}
         actions={actions}
         onRun={(actionId, text) => void runAction(actionId, text)}
+        onEditText={(text) => setPopup({ phase: "captured", text })}
         onApply={(result) => void handleApply(result)}
         onCopy={(result) => void handleCopyResult(result)}
         onClose={closePopup}
@@ -615,6 +666,7 @@ export function App() {
                 <option>OpenRouter</option>
                 <option>Perplexity</option>
                 <option>Groq</option>
+                <option>Ollama</option>
               </select>
               <label>{tr("apiKey")}</label>
               <input
@@ -688,7 +740,7 @@ export function App() {
             {setupDone ? <p className="ok">{tr("setupSaved")}</p> : null}
           </div>
 
-          <div className="card">
+          <div className="card" ref={insightsRef}>
             <h2>{tr("insights")}</h2>
             <div className="row buttons">
               <button onClick={() => void appendLog(true)}>{tr("runSuccess")}</button>
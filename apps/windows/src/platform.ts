@@ -3,14 +3,257 @@ import {
   readText as tauriReadClipboardText,
   writeText as tauriWriteClipboardText,
 } from "@tauri-apps/api/clipboard";
-import type { ExecutionLogEntry } from "../../../shared/core/src";
+import type { DiffSummary, ExecutionLogEntry } from "../../../shared/core/src";
 
 export interface Action {
   id: string;
   name: string;
+  /**
+   * `{{file}}` is replaced by the file open in the source app, `{{before}}` and
+   * `{{after}}` by the text around the selection; each by nothing when unknown.
+   */
   prompt: string;
   createdAt: string;
   lastUsedAt?: string;
+  /** Successful runs. Kept by the backend; values sent in a save are ignored. */
+  runCount?: number;
+  /** Fail over to `fallbackProviders` when the primary provider is down. */
+  useFallback?: boolean;
+  /** Put the result on the clipboard instead of pasting it; see `outputDestination`. */
+  copyOnly?: boolean;
+  /** Where results go; unset, `copyOnly` picks between paste and clipboard. */
+  outputDestination?: OutputDestination | null;
+  /** Absolute path results are written to (replaced) with the `file` destination. */
+  outputPath?: string | null;
+  /** Always answer in this language, e.g. "Japanese". */
+  outputLanguage?: string | null;
+  reasoningEffort?: ReasoningEffort | null;
+  /** Tags sent with this action's requests (OpenAI only). */
+  metadata?: Record<string, string>;
+  /** Fixed sampling seed (OpenAI, OpenRouter, Groq). */
+  seed?: number | null;
+  /** Shown in the favorites bar and the tray menu, in list order. */
+  pinned?: boolean;
+  /** Reuse a recent output when run again on the same text. */
+  cacheResponses?: boolean;
+  /** Stop sequences: at most 4 (OpenAI, OpenRouter, Groq) or 8 (Anthropic). */
+  stop?: string[];
+  /** Set on actions installed from a preset. */
+  presetId?: string | null;
+  /** Overrides `requestTimeoutMs` for this action; 1000-300000. */
+  timeoutMs?: number | null;
+  /** Reformat the text locally; without `sendTransformed` no provider is called. */
+  localTransform?: LocalTransform | null;
+  /** Send the transformed text to the provider instead of using it as the result. */
+  sendTransformed?: boolean;
+  /** Key of `parameterPresets` in setup; validated on save. */
+  parameterPreset?: string | null;
+  /** On a context-length error, retry once with the input cut down to fit. */
+  truncateOnContextOverflow?: boolean;
+  /** Local tools the model may call before answering (native runs only). */
+  tools?: Tool[];
+  /**
+   * Press Ctrl+A before copying to run on the whole document. Replaces the
+   * selection: only plain edit controls get it back, and pasting the result
+   * replaces the whole document unless `copyOnly` is set. Not applied to
+   * picker captures, whose action is chosen afterwards.
+   */
+  selectAllFirst?: boolean;
+  /** Asked before every run; the run waits for `confirmRun` (60s at most). */
+  confirmMessage?: string | null;
+  /** Sent instead of Ctrl+C when capturing for this action, e.g. "Ctrl+Shift+C". */
+  copyKeys?: string | null;
+  /** Sent instead of Ctrl+V when pasting this action's results, e.g. "Shift+Insert". */
+  pasteKeys?: string | null;
+  /** Log how many characters each run added and removed; see `diffText`. */
+  logDiff?: boolean;
+}
+
+/**
+ * Where an action's results go after a native run (`runAction`, the
+ * shortcut). The backend delivers `clipboard` and `file` results itself;
+ * `paste` and `display` are left to the caller. Runs the window makes
+ * through `callAI` always show their result.
+ */
+export type OutputDestination = "paste" | "clipboard" | "display" | "file";
+
+/** Built-in tools an action can allow. */
+export type Tool = "calculator" | "currentTime";
+
+/** Named sampling parameters; unset fields keep the provider defaults. */
+export interface ModelParameters {
+  /** 0-2; capped at 1 for Anthropic. */
+  temperature?: number | null;
+  /** 0-1. */
+  topP?: number | null;
+  maxTokens?: number | null;
+}
+
+export type LocalTransform =
+  | "quote"
+  | "uppercase"
+  | "lowercase"
+  | "titlecase"
+  | "trim"
+  | "jsonPretty"
+  /** `replacement` may use `$1` or `${name}` for capture groups. */
+  | { regexReplace: { pattern: string; replacement: string } };
+
+export type ReasoningEffort = "low" | "medium" | "high";
+
+/** OpenAI only; `responses` drops seeds and stop sequences, which it doesn't take. */
+export type OpenAIEndpoint = "chatCompletions" | "responses";
+
+export interface ProviderSettings {
+  /** Extra request headers; Authorization, Content-Type and x-api-key are rejected. */
+  customHeaders?: Record<string, string>;
+  /** Defaults to `chatCompletions`. */
+  openaiEndpoint?: OpenAIEndpoint;
+  /** OpenAI only: sent as `OpenAI-Organization`; omitted when blank. */
+  openaiOrganization?: string | null;
+  /** OpenAI only: sent as `OpenAI-Project`; omitted when blank. */
+  openaiProject?: string | null;
+  /**
+   * Anthropic only: read answers as they stream in; actions with tools don't
+   * stream. Ollama always streams. Streamed text arrives in `action-chunk`
+   * events; `action-done` still carries the whole output.
+   */
+  stream?: boolean;
+}
+
+export interface ProviderConfig {
+  provider: string;
+  model?: string | null;
+}
+
+export type ShortcutModifier =
+  | { kind: "control" }
+  | { kind: "meta" }
+  | { kind: "alt" }
+  | { kind: "custom"; keyCode: number };
+
+export type ShortcutMode = "openPicker" | "runDefaultSilently";
+
+export type PickerOverrideModifier = "shift" | "ctrl" | "alt";
+
+export type CompletionNotice = "focus" | "showWithoutFocus" | "flash";
+
+/**
+ * `direct` replaces the focused control's selection without the clipboard
+ * where it can (classic edit controls, or UI Automation when the whole value
+ * is selected) and falls back to `clipboard`.
+ */
+export type PasteMethod = "clipboard" | "direct";
+
+/** Payload of the `text-pasted` event. */
+export interface TextPasted {
+  method: PasteMethod;
+  chars: number;
+}
+
+/**
+ * Which failures are resent to the same provider. `safeOnly` resends only
+ * requests the provider didn't process: connection failures, 429, 503 and
+ * 529. Fallback providers are tried on any outage regardless.
+ */
+export type RetryPolicy = "safeOnly" | "anyOutage" | "never";
+
+export interface Preferences {
+  copyPasteModifier?: ShortcutModifier;
+  usePhysicalKeys?: boolean;
+  confirmPasteOverChars?: number;
+  confirmPasteTimeoutMs?: number;
+  pasteMethod?: PasteMethod;
+  shortcutMode?: ShortcutMode;
+  /** Held with the main shortcut to force the picker; null disables it. */
+  pickerOverrideModifier?: PickerOverrideModifier | null;
+  completionNotice?: CompletionNotice;
+  logLevel?: string;
+  /** How long to wait for the foreground app to answer the simulated copy. */
+  captureMaxWaitMs?: number;
+  /**
+   * Put a placeholder on the clipboard before the simulated copy (default).
+   * Off, the copy is noticed by the clipboard changing, which clipboard
+   * managers don't record.
+   */
+  captureClearClipboard?: boolean;
+  /** Show a desktop notification when a background run finishes. */
+  notifyOnComplete?: boolean;
+  /** Connect to the provider at launch to speed up the first run. */
+  warmUpOnLaunch?: boolean;
+  /** Hide the window this long after an auto-paste run; null keeps it up. */
+  autoHideAfterPasteMs?: number | null;
+  /** Release the main shortcut after this many idle minutes; showing the window restores it. */
+  releaseShortcutAfterIdleMins?: number | null;
+  /** Outputs kept for caching actions; 0 disables the cache. */
+  responseCacheSize?: number;
+  responseCacheTtlSecs?: number;
+  /** Closing the window hides it to the tray instead of quitting. */
+  closeToTray?: boolean;
+  /** Keep run inputs in the log so entries can be replayed. */
+  logInputs?: boolean;
+  /** Regexes whose matches in logged prompts and inputs become `[REDACTED]`. */
+  logRedactionPatterns?: string[];
+  /** Store the execution log without indentation to keep the file small. */
+  compactLogs?: boolean;
+  /** Past this many bytes the log moves to `logs-archive/`; 0 trims it to 500 entries. */
+  logArchiveBytes?: number;
+  /** Archives kept before the oldest is deleted; 0 keeps them all. */
+  logArchiveCount?: number;
+  /** Provider requests over this many bytes are refused; 0 disables the limit. */
+  maxRequestBytes?: number;
+  /** Captures over this many characters emit `large-input-warning` and never run silently. */
+  largeInputWarningChars?: number;
+  /** How long a provider request may take before it is abandoned. */
+  requestTimeoutMs?: number;
+  /** Which failures are resent to the same provider; `anyOutage` may run a request twice. */
+  retryPolicy?: RetryPolicy;
+  /** Resends to the same provider the policy allows before falling back; 0-5. */
+  requestRetries?: number;
+  /** Holding the main shortcut this long runs the default action; a tap opens the picker. */
+  holdToCaptureMs?: number | null;
+  /** Send anonymized run counts and error categories, never text. Off until opted in. */
+  telemetryEnabled?: boolean;
+  /** HTTPS endpoint telemetry batches are posted to; required when enabled. */
+  telemetryEndpoint?: string | null;
+  /**
+   * Experimental: emit `clipboard-changed` and offer an action whenever new
+   * text is copied. Reads everything the user copies, so it is off by default.
+   */
+  clipboardWatchEnabled?: boolean;
+  /** How often the clipboard watch polls, 250 to 60000 ms. */
+  clipboardWatchIntervalMs?: number;
+}
+
+export type AutoPasteOutcome = "pasted" | "awaitingConfirmation";
+
+export interface PasteConfirmRequest {
+  executionId: string;
+  characters: number;
+}
+
+/** Payload of `capture-timed-out`; suggest raising `captureMaxWaitMs`. */
+export interface CaptureTimedOut {
+  waitedMs: number;
+}
+
+/** Payload of `action-last-used`; update the action so a later save keeps the time. */
+export interface ActionLastUsed {
+  actionId: string;
+  lastUsedAt: string;
+  /** Whether the run added one to `runCount`. */
+  succeeded: boolean;
+}
+
+/** Payload of `capture-failed`; keyboard simulation could not send the copy. */
+export interface CaptureFailed {
+  error: string;
+}
+
+/** Payload of `large-input-warning`; confirm before running on the capture. */
+export interface LargeInputWarning {
+  chars: number;
+  threshold: number;
 }
 
 export interface SetupPayload {
@@ -19,6 +262,16 @@ export interface SetupPayload {
   actions: Action[];
   defaultActionId?: string;
   setupCompletedAt: string;
+  preferences?: Preferences;
+  fallbackProviders?: ProviderConfig[];
+  /** Keyed by provider name. */
+  providerSettings?: Record<string, ProviderSettings>;
+  /** Keyed by preset name, e.g. "creative" or "precise". */
+  parameterPresets?: Record<string, ModelParameters>;
+  /** Opt-in: send an anonymous, stable user id with provider requests. */
+  sendUserId?: boolean;
+  /** Generated by the backend on opt-in. */
+  userId?: string | null;
 }
 
 // Legacy format (backward compatibility)
@@ -30,9 +283,22 @@ interface LegacySetupPayload {
   setupCompletedAt: string;
 }
 
+export interface PermissionCheck {
+  ready: boolean;
+  /** How to fix the capability; only present when it isn't ready. */
+  hint?: string | null;
+}
+
 export interface PermissionStatus {
   globalShortcutReady: boolean;
   clipboardReady: boolean;
+  globalShortcut?: PermissionCheck;
+  clipboardRead?: PermissionCheck;
+  clipboardWrite?: PermissionCheck;
+  inputSimulation?: PermissionCheck;
+  keyring?: PermissionCheck;
+  /** Not ready when settings live in a fallback directory and may not persist. */
+  dataDir?: PermissionCheck;
   note?: string;
 }
 
@@ -122,6 +388,20 @@ export async function saveSetup(setup: SetupPayload): Promise<void> {
   localStorage.setItem(SETUP_KEY, JSON.stringify(setup));
 }
 
+/**
+ * Save setup for edits made as the user types: validated now, written once
+ * the edits pause, and the keyring only touched when the key changed. Write
+ * failures arrive as `setup-save-failed`.
+ */
+export async function saveSetupDebounced(setup: SetupPayload): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("save_setup_debounced", { setup });
+    return;
+  }
+
+  localStorage.setItem(SETUP_KEY, JSON.stringify(setup));
+}
+
 export async function loadExecutionLogs(): Promise<ExecutionLogEntry[]> {
   if (isTauriRuntime()) {
     try {
@@ -134,12 +414,123 @@ export async function loadExecutionLogs(): Promise<ExecutionLogEntry[]> {
   return parseJson<ExecutionLogEntry[]>(localStorage.getItem(LOGS_KEY)) ?? [];
 }
 
+export interface ExecutionLogPage {
+  entries: ExecutionLogEntry[];
+  offset: number;
+  total: number;
+}
+
+/** A slice of the log, oldest entry first, without loading the whole history. */
+export async function loadExecutionLogsPage(
+  offset: number,
+  limit: number,
+): Promise<ExecutionLogPage> {
+  if (isTauriRuntime()) {
+    try {
+      return await invoke<ExecutionLogPage>("load_execution_logs_page", { offset, limit });
+    } catch {
+      return { entries: [], offset, total: 0 };
+    }
+  }
+
+  const logs = parseJson<ExecutionLogEntry[]>(localStorage.getItem(LOGS_KEY)) ?? [];
+  const start = Math.min(offset, logs.length);
+  return { entries: logs.slice(start, start + limit), offset: start, total: logs.length };
+}
+
+/** Write the log to `path` as JSON Lines, one entry per line; returns the count. */
+export async function exportLogsNdjson(path: string): Promise<number> {
+  return invoke<number>("export_logs_ndjson", { path });
+}
+
+/** Entries mentioning `query` in their action name, prompt, or error; best first. */
+export async function searchLogs(query: string): Promise<ExecutionLogEntry[]> {
+  if (isTauriRuntime()) {
+    return invoke<ExecutionLogEntry[]>("search_logs", { query });
+  }
+
+  const needle = query.trim().toLowerCase();
+  if (!needle) {
+    return [];
+  }
+  const logs = parseJson<ExecutionLogEntry[]>(localStorage.getItem(LOGS_KEY)) ?? [];
+  return logs
+    .filter((entry) =>
+      [entry.actionName, entry.prompt, entry.errorMessage ?? ""].some((field) =>
+        field.toLowerCase().includes(needle),
+      ),
+    )
+    .reverse()
+    .slice(0, 50);
+}
+
+export interface ActionLogSummary {
+  count: number;
+  lastRunAt: string | null;
+  /** Share of successful runs, from 0 to 1. */
+  successRate: number;
+  /** The action's lifetime `runCount`; null for deleted actions. */
+  runCount: number | null;
+}
+
+export interface ActionLogGroup {
+  summary: ActionLogSummary;
+  entries: ExecutionLogEntry[];
+}
+
+/** The log grouped by action id, each group oldest entry first. */
+export async function logsGroupedByAction(): Promise<Record<string, ActionLogGroup>> {
+  if (isTauriRuntime()) {
+    return invoke<Record<string, ActionLogGroup>>("logs_grouped_by_action");
+  }
+
+  const logs = parseJson<ExecutionLogEntry[]>(localStorage.getItem(LOGS_KEY)) ?? [];
+  const groups: Record<string, ActionLogGroup> = {};
+  for (const entry of logs) {
+    const group = (groups[entry.actionId] ??= {
+      summary: { count: 0, lastRunAt: null, successRate: 0 },
+      entries: [],
+    });
+    group.entries.push(entry);
+  }
+  for (const group of Object.values(groups)) {
+    const succeeded = group.entries.filter((entry) => entry.success).length;
+    group.summary = {
+      count: group.entries.length,
+      lastRunAt: group.entries[group.entries.length - 1].timestamp,
+      successRate: succeeded / group.entries.length,
+    };
+  }
+  return groups;
+}
+
+export interface LogRepairReport {
+  recovered: number;
+  lost: number;
+  backupPath: string | null;
+}
+
+/** Salvage readable entries from a corrupt log file and rewrite it. */
+export async function repairLogs(): Promise<LogRepairReport> {
+  if (isTauriRuntime()) {
+    return invoke<LogRepairReport>("repair_logs");
+  }
+
+  const logs = parseJson<ExecutionLogEntry[]>(localStorage.getItem(LOGS_KEY)) ?? [];
+  return { recovered: logs.length, lost: 0, backupPath: null };
+}
+
+export interface AppendedExecutionLog {
+  entry: ExecutionLogEntry;
+  total: number;
+}
+
 export async function appendExecutionLog(
   entry: ExecutionLogEntry,
-): Promise<ExecutionLogEntry[]> {
+): Promise<AppendedExecutionLog> {
   if (isTauriRuntime()) {
     try {
-      return await invoke<ExecutionLogEntry[]>("append_execution_log", { entry });
+      return await invoke<AppendedExecutionLog>("append_execution_log", { entry });
     } catch {
       // Fallback to browser storage.
     }
@@ -147,7 +538,72 @@ export async function appendExecutionLog(
 
   const logs = [...(await loadExecutionLogs()), entry].slice(-500);
   localStorage.setItem(LOGS_KEY, JSON.stringify(logs));
-  return logs;
+  return { entry, total: logs.length };
+}
+
+/** Store the API key for a fallback provider in the native keyring. */
+export async function saveProviderApiKey(provider: string, apiKey: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("save_provider_api_key", { provider, apiKey });
+  }
+}
+
+/** Whether a key is stored for `provider`; the key itself is never returned. */
+export async function hasApiKey(provider: string): Promise<boolean> {
+  if (isTauriRuntime()) {
+    return invoke<boolean>("has_api_key", { provider });
+  }
+  return false;
+}
+
+/** Result of `providerCapabilities`. */
+export interface ProviderCapabilities {
+  /** The provider API streams; native runs stream from Ollama and Anthropic. */
+  streaming: boolean;
+  vision: boolean;
+  jsonMode: boolean;
+  /** Most stop sequences per request; null when unsupported. */
+  stopSequences: number | null;
+  seed: boolean;
+  reasoning: boolean;
+  /** Temperature and top-p from parameter presets take effect. */
+  temperature: boolean;
+  metadata: boolean;
+  userId: boolean;
+  /** Actions' `tools` take effect; Anthropic models can't use them while reasoning. */
+  tools: boolean;
+}
+
+/**
+ * Why `apiKey` obviously isn't a key for `provider` (wrong prefix, stray
+ * spaces), or null if it looks fine. Checks the format only, offline.
+ */
+export async function checkApiKeyFormat(provider: string, apiKey: string): Promise<string | null> {
+  if (isTauriRuntime()) {
+    return invoke<string | null>("check_api_key_format", { provider, apiKey });
+  }
+  return null;
+}
+
+/** What the provider supports for `model` (its default model when omitted). */
+export async function providerCapabilities(
+  provider: string,
+  model?: string,
+): Promise<ProviderCapabilities | null> {
+  if (isTauriRuntime()) {
+    return invoke<ProviderCapabilities>("provider_capabilities", { provider, model });
+  }
+  return null;
+}
+
+/**
+ * Delete the stored key for `provider`.  With `clearProvider`, clearing the
+ * primary provider's key also blanks the provider in setup.
+ */
+export async function clearApiKey(provider: string, clearProvider = false): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("clear_api_key", { provider, clearProvider });
+  }
 }
 
 export async function registerGlobalShortcut(shortcut: string): Promise<void> {
@@ -162,6 +618,282 @@ export async function unregisterGlobalShortcut(): Promise<void> {
   }
 }
 
+export interface RegisteredShortcuts {
+  main: string | null;
+  /** False while the main shortcut is released for idleness. */
+  mainActive: boolean;
+  pickerOverride: string | null;
+  logs: string | null;
+  /** Accelerators keyed by action id. */
+  actions: Record<string, string>;
+}
+
+/** Everything ShortcutAI currently holds, for debugging stuck hotkeys. */
+export async function listRegisteredShortcuts(): Promise<RegisteredShortcuts | null> {
+  if (isTauriRuntime()) {
+    return invoke<RegisteredShortcuts>("list_registered_shortcuts");
+  }
+  return null;
+}
+
+/** Drop every global shortcut and forget them; register the configured ones again after. */
+export async function unregisterAllShortcuts(): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("unregister_all_shortcuts");
+  }
+}
+
+/** Register the main shortcut afresh; resolves to false if none is set. */
+export async function reassertShortcut(): Promise<boolean> {
+  if (isTauriRuntime()) {
+    return invoke<boolean>("reassert_shortcut");
+  }
+  return false;
+}
+
+/** One action as shareable JSON; API keys and provider settings are never included. */
+export async function exportAction(actionId: string): Promise<string> {
+  return invoke<string>("export_action", { actionId });
+}
+
+/** Add an action from `exportAction` output; it gets a fresh id. */
+export async function importAction(json: string): Promise<Action> {
+  return invoke<Action>("import_action", { json });
+}
+
+export type PresetId = "summarize" | "translate" | "fix-grammar" | "explain";
+
+/** Add starter actions (all presets by default); already installed ones are skipped. */
+export async function installPresetActions(presetIds?: PresetId[]): Promise<Action[]> {
+  return invoke<Action[]>("install_preset_actions", { presetIds: presetIds ?? null });
+}
+
+/** Pinned actions from the saved setup, in pin order. */
+export async function listPinnedActions(): Promise<Action[]> {
+  if (isTauriRuntime()) {
+    return invoke<Action[]>("list_pinned_actions");
+  }
+  return [];
+}
+
+export type ContentKind = "url" | "email" | "code" | "prose" | "other";
+
+export type Script =
+  | "latin"
+  | "cyrillic"
+  | "greek"
+  | "arabic"
+  | "hebrew"
+  | "devanagari"
+  | "thai"
+  | "hangul"
+  | "kana"
+  | "han";
+
+/** Best-effort guess at what a capture is; also logged with each run. */
+export interface ContentClass {
+  kind: ContentKind;
+  script: Script | null;
+}
+
+/** Payload of the `action-shortcut-triggered` event. */
+export interface ActionShortcutTriggered {
+  actionId: string;
+  /** Null when the capture was blank. */
+  content: ContentClass | null;
+  text: string;
+}
+
+/** Result of `analyzeText`. CJK characters count as one word each. */
+export interface TextStats {
+  chars: number;
+  charsWithoutSpaces: number;
+  words: number;
+  lines: number;
+  readingTimeSecs: number;
+}
+
+/** Count characters, words and lines locally, without calling a provider. */
+export async function analyzeText(text: string): Promise<TextStats> {
+  if (isTauriRuntime()) {
+    return invoke<TextStats>("analyze_text", { text });
+  }
+  const chars = [...text];
+  const words = text.match(/[\p{L}\p{N}]+(?:['’-][\p{L}\p{N}]+)*/gu)?.length ?? 0;
+  return {
+    chars: chars.length,
+    charsWithoutSpaces: chars.filter((c) => !/\s/.test(c)).length,
+    words,
+    lines: text ? text.split(/\r?\n/).length - (text.endsWith("\n") ? 1 : 0) : 0,
+    readingTimeSecs: Math.ceil((words / 238) * 60),
+  };
+}
+
+export async function classifyText(text: string): Promise<ContentClass | null> {
+  if (isTauriRuntime()) {
+    return invoke<ContentClass | null>("classify_text", { text });
+  }
+  return null;
+}
+
+export interface DiffSpan {
+  kind: "unchanged" | "removed" | "added";
+  text: string;
+}
+
+/** Word-level diff; removals come before the additions that replace them. */
+export interface TextDiff {
+  spans: DiffSpan[];
+  summary: DiffSummary;
+}
+
+export async function diffText(before: string, after: string): Promise<TextDiff> {
+  if (isTauriRuntime()) {
+    return invoke<TextDiff>("diff_text", { before, after });
+  }
+  if (before === after) {
+    const spans: DiffSpan[] = before ? [{ kind: "unchanged", text: before }] : [];
+    return { spans, summary: { charsAdded: 0, charsRemoved: 0 } };
+  }
+  const spans: DiffSpan[] = [];
+  if (before) spans.push({ kind: "removed", text: before });
+  if (after) spans.push({ kind: "added", text: after });
+  return { spans, summary: { charsAdded: after.length, charsRemoved: before.length } };
+}
+
+export async function registerActionShortcut(actionId: string, shortcut: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("register_action_shortcut", { actionId, shortcut });
+  }
+}
+
+export async function unregisterActionShortcut(actionId: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("unregister_action_shortcut", { actionId });
+  }
+}
+
+/** The accelerator bound to an action, or null when it has none. */
+export async function getActionShortcut(actionId: string): Promise<string | null> {
+  if (isTauriRuntime()) {
+    return invoke<string | null>("get_action_shortcut", { actionId });
+  }
+  return null;
+}
+
+/** Bind a shortcut that shows the window on the log; it emits `show-logs`. */
+export async function registerLogsShortcut(shortcut: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("register_logs_shortcut", { shortcut });
+  }
+}
+
+export async function unregisterLogsShortcut(): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("unregister_logs_shortcut");
+  }
+}
+
+export interface TimingStats {
+  minMs: number;
+  avgMs: number;
+  maxMs: number;
+}
+
+export interface RoundtripBenchmark {
+  iterations: number;
+  failures: number;
+  capture: TimingStats | null;
+  roundtrip: TimingStats | null;
+}
+
+/**
+ * Paste `sampleText` into the focused text field and copy it back a few
+ * times, to pick capture delays from real timings. Replaces the field's text.
+ */
+export async function benchmarkRoundtrip(
+  sampleText: string,
+  iterations?: number,
+): Promise<RoundtripBenchmark> {
+  return invoke<RoundtripBenchmark>("benchmark_roundtrip", { sampleText, iterations });
+}
+
+export interface RestoreCase {
+  name: string;
+  passed: boolean;
+  expected: string;
+  /** Null when the clipboard held no readable text afterwards. */
+  observed: string | null;
+  error: string | null;
+}
+
+export interface RestoreCheck {
+  passed: boolean;
+  cases: RestoreCase[];
+}
+
+/**
+ * Run full captures over a few clipboard contents (short, long, emoji,
+ * multiline) and check each was restored exactly.
+ */
+export async function checkClipboardRestore(): Promise<RestoreCheck> {
+  return invoke<RestoreCheck>("check_clipboard_restore");
+}
+
+export interface SelfTestCheck {
+  passed: boolean;
+  durationMs: number | null;
+  error: string | null;
+}
+
+export interface SelfTestReport {
+  passed: boolean;
+  clipboardWrite: SelfTestCheck;
+  clipboardRead: SelfTestCheck;
+  keySimulation: SelfTestCheck;
+  capture: SelfTestCheck;
+  paste: SelfTestCheck;
+}
+
+/**
+ * Check the clipboard, keyboard simulation and a timed capture and paste
+ * against an off-screen scratch buffer. Needs no provider.
+ */
+export async function runSelfTest(): Promise<SelfTestReport> {
+  return invoke<SelfTestReport>("run_self_test");
+}
+
+export interface ProfileIndex {
+  currentProfile: string;
+  profiles: string[];
+}
+
+const BROWSER_PROFILES: ProfileIndex = { currentProfile: "default", profiles: ["default"] };
+
+export async function listProfiles(): Promise<ProfileIndex> {
+  if (isTauriRuntime()) {
+    return invoke<ProfileIndex>("list_profiles");
+  }
+  return BROWSER_PROFILES;
+}
+
+/** Add a profile, optionally copying the active profile's setup (never its keys). */
+export async function createProfile(name: string, copyCurrent = false): Promise<ProfileIndex> {
+  return invoke<ProfileIndex>("create_profile", { name, copyCurrent });
+}
+
+/**
+ * Activate a profile. The backend emits `profile-switched`; reload setup and
+ * rebind action shortcuts when it arrives.
+ */
+export async function switchProfile(name: string): Promise<void> {
+  await invoke("switch_profile", { name });
+}
+
+export async function deleteProfile(name: string): Promise<ProfileIndex> {
+  return invoke<ProfileIndex>("delete_profile", { name });
+}
+
 export async function readClipboardText(): Promise<string> {
   if (isTauriRuntime()) {
     return (await tauriReadClipboardText()) ?? "";
@@ -185,21 +917,319 @@ export async function writeClipboardText(text: string): Promise<void> {
   }
 }
 
+/** Put text on the clipboard without simulating a paste. */
+export async function copyToClipboard(text: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("copy_to_clipboard", { text });
+    return;
+  }
+
+  await writeClipboardText(text);
+}
+
+/** Keep the window up after an auto-paste run; focusing the window does the same. */
+export async function cancelAutoHide(): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("cancel_auto_hide");
+  }
+}
+
+export interface SetupCheck {
+  passed: boolean;
+  message: string;
+}
+
+/** Result of `validateSetup`; block onboarding completion until `ready`. */
+export interface SetupReport {
+  ready: boolean;
+  setup: SetupCheck;
+  apiKey: SetupCheck;
+  actions: SetupCheck;
+  defaultAction: SetupCheck;
+  shortcut: SetupCheck;
+}
+
+/**
+ * Check the saved setup end to end: key accepted, actions present, default
+ * action resolvable, and `shortcut` (or the registered one) usable.
+ */
+export async function validateSetup(shortcut?: string): Promise<SetupReport | null> {
+  if (isTauriRuntime()) {
+    return invoke<SetupReport>("validate_setup", { shortcut });
+  }
+  return null;
+}
+
+/** Whether ShortcutAI starts at Windows sign-in, as the registry has it. */
+export async function getLaunchAtStartup(): Promise<boolean> {
+  if (isTauriRuntime()) {
+    return invoke<boolean>("get_launch_at_startup");
+  }
+  return false;
+}
+
+/** Start (or stop starting) ShortcutAI at sign-in; rejects with a readable reason. */
+export async function setLaunchAtStartup(enabled: boolean): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("set_launch_at_startup", { enabled });
+  }
+}
+
+/** Result of `queryLogArchives`: totals over the archived and current logs. */
+export interface LogHistoryStats {
+  totalRuns: number;
+  successfulRuns: number;
+  failedRuns: number;
+  successRate: number;
+  averageDurationMs: number;
+  firstRunAt: string | null;
+  lastRunAt: string | null;
+  byAction: Record<string, ActionLogSummary>;
+  archives: number;
+  unreadableArchives: number;
+}
+
+/** Long-term stats across log archives; `since`/`until` are inclusive RFC 3339 times. */
+export async function queryLogArchives(filter?: {
+  actionId?: string;
+  since?: string;
+  until?: string;
+}): Promise<LogHistoryStats | null> {
+  if (isTauriRuntime()) {
+    return invoke<LogHistoryStats>("query_log_archives", { ...filter });
+  }
+  return null;
+}
+
+/** Actions with near-identical prompts, for the cleanup view. */
+export interface DuplicateGroup {
+  actions: { id: string; name: string }[];
+  /** Weakest prompt similarity that linked the group, 0-1; 1 is identical. */
+  similarity: number;
+}
+
+/** Likely duplicate actions by prompt similarity; changes nothing. */
+export async function findDuplicateActions(threshold?: number): Promise<DuplicateGroup[]> {
+  if (isTauriRuntime()) {
+    return invoke<DuplicateGroup[]>("find_duplicate_actions", { threshold });
+  }
+  return [];
+}
+
+/** Actions by `runCount`, most used first; ties go to the more recently used. */
+export async function mostUsedActions(limit?: number): Promise<Action[]> {
+  if (isTauriRuntime()) {
+    return invoke<Action[]>("most_used_actions", { limit });
+  }
+  return [];
+}
+
+/** A provider response as received, with the API key redacted. */
+export interface RawResponse {
+  provider: string;
+  model: string;
+  receivedAt: string;
+  status: number;
+  /** Name and value pairs, without cookies or credentials. */
+  headers: [string, string][];
+  body: string;
+  /** The body was cut at 256 KiB. */
+  truncated: boolean;
+}
+
+/** The last provider response; only kept while debug logging is on. */
+export async function getLastRawResponse(): Promise<RawResponse | null> {
+  if (isTauriRuntime()) {
+    return invoke<RawResponse | null>("get_last_raw_response");
+  }
+  return null;
+}
+
+/** Result of `getResourceStats`; byte counts are approximate. */
+export interface ResourceStats {
+  logEntries: number;
+  logBytes: number;
+  registeredShortcuts: number;
+  responseCacheEntries: number;
+  responseCacheBytes: number;
+  /** The last capture's file name and surrounding text, and the last output. */
+  captureBytes: number;
+  pendingPastes: number;
+  runsInFlight: number;
+  /** Null outside Windows. */
+  workingSetBytes: number | null;
+  peakWorkingSetBytes: number | null;
+}
+
+/** What the app holds in memory, for troubleshooting high memory use. */
+export async function getResourceStats(): Promise<ResourceStats | null> {
+  if (isTauriRuntime()) {
+    return invoke<ResourceStats>("get_resource_stats");
+  }
+  return null;
+}
+
+/** Result of `telemetryStatus`, for the consent screen. */
+export interface TelemetryStatus {
+  enabled: boolean;
+  endpoint: string | null;
+  /** Runs counted but not sent yet. */
+  pendingRuns: number;
+  lastSentAt: string | null;
+  lastError: string | null;
+}
+
+/** Whether telemetry is on, where it is sent, and how the last send went. */
+export async function telemetryStatus(): Promise<TelemetryStatus | null> {
+  if (isTauriRuntime()) {
+    return invoke<TelemetryStatus>("telemetry_status");
+  }
+  return null;
+}
+
+/** Payload of the `clipboard-changed` event. */
+export interface ClipboardChanged {
+  text: string;
+  content: ContentClass | null;
+}
+
+/** Result of `clipboardWatchStatus`, also sent as `clipboard-watch-status`. */
+export interface ClipboardWatchStatus {
+  enabled: boolean;
+  paused: boolean;
+  intervalMs: number;
+}
+
+/** Pause or resume the clipboard watch until the app restarts. */
+export async function setClipboardWatchPaused(paused: boolean): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("set_clipboard_watch_paused", { paused });
+  }
+}
+
+export async function clipboardWatchStatus(): Promise<ClipboardWatchStatus | null> {
+  if (isTauriRuntime()) {
+    return invoke<ClipboardWatchStatus>("clipboard_watch_status");
+  }
+  return null;
+}
+
+/**
+ * Delete setup, logs and stored keys and unregister every shortcut, leaving
+ * the app as on first launch. Rejects unless `confirm` is true or while a run
+ * is in flight; emits `factory-reset` when done.
+ */
+export async function factoryReset(confirm: boolean): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("factory_reset", { confirm });
+  }
+}
+
+/**
+ * Whether restarting for an update would interrupt nothing: no native run in
+ * flight and no pending log or setup write. Always true in the browser.
+ */
+export async function canUpdate(): Promise<boolean> {
+  if (isTauriRuntime()) {
+    return invoke<boolean>("can_update");
+  }
+  return true;
+}
+
+/** Payload of `confirm-required`; answer with `confirmRun` or `declineRun`. */
+export interface ConfirmRequired {
+  executionId: string;
+  actionId: string;
+  actionName: string;
+  message: string;
+}
+
+/** Let a run waiting on its action's `confirmMessage` go ahead. */
+export async function confirmRun(executionId: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("confirm_run", { executionId });
+  }
+}
+
+/** Call off a run waiting on its action's `confirmMessage`; it fails as declined. */
+export async function declineRun(executionId: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("decline_run", { executionId });
+  }
+}
+
+/** Paste commands reject with this message when a password field has focus. */
+export const PASSWORD_FIELD_ERROR = "Refusing to paste into a password field";
+
 /**
  * Write `text` to the clipboard and simulate Ctrl+V in the previously-focused
- * application.  On Tauri, this is handled natively in Rust; in browser preview,
- * it just copies to clipboard.
+ * application, or with `targetWindow` in the first window whose title
+ * contains it (ignoring case).  With `executionId`, the method used is
+ * recorded on that run's log entry.  On Tauri, this is handled natively in
+ * Rust; in browser preview, it just copies to clipboard.
  */
-export async function pasteText(text: string): Promise<void> {
+export async function pasteText(
+  text: string,
+  actionId?: string,
+  targetWindow?: string,
+  executionId?: string,
+): Promise<PasteMethod> {
   if (isTauriRuntime()) {
-    await invoke("paste_text", { text });
-    return;
+    return invoke<PasteMethod>("paste_text", { text, actionId, targetWindow, executionId });
   }
 
   // Browser preview: just write to clipboard as a best-effort.
   if (typeof navigator !== "undefined" && navigator.clipboard) {
     await navigator.clipboard.writeText(text);
   }
+  return "clipboard";
+}
+
+/**
+ * Paste a result over the captured selection.  Large replacements are held
+ * until `confirmPaste` is called; the backend emits `paste-confirm-required`
+ * and `paste-cancelled` (on timeout) for those.
+ */
+export async function autoPaste(
+  executionId: string,
+  text: string,
+  replacedLength: number,
+  actionId?: string,
+): Promise<AutoPasteOutcome> {
+  if (isTauriRuntime()) {
+    return invoke<AutoPasteOutcome>("auto_paste", {
+      executionId,
+      text,
+      replacedLength,
+      actionId,
+    });
+  }
+
+  await pasteText(text);
+  return "pasted";
+}
+
+export async function confirmPaste(executionId: string): Promise<PasteMethod | null> {
+  if (isTauriRuntime()) {
+    return invoke<PasteMethod>("confirm_paste", { executionId });
+  }
+  return null;
+}
+
+export async function cancelPaste(executionId: string): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("cancel_paste", { executionId });
+  }
+}
+
+/**
+ * Announce a finished action.  Auto-paste completions show the window without
+ * taking focus so typing in the target application isn't interrupted.
+ */
+export async function notifyActionComplete(autoPaste: boolean): Promise<void> {
+  if (isTauriRuntime()) {
+    await invoke("notify_action_complete", { autoPaste });
+  }
 }
 
 /**
@@ -212,11 +1242,161 @@ export async function hideWindow(): Promise<void> {
   }
 }
 
+/**
+ * Tail of the native trace log, for bug reports. If tracing couldn't be set
+ * up at launch, the first line says why.
+ */
+export async function getRecentTrace(maxLines?: number): Promise<string[]> {
+  if (isTauriRuntime()) {
+    return invoke<string[]>("get_recent_trace", { maxLines });
+  }
+  return [];
+}
+
+// ---------------------------------------------------------------------------
+// Native action execution
+// ---------------------------------------------------------------------------
+
+export interface ActionRunResult {
+  executionId: string;
+  actionId: string;
+  output: string;
+  /** Null for local transforms. */
+  provider: string | null;
+  modelId: string | null;
+  durationMs: number;
+  copyOnly: boolean;
+  outputDestination: OutputDestination;
+  cached: boolean;
+}
+
+/**
+ * Payload of the `action-done` event emitted after every native run.  Long
+ * outputs arrive first as `action-output-chunk` events and `output` is null;
+ * they can also be fetched with `getLastOutput`.
+ */
+export interface ActionDone {
+  executionId: string;
+  actionId: string;
+  success: boolean;
+  output?: string | null;
+  outputLength: number;
+  outputChunked: boolean;
+  errorMessage?: string | null;
+}
+
+export interface ActionOutputChunk {
+  executionId: string;
+  index: number;
+  total: number;
+  text: string;
+}
+
+/**
+ * Payload of the `action-chunk` event: a piece of a streamed answer as it
+ * arrives. A retried or failed-over request streams again with the next
+ * `request` number, so text from earlier requests should be dropped.
+ */
+export interface ActionChunk {
+  executionId: string;
+  /** Counts the run's provider requests from 1. */
+  request: number;
+  text: string;
+}
+
+export interface LastOutput {
+  executionId: string;
+  actionId: string;
+  text: string;
+}
+
+/** Run a saved action natively; the run is logged by the backend. */
+export async function runAction(actionId: string, text: string): Promise<ActionRunResult> {
+  return invoke<ActionRunResult>("run_action", { actionId, text });
+}
+
+/** Run on the (possibly edited) text from the capture preview. */
+export async function runWithText(actionId: string, text: string): Promise<ActionRunResult> {
+  return invoke<ActionRunResult>("run_with_text", { actionId, text });
+}
+
+/** Abort the running native runs of an action; resolves to whether any was running. */
+export async function cancelAction(actionId: string): Promise<boolean> {
+  if (isTauriRuntime()) {
+    return invoke<boolean>("cancel_action", { actionId });
+  }
+  return false;
+}
+
+/** Re-run a logged execution on its logged input (needs `logInputs`). */
+export async function replayLogEntry(logId: string): Promise<ActionRunResult> {
+  return invoke<ActionRunResult>("replay_log_entry", { logId });
+}
+
+/** Run a one-off prompt on `input` without a stored action; logged as "(ad-hoc)". */
+export async function runAdhoc(prompt: string, input: string): Promise<ActionRunResult> {
+  return invoke<ActionRunResult>("run_adhoc", { prompt, input });
+}
+
+/** One provider's answer in `compareProviders`. */
+export interface ProviderComparison {
+  provider: string;
+  /** Null when the provider couldn't be called, e.g. without a stored key. */
+  modelId?: string | null;
+  output?: string | null;
+  error?: string | null;
+  durationMs: number;
+}
+
+/**
+ * Run a saved action against each of `providers` at once, to compare their
+ * outputs. Per-provider failures come back as `error`; nothing is logged.
+ */
+export async function compareProviders(
+  actionId: string,
+  input: string,
+  providers: string[],
+): Promise<ProviderComparison[]> {
+  return invoke<ProviderComparison[]>("compare_providers", { actionId, input, providers });
+}
+
+export async function getLastOutput(): Promise<LastOutput | null> {
+  if (isTauriRuntime()) {
+    return invoke<LastOutput | null>("get_last_output");
+  }
+  return null;
+}
+
+/** Run a saved action on the current clipboard text, without capturing a selection. */
+export async function runActionOnClipboard(actionId: string): Promise<ActionRunResult> {
+  return invoke<ActionRunResult>("run_action_on_clipboard", { actionId });
+}
+
+/** Where `runActionOnFile` also writes the output: over the file, or to `<stem>.output.<ext>`. */
+export type FileOutput = "overwrite" | "sibling";
+
+export interface FileRunResult extends ActionRunResult {
+  /** Path the output was written to, when `output` was given. */
+  writtenTo: string | null;
+}
+
+/**
+ * Run a saved action on a UTF-8 or UTF-16 text file of up to 2 MB; binary
+ * files are rejected. Written output keeps the file's encoding.
+ */
+export async function runActionOnFile(
+  actionId: string,
+  path: string,
+  output?: FileOutput,
+): Promise<FileRunResult> {
+  return invoke<FileRunResult>("run_action_on_file", { actionId, path, output });
+}
+
 // ---------------------------------------------------------------------------
 // AI provider integration
 // ---------------------------------------------------------------------------
 
-export type Provider = "OpenAI" | "Anthropic" | "OpenRouter" | "Perplexity" | "Groq";
+export type Provider = "OpenAI" | "Anthropic" | "OpenRouter" | "Perplexity" | "Groq" | "Ollama";
 
 interface ChatMessage {
   role: "system" | "user" | "assistant";
@@ -238,6 +1418,7 @@ const DEFAULT_MODELS: Record<Provider, string> = {
   OpenRouter: "openai/gpt-4o-mini",
   Perplexity: "llama-3.1-sonar-small-128k-online",
   Groq: "llama-3.1-8b-instant",
+  Ollama: "llama3.2",
 };
 
 async function callOpenAICompatible(
@@ -317,6 +1498,8 @@ export async function callAI(
     OpenRouter: "https://openrouter.ai/api/v1",
     Perplexity: "https://api.perplexity.ai",
     Groq: "https://api.groq.com/openai/v1",
+    // Ollama's OpenAI-compatible API; it ignores the key.
+    Ollama: "http://localhost:11434/v1",
   };
 
   const messages: ChatMessage[] = [
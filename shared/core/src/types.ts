@@ -11,6 +11,29 @@ export interface ExecutionLogEntry {
   outputLength: number;
   success: boolean;
   errorMessage?: string | null;
+  /** Tokens a reasoning model spent thinking, when the provider reports them. */
+  reasoningTokens?: number | null;
+  /** Provider backend fingerprint; seeded outputs only repeat while it matches. */
+  systemFingerprint?: string | null;
+  /** Served from the response cache without calling the provider. */
+  cached?: boolean;
+  /** The text the action ran on, when input logging is enabled. */
+  input?: string | null;
+  /** The provider didn't answer within the request timeout. */
+  timedOut?: boolean;
+  /** The input was too long for the model and a truncated copy was sent instead. */
+  truncatedRetry?: boolean;
+  /** Stopped with `cancelAction` before the provider answered. */
+  cancelled?: boolean;
+  /** How much the output changed from the input, for actions with `logDiff`. */
+  diff?: DiffSummary | null;
+  /** How the output was pasted, once it was. */
+  pasteMethod?: "clipboard" | "direct" | null;
+}
+
+export interface DiffSummary {
+  charsAdded: number;
+  charsRemoved: number;
 }
 
 export interface ActionExecutionStats {